@@ -0,0 +1,246 @@
+// Limite de espaço da biblioteca de vídeos.
+//
+// Antes de cada download `garantir_espaco` confere duas coisas: que a
+// biblioteca não passe de `biblioteca_limite_mb` (0 = sem limite) e que o
+// disco fique com pelo menos `disco_reserva_mb` livres. Se faltar espaço,
+// apaga músicas pela política `biblioteca_politica` ("menos_tocadas" ou
+// "sem_tocar", contadas em `historico_local`), nunca as fixadas nem as
+// baixadas há menos de PROTECAO_NOVAS_MS (senão uma música nova
+// expulsaria a outra em ciclo).
+//
+// As músicas apagadas ficam em `musicas_liberadas` e a fila automática não
+// as baixa de novo; pedir o código ou priorizar o download traz de volta.
+//
+// Downloads simultâneos conferem um de cada vez e cada um reserva o próprio
+// tamanho até terminar (`Reserva`): dois downloads não contam com o mesmo
+// espaço livre.
+
+use crate::db;
+use crate::midia;
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Mutex;
+
+const CHAVE_LIMITE_MB: &str = "biblioteca_limite_mb";
+const CHAVE_RESERVA_MB: &str = "disco_reserva_mb";
+const CHAVE_POLITICA: &str = "biblioteca_politica";
+
+const RESERVA_PADRAO_MB: u64 = 2048;
+const POLITICAS: [&str; 2] = ["menos_tocadas", "sem_tocar"];
+const MB: u64 = 1024 * 1024;
+
+/// Músicas baixadas há menos tempo que isso não são apagadas.
+const PROTECAO_NOVAS_MS: i64 = 7 * 24 * 60 * 60 * 1000;
+
+/// Bytes reservados pelos downloads em andamento (codigo → tamanho).
+static RESERVAS: Lazy<Mutex<HashMap<String, i64>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Espaço reservado para um download; devolvido quando sai de escopo.
+pub struct Reserva {
+    codigo: String,
+}
+
+impl Drop for Reserva {
+    fn drop(&mut self) {
+        RESERVAS.lock().unwrap().remove(&self.codigo);
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct StatusArmazenamento {
+    /// Bytes ocupados pela biblioteca
+    pub usado: i64,
+    /// 0 = sem limite
+    #[serde(rename = "limiteMb")]
+    pub limite_mb: u64,
+    #[serde(rename = "reservaMb")]
+    pub reserva_mb: u64,
+    /// Espaço livre no disco da biblioteca; None se não deu para medir
+    #[serde(rename = "livreDisco")]
+    pub livre_disco: Option<u64>,
+    pub politica: String,
+    pub fixadas: Vec<String>,
+    /// Apagadas para liberar espaço e fora da fila automática
+    pub liberadas: usize,
+}
+
+fn config_u64(chave: &str) -> Option<u64> {
+    db::get_config(chave).ok().flatten().and_then(|v| v.parse::<u64>().ok())
+}
+
+pub fn limite_mb() -> u64 {
+    config_u64(CHAVE_LIMITE_MB).unwrap_or(0)
+}
+
+pub fn reserva_mb() -> u64 {
+    config_u64(CHAVE_RESERVA_MB).unwrap_or(RESERVA_PADRAO_MB)
+}
+
+pub fn politica() -> String {
+    db::get_config(CHAVE_POLITICA)
+        .ok()
+        .flatten()
+        .filter(|p| POLITICAS.contains(&p.as_str()))
+        .unwrap_or_else(|| POLITICAS[0].to_string())
+}
+
+/// Altera só o que vier preenchido. Aumentar ou tirar o limite devolve à
+/// fila as músicas apagadas por falta de espaço.
+pub fn configurar(limite_mb: Option<u64>, reserva_mb: Option<u64>, politica: Option<&str>) -> Result<(), String> {
+    if let Some(p) = politica {
+        if !POLITICAS.contains(&p) {
+            return Err(format!("Política desconhecida: {} (use {})", p, POLITICAS.join(" ou ")));
+        }
+        db::set_config(CHAVE_POLITICA, p)?;
+    }
+    if let Some(r) = reserva_mb {
+        db::set_config(CHAVE_RESERVA_MB, &r.to_string())?;
+    }
+    if let Some(novo) = limite_mb {
+        let atual = self::limite_mb();
+        db::set_config(CHAVE_LIMITE_MB, &novo.to_string())?;
+        if novo == 0 || (atual != 0 && novo > atual) {
+            db::limpar_liberadas()?;
+        }
+    }
+    Ok(())
+}
+
+pub fn status(data_dir: &str) -> Result<StatusArmazenamento, String> {
+    Ok(StatusArmazenamento {
+        usado: db::storage_used()?,
+        limite_mb: limite_mb(),
+        reserva_mb: reserva_mb(),
+        livre_disco: espaco_livre(&Path::new(data_dir).join("musicas")),
+        politica: politica(),
+        fixadas: db::listar_fixadas()?,
+        liberadas: db::listar_liberadas()?.len(),
+    })
+}
+
+/// Espaço disponível no disco que contém `dir`.
+pub fn espaco_livre(dir: &Path) -> Option<u64> {
+    let dir = std::fs::canonicalize(dir).ok()?;
+    // No Windows canonicalize devolve \\?\C:\..., os pontos de montagem não
+    let dir = match dir.to_str().and_then(|s| s.strip_prefix(r"\\?\")) {
+        Some(s) => std::path::PathBuf::from(s),
+        None => dir,
+    };
+    let discos = sysinfo::Disks::new_with_refreshed_list();
+    discos
+        .list()
+        .iter()
+        .filter(|d| dir.starts_with(d.mount_point()))
+        .max_by_key(|d| d.mount_point().as_os_str().len())
+        .map(|d| d.available_space())
+}
+
+/// Garante espaço para baixar `codigo` (`tamanho` bytes, se conhecido),
+/// apagando músicas pela política se preciso, e reserva esse espaço enquanto
+/// a `Reserva` devolvida existir. Erro se nem apagando tudo o que é
+/// permitido couber.
+pub fn garantir_espaco(musicas_dir: &Path, codigo: &str, tamanho: Option<i64>) -> Result<Reserva, String> {
+    let tamanho = tamanho.unwrap_or(0).max(0);
+    // Conferir e reservar de uma vez só: outro download espera aqui
+    let mut reservas = RESERVAS.lock().unwrap();
+    let reservado: i64 = reservas.iter().filter(|(c, _)| c.as_str() != codigo).map(|(_, t)| *t).sum();
+    // Um novo download da mesma música substitui o arquivo atual
+    let atual = db::get_musica_local(codigo)?.and_then(|m| m.tamanho).unwrap_or(0);
+
+    let mut falta = 0;
+    let limite = limite_mb() as i64 * MB as i64;
+    if limite > 0 {
+        falta = db::storage_used()? + reservado - atual + tamanho - limite;
+    }
+    if let Some(livre) = espaco_livre(musicas_dir) {
+        falta = falta.max(reserva_mb() as i64 * MB as i64 + reservado + tamanho - livre as i64);
+    }
+    if falta > 0 {
+        liberar(falta, codigo).map_err(|disponivel| {
+            format!(
+                "Sem espaço para {}: faltam {} MB e só {} MB podem ser liberados",
+                codigo,
+                falta as u64 / MB + 1,
+                disponivel as u64 / MB
+            )
+        })?;
+    }
+    reservas.insert(codigo.to_string(), tamanho);
+    Ok(Reserva { codigo: codigo.to_string() })
+}
+
+/// Apaga a biblioteca até caber no limite configurado (ex.: depois de
+/// diminuí-lo). Apaga o que der, mesmo sem chegar ao limite.
+pub fn aplicar_limite() -> Result<Vec<String>, String> {
+    let limite = limite_mb() as i64 * MB as i64;
+    if limite == 0 {
+        return Ok(vec![]);
+    }
+    let falta = db::storage_used()? - limite;
+    if falta <= 0 {
+        return Ok(vec![]);
+    }
+    Ok(match liberar(falta, "") {
+        Ok(liberadas) => liberadas,
+        Err(_) => apagar(&candidatas("")?, i64::MAX),
+    })
+}
+
+fn candidatas(exceto: &str) -> Result<Vec<db::CandidataLiberacao>, String> {
+    let baixadas_antes = chrono::Utc::now().timestamp_millis() - PROTECAO_NOVAS_MS;
+    Ok(db::candidatas_liberacao(&politica(), baixadas_antes)?
+        .into_iter()
+        .filter(|c| c.codigo != exceto)
+        .collect())
+}
+
+/// Libera `falta` bytes. Se não der, não apaga nada e devolve quanto
+/// poderia ser liberado.
+fn liberar(falta: i64, exceto: &str) -> Result<Vec<String>, i64> {
+    let candidatas = candidatas(exceto).map_err(|e| {
+        log::error!("[ARMAZENAMENTO] {}", e);
+        0
+    })?;
+    let disponivel: i64 = candidatas.iter().map(|c| c.tamanho).sum();
+    if disponivel < falta {
+        return Err(disponivel);
+    }
+    Ok(apagar(&candidatas, falta))
+}
+
+fn apagar(candidatas: &[db::CandidataLiberacao], falta: i64) -> Vec<String> {
+    let mut liberado = 0;
+    let mut apagadas = Vec::new();
+    for c in candidatas {
+        if liberado >= falta {
+            break;
+        }
+        if let Err(e) = std::fs::remove_file(&c.arquivo) {
+            // Em uso (tocando) ou sem permissão: tenta a próxima
+            if e.kind() != std::io::ErrorKind::NotFound {
+                log::warn!("[ARMAZENAMENTO] {} não apagada: {}", c.codigo, e);
+                continue;
+            }
+        }
+        for companheiro in midia::companheiros(Path::new(&c.arquivo)) {
+            std::fs::remove_file(companheiro).ok();
+        }
+        if let Err(e) = db::remover_musica_local(&c.codigo) {
+            log::error!("[ARMAZENAMENTO] {}: {}", c.codigo, e);
+            continue;
+        }
+        db::registrar_liberada(&c.codigo, Some(c.tamanho)).ok();
+        liberado += c.tamanho;
+        apagadas.push(c.codigo.clone());
+    }
+    if !apagadas.is_empty() {
+        log::info!(
+            "[ARMAZENAMENTO] {} músicas apagadas para liberar {} MB",
+            apagadas.len(),
+            liberado as u64 / MB
+        );
+    }
+    apagadas
+}
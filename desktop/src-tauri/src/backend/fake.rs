@@ -0,0 +1,386 @@
+// Backend em memória para desenvolvimento e testes de integração.
+//
+// Os dados vêm de um JSON no formato:
+//
+//   { "musicas": [MusicaRemota...], "chaves": [ChaveRemota...],
+//     "assinaturas": [AssinaturaRemota...], "popularidade": [PopularidadeRemota...],
+//     "segredos_rede_local": { "<user_id>": "<segredo>" },
+//     "historico": [ItemHistorico...] }
+//
+// `arquivo` de cada música é um caminho local (ou file://) copiado no download.
+
+use super::{
+    timestamp_cmp, ArquivoBaixado, AssinaturaRemota, BackendError, BoxFuture, CatalogBackend, ChaveRemota,
+    Integridade, ItemHistorico, LicenseBackend, MusicaRemota, OnPagina, OnProgresso, PaginaCatalogo, PopularidadeRemota,
+};
+use crate::download::ArquivoParcial;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+#[derive(Debug, Default, Deserialize)]
+pub struct FakeData {
+    #[serde(default)]
+    pub musicas: Vec<MusicaRemota>,
+    #[serde(default)]
+    pub chaves: Vec<ChaveRemota>,
+    #[serde(default)]
+    pub assinaturas: Vec<AssinaturaRemota>,
+    #[serde(default)]
+    pub popularidade: Vec<PopularidadeRemota>,
+    /// user_id → segredo da rede local
+    #[serde(default)]
+    pub segredos_rede_local: HashMap<String, String>,
+    /// Execuções recebidas por `registrar_historico`
+    #[serde(default)]
+    pub historico: Vec<ItemHistorico>,
+}
+
+/// Tamanho de página pequeno para exercitar a paginação
+const PAGINA: usize = 50;
+
+pub struct FakeBackend {
+    data: Mutex<FakeData>,
+}
+
+impl FakeBackend {
+    pub fn new(data: FakeData) -> Self {
+        Self { data: Mutex::new(data) }
+    }
+
+    pub fn from_file(path: &str) -> Result<Self, String> {
+        let content = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+        let data: FakeData = serde_json::from_str(&content).map_err(|e| e.to_string())?;
+        Ok(Self::new(data))
+    }
+}
+
+impl CatalogBackend for FakeBackend {
+    fn fetch_musicas<'a>(
+        &'a self,
+        alteradas_desde: Option<&'a str>,
+        on_pagina: OnPagina<'a>,
+    ) -> BoxFuture<'a, Result<i64, BackendError>> {
+        Box::pin(async move {
+            let mut linhas: Vec<MusicaRemota> = {
+                let data = self.data.lock().unwrap();
+                data.musicas
+                    .iter()
+                    .filter(|m| match alteradas_desde {
+                        None => true,
+                        Some(desde) => m
+                            .updated_at
+                            .as_deref()
+                            .is_some_and(|u| timestamp_cmp(u, desde) != std::cmp::Ordering::Less),
+                    })
+                    .cloned()
+                    .collect()
+            };
+            linhas.sort_by(|a, b| timestamp_cmp(a.updated_at.as_deref().unwrap_or(""), b.updated_at.as_deref().unwrap_or("")));
+            let total = linhas.len() as i64;
+            let mut recebidas = 0;
+            for pagina in linhas.chunks(PAGINA) {
+                recebidas += pagina.len() as i64;
+                on_pagina(PaginaCatalogo { musicas: pagina.to_vec(), recebidas, total: Some(total) })?;
+            }
+            Ok(recebidas)
+        })
+    }
+
+    fn download_file<'a>(
+        &'a self,
+        url: &'a str,
+        dest: &'a str,
+        integridade: &'a Integridade,
+        progresso: OnProgresso<'a>,
+    ) -> BoxFuture<'a, Result<ArquivoBaixado, BackendError>> {
+        Box::pin(async move {
+            let src = url.strip_prefix("file://").unwrap_or(url);
+            let conteudo = std::fs::read(src).map_err(|e| BackendError::Io(format!("{}: {}", src, e)))?;
+            let mut arquivo = ArquivoParcial::criar(std::path::Path::new(dest))?;
+            arquivo.gravar(&conteudo)?;
+            progresso(arquivo.gravados(), Some(conteudo.len() as u64));
+            arquivo.concluir(integridade)
+        })
+    }
+
+    fn fetch_popularidade<'a>(&'a self, limite: i64) -> BoxFuture<'a, Result<Vec<PopularidadeRemota>, BackendError>> {
+        Box::pin(async move {
+            let mut lista = self.data.lock().unwrap().popularidade.clone();
+            lista.sort_by_key(|p| std::cmp::Reverse(p.total));
+            lista.truncate(limite.max(0) as usize);
+            Ok(lista)
+        })
+    }
+}
+
+impl LicenseBackend for FakeBackend {
+    fn buscar_chave<'a>(&'a self, chave: &'a str) -> BoxFuture<'a, Result<Option<ChaveRemota>, BackendError>> {
+        Box::pin(async move {
+            let data = self.data.lock().unwrap();
+            Ok(data.chaves.iter().find(|c| c.chave == chave).cloned())
+        })
+    }
+
+    fn check_assinatura<'a>(&'a self, user_id: &'a str) -> BoxFuture<'a, Result<Option<AssinaturaRemota>, BackendError>> {
+        Box::pin(async move {
+            let data = self.data.lock().unwrap();
+            Ok(data
+                .assinaturas
+                .iter()
+                .find(|a| a.user_id == user_id && a.status == "ativa")
+                .cloned())
+        })
+    }
+
+    fn update_ultimo_uso<'a>(&'a self, chave_id: &'a str) -> BoxFuture<'a, Result<(), BackendError>> {
+        Box::pin(async move {
+            let mut data = self.data.lock().unwrap();
+            let chave = data
+                .chaves
+                .iter_mut()
+                .find(|c| c.id == chave_id)
+                .ok_or(BackendError::Http { status: 404, corpo: String::new() })?;
+            chave.ultimo_uso = Some(chrono::Utc::now().to_rfc3339());
+            Ok(())
+        })
+    }
+
+    fn vincular_machine_id<'a>(&'a self, chave_id: &'a str, machine_id: &'a str) -> BoxFuture<'a, Result<(), BackendError>> {
+        Box::pin(async move {
+            let mut data = self.data.lock().unwrap();
+            let chave = data
+                .chaves
+                .iter_mut()
+                .find(|c| c.id == chave_id)
+                .ok_or(BackendError::Http { status: 404, corpo: String::new() })?;
+            let now = chrono::Utc::now().to_rfc3339();
+            chave.machine_id = Some(machine_id.to_string());
+            chave.ultimo_uso = Some(now);
+            Ok(())
+        })
+    }
+
+    fn segredo_rede_local<'a>(&'a self, chave: &'a str, machine_id: &'a str) -> BoxFuture<'a, Result<Option<String>, BackendError>> {
+        Box::pin(async move {
+            let data = self.data.lock().unwrap();
+            let conta = data
+                .chaves
+                .iter()
+                .find(|c| c.chave == chave && c.status == "ativa" && c.machine_id.as_deref() == Some(machine_id))
+                .and_then(|c| c.user_id.as_ref())
+                .and_then(|u| u.as_str());
+            Ok(conta.and_then(|u| data.segredos_rede_local.get(u)).cloned())
+        })
+    }
+
+    fn registrar_historico<'a>(
+        &'a self,
+        chave: &'a str,
+        machine_id: &'a str,
+        itens: &'a [ItemHistorico],
+    ) -> BoxFuture<'a, Result<Option<i64>, BackendError>> {
+        Box::pin(async move {
+            let mut data = self.data.lock().unwrap();
+            if !data.chaves.iter().any(|c| c.chave == chave && c.status == "ativa" && c.machine_id.as_deref() == Some(machine_id)) {
+                return Ok(None);
+            }
+            let novos: Vec<ItemHistorico> = itens
+                .iter()
+                .filter(|i| data.musicas.iter().any(|m| m.codigo == i.codigo))
+                .cloned()
+                .collect();
+            let gravados = novos.len() as i64;
+            data.historico.extend(novos);
+            Ok(Some(gravados))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::catalogo;
+    use crate::commands::{ativacao, historico};
+    use crate::db;
+    use crate::download::sha256_arquivo;
+    use crate::download_manager::{DownloadManager, EventoDownload, StatusDownloads};
+    use crate::rede_local;
+    use crate::teste;
+    use std::path::Path;
+    use std::sync::Arc;
+
+    fn musica(dir: &Path, codigo: &str, conteudo: &[u8], updated_at: &str) -> serde_json::Value {
+        let origem = dir.join("origem");
+        std::fs::create_dir_all(&origem).unwrap();
+        let arquivo = origem.join(format!("{}.mp4", codigo));
+        std::fs::write(&arquivo, conteudo).unwrap();
+        serde_json::json!({
+            "id": format!("id-{}", codigo),
+            "codigo": codigo,
+            "artista": "Artista",
+            "titulo": format!("Música {}", codigo),
+            "arquivo": arquivo.to_string_lossy(),
+            "nome_arquivo": null,
+            "tamanho": conteudo.len(),
+            "duracao": 180,
+            "user_id": null,
+            "created_at": updated_at,
+            "updated_at": updated_at,
+            "sha256": sha256_arquivo(&arquivo).unwrap(),
+        })
+    }
+
+    fn backend(dados: serde_json::Value) -> FakeBackend {
+        FakeBackend::new(serde_json::from_value(dados).unwrap())
+    }
+
+    /// Espera a fila chegar em `pronto` sem nada baixando (até 10s).
+    fn esperar_fila(manager: &DownloadManager, pronto: impl Fn(&StatusDownloads) -> bool) -> StatusDownloads {
+        let limite = std::time::Instant::now() + std::time::Duration::from_secs(10);
+        loop {
+            let status = manager.status();
+            if pronto(&status) && status.baixando.is_empty() {
+                return status;
+            }
+            assert!(std::time::Instant::now() < limite, "fila parada: {:?}", status);
+            std::thread::sleep(std::time::Duration::from_millis(20));
+        }
+    }
+
+    #[test]
+    fn sincroniza_e_baixa_o_catalogo() {
+        let dir = teste::dados();
+        let data_dir = dir.to_str().unwrap();
+        let fake = backend(serde_json::json!({
+            "musicas": [
+                musica(&dir, "91001", b"video um", "2026-01-01T00:00:00Z"),
+                musica(&dir, "91002", b"video dois", "2026-01-02T00:00:00Z"),
+            ],
+        }));
+
+        tauri::async_runtime::block_on(async {
+            // Completa: o banco é compartilhado com os outros testes
+            let r = catalogo::sincronizar_com(&fake, data_dir, true, true, &|_| {}).await.unwrap();
+            assert!(r.executada);
+            assert_eq!(r.recebidas, 2);
+            assert_eq!(db::get_catalogo_by_codigo("91002").unwrap().map(|m| m.id), Some("id-91002".to_string()));
+        });
+
+        // A fila é montada a partir do catálogo local e despachada pelo manager
+        let manager = DownloadManager::new(Arc::new(fake), data_dir);
+        manager.iniciar();
+        manager.retomar();
+        let status = esperar_fila(&manager, |s| s.concluidos >= 2);
+        assert_eq!(status.concluidos, 2);
+        assert_eq!(status.pendentes, 0);
+        for codigo in ["91001", "91002"] {
+            let local = db::get_musica_local(codigo).unwrap().expect("música indexada");
+            assert!(Path::new(&local.arquivo).is_file());
+            assert_eq!(local.sha256, Some(sha256_arquivo(Path::new(&local.arquivo)).unwrap()));
+        }
+
+        // Já baixadas: nada volta para a fila
+        tauri::async_runtime::block_on(async {
+            assert_eq!(manager.atualizar_fila().await.unwrap(), 0);
+        });
+        manager.pausar();
+    }
+
+    #[test]
+    fn download_que_nao_confere_nao_entra_na_biblioteca() {
+        let dir = teste::dados();
+        let data_dir = dir.to_str().unwrap();
+        let mut corrompida = musica(&dir, "91003", b"video tres", "2026-01-03T00:00:00Z");
+        corrompida["sha256"] = serde_json::json!("00".repeat(32));
+        let fake = backend(serde_json::json!({ "musicas": [corrompida] }));
+
+        tauri::async_runtime::block_on(async {
+            catalogo::sincronizar_com(&fake, data_dir, true, true, &|_| {}).await.unwrap();
+        });
+
+        let manager = DownloadManager::new(Arc::new(fake), data_dir);
+        let erros = Arc::new(Mutex::new(Vec::new()));
+        let recebidos = erros.clone();
+        manager.on_evento(move |evento| {
+            if let EventoDownload::Erro(e) = evento {
+                recebidos.lock().unwrap().push(e);
+            }
+        });
+        manager.iniciar();
+        manager.retomar();
+        esperar_fila(&manager, |_| !erros.lock().unwrap().is_empty());
+        manager.pausar();
+
+        let erros = erros.lock().unwrap();
+        assert!(erros.iter().any(|e| e.codigo == "91003" && e.erro.contains("SHA-256")), "{:?}", *erros);
+        assert!(db::get_musica_local("91003").unwrap().is_none());
+        assert!(!dir.join("musicas").join("91003.mp4").exists());
+    }
+
+    #[test]
+    fn ativa_chave_online_e_revalida() {
+        let _dir = teste::dados();
+        let expira = (chrono::Utc::now() + chrono::Duration::days(30)).to_rfc3339();
+        let fake = backend(serde_json::json!({
+            "chaves": [
+                { "id": "c1", "chave": "TESTE-0001", "tipo": "assinatura", "status": "ativa",
+                  "data_expiracao": expira, "data_inicio": null, "limite_tempo": null,
+                  "user_id": "conta-1", "ultimo_uso": null, "machine_id": null },
+                { "id": "c2", "chave": "TESTE-0002", "tipo": "assinatura", "status": "ativa",
+                  "data_expiracao": expira, "data_inicio": null, "limite_tempo": null,
+                  "user_id": "conta-1", "ultimo_uso": null, "machine_id": "outra-maquina" },
+            ],
+            "segredos_rede_local": { "conta-1": "segredo-da-conta" },
+        }));
+        let machine_id = db::get_or_create_machine_id().unwrap();
+
+        tauri::async_runtime::block_on(async {
+            let mut evento = ativacao::novo_evento("validar_chave", Some("TESTE-0001"));
+            let r = ativacao::validar_chave_online(&fake, "TESTE-0001", &mut evento).await.unwrap();
+            assert!(r.valida, "{:?}", r.error);
+            // Primeira ativação vincula a máquina e traz o segredo da conta
+            let chave = fake.buscar_chave("TESTE-0001").await.unwrap().unwrap();
+            assert_eq!(chave.machine_id.as_deref(), Some(machine_id.as_str()));
+            assert!(rede_local::status().conta_vinculada);
+
+            let salva = db::get_ativacao().unwrap().expect("ativação salva");
+            let mut evento = ativacao::novo_evento("verificar_ativacao", Some(&salva.chave));
+            let status = ativacao::verificar_ativacao_salva(&fake, salva, &mut evento).await.unwrap();
+            assert!(status.ativada && !status.expirada);
+            assert_eq!(status.modo, "online");
+            assert!(status.dias_restantes.is_some_and(|d| (29..=30).contains(&d)));
+
+            // Chave presa a outra máquina não ativa esta
+            let mut evento = ativacao::novo_evento("validar_chave", Some("TESTE-0002"));
+            let r = ativacao::validar_chave_online(&fake, "TESTE-0002", &mut evento).await.unwrap();
+            assert!(!r.valida);
+            assert_eq!(evento.resultado, "conflito_maquina");
+        });
+        rede_local::definir_segredo(None);
+    }
+
+    #[test]
+    fn envia_historico_com_o_tom() {
+        let dir = teste::dados();
+        let machine_id = db::get_or_create_machine_id().unwrap();
+        let fake = backend(serde_json::json!({
+            "musicas": [musica(&dir, "91004", b"video quatro", "2026-01-04T00:00:00Z")],
+            "chaves": [
+                { "id": "c3", "chave": "TESTE-0003", "tipo": "maquina", "status": "ativa",
+                  "data_expiracao": null, "data_inicio": null, "limite_tempo": null,
+                  "user_id": "conta-1", "ultimo_uso": null, "machine_id": machine_id },
+            ],
+        }));
+        db::salvar_ativacao("TESTE-0003", "maquina", None, None, None).unwrap();
+        db::salvar_historico_db("91004", -2).unwrap();
+        // Fora do catálogo: o servidor descarta, mas sai da fila de envio
+        db::salvar_historico_db("99999", 0).unwrap();
+
+        tauri::async_runtime::block_on(historico::enviar_pendentes(&fake));
+        let enviados = fake.data.lock().unwrap().historico.clone();
+        assert_eq!(enviados.len(), 1);
+        assert_eq!((enviados[0].codigo.as_str(), enviados[0].tom), ("91004", -2));
+        assert!(db::historico_nao_enviado(10).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,262 @@
+// Backend remoto (catálogo de músicas + licenças).
+//
+// Os comandos recebem `Backend` do estado do Tauri em vez de falar direto com
+// o Supabase. Em produção a implementação é `supabase::PostgrestBackend`;
+// para desenvolvimento e testes existe `fake::FakeBackend`, em memória.
+
+pub mod fake;
+
+use crate::http::{Connectivity, HttpClient, HttpConfig};
+use serde::{Deserialize, Serialize};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+
+pub type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+// ─── Erros ──────────────────────────────────────────────────────────────────
+
+#[derive(Debug, Clone)]
+pub enum BackendError {
+    /// URL/chave do Supabase ausentes
+    NaoConfigurado,
+    /// Falha de transporte (DNS, conexão recusada, timeout...)
+    Rede(String),
+    /// Rede sabidamente fora do ar; requisição nem foi enviada
+    Offline,
+    /// Resposta HTTP fora de 2xx
+    Http { status: u16, corpo: String },
+    /// Corpo da resposta não corresponde ao formato esperado
+    Parse(String),
+    /// Erro local ao gravar o resultado (arquivo, SQLite)
+    Io(String),
+    /// Arquivo baixado não confere com o tamanho/hash do catálogo
+    Integridade(String),
+}
+
+impl std::fmt::Display for BackendError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BackendError::NaoConfigurado => write!(f, "Supabase not configured"),
+            BackendError::Rede(e) => write!(f, "Network error: {}", e),
+            BackendError::Offline => write!(f, "Offline: sem conexão com o servidor"),
+            BackendError::Http { status, corpo } if corpo.is_empty() => write!(f, "Supabase error {}", status),
+            BackendError::Http { status, corpo } => write!(f, "Supabase error {}: {}", status, corpo),
+            BackendError::Parse(e) => write!(f, "Parse error: {}", e),
+            BackendError::Io(e) => write!(f, "IO error: {}", e),
+            BackendError::Integridade(e) => write!(f, "Arquivo corrompido: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for BackendError {}
+
+impl From<BackendError> for String {
+    fn from(e: BackendError) -> Self {
+        e.to_string()
+    }
+}
+
+// ─── Modelos remotos ────────────────────────────────────────────────────────
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MusicaRemota {
+    pub id: String,
+    pub codigo: String,
+    pub artista: String,
+    pub titulo: String,
+    pub arquivo: String,
+    pub nome_arquivo: Option<String>,
+    pub tamanho: Option<serde_json::Value>,
+    pub duracao: Option<serde_json::Value>,
+    pub user_id: Option<String>,
+    pub created_at: Option<String>,
+    pub updated_at: Option<String>,
+    /// Tombstone: preenchido quando a música foi apagada do catálogo
+    #[serde(default)]
+    pub deleted_at: Option<String>,
+    /// SHA-256 (hex) do vídeo; muda quando o arquivo é substituído
+    #[serde(default)]
+    pub sha256: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChaveRemota {
+    pub id: String,
+    pub chave: String,
+    #[serde(default)]
+    pub tipo: String,
+    #[serde(default)]
+    pub status: String,
+    pub data_expiracao: Option<String>,
+    pub data_inicio: Option<String>,
+    pub limite_tempo: Option<f64>,
+    pub user_id: Option<serde_json::Value>,
+    pub ultimo_uso: Option<String>,
+    /// Identificador único da máquina que ativou esta chave.
+    /// None = ainda não ativada em nenhuma máquina.
+    pub machine_id: Option<String>,
+    // Accept any extra fields from Supabase without failing
+    #[serde(flatten)]
+    pub extra: std::collections::HashMap<String, serde_json::Value>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[allow(dead_code)]
+pub struct AssinaturaRemota {
+    pub id: String,
+    pub user_id: String,
+    pub status: String,
+    pub data_fim: Option<String>,
+}
+
+/// Execuções de uma música em todas as máquinas (função `musicas_populares`).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PopularidadeRemota {
+    pub musica_id: String,
+    pub total: i64,
+}
+
+/// Música tocada nesta máquina, enviada ao histórico da conta (função
+/// `registrar_historico`).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ItemHistorico {
+    pub codigo: String,
+    /// ms desde a época
+    pub data_execucao: i64,
+    /// Semitons em relação ao original
+    pub tom: i32,
+}
+
+/// Compara timestamps do PostgREST (RFC 3339); se não der para interpretar,
+/// cai na comparação de texto, que funciona para o mesmo formato/fuso.
+pub fn timestamp_cmp(a: &str, b: &str) -> std::cmp::Ordering {
+    match (chrono::DateTime::parse_from_rfc3339(a), chrono::DateTime::parse_from_rfc3339(b)) {
+        (Ok(a), Ok(b)) => a.cmp(&b),
+        _ => a.cmp(b),
+    }
+}
+
+/// Uma página do catálogo, entregue assim que termina de chegar.
+pub struct PaginaCatalogo {
+    pub musicas: Vec<MusicaRemota>,
+    /// Total acumulado de linhas recebidas, incluindo esta página
+    pub recebidas: i64,
+    /// Total de linhas que o servidor informou (`Content-Range`), se houver
+    pub total: Option<i64>,
+}
+
+/// O que o catálogo diz sobre o arquivo, para conferir o download.
+#[derive(Debug, Clone, Default)]
+pub struct Integridade {
+    /// Tamanho em bytes (None ou 0 no catálogo = desconhecido)
+    pub tamanho: Option<u64>,
+    /// SHA-256 em hex
+    pub sha256: Option<String>,
+}
+
+/// Arquivo gravado e conferido.
+#[derive(Debug, Clone)]
+pub struct ArquivoBaixado {
+    pub tamanho: u64,
+    /// SHA-256 calculado do que foi gravado (guardado para reverificação)
+    pub sha256: String,
+}
+
+/// Andamento de um download: (bytes já no disco, tamanho total se conhecido).
+/// Chamado a cada bloco; quem recebe é que decide quando repassar.
+pub type OnProgresso<'a> = &'a (dyn Fn(u64, Option<u64>) + Send + Sync);
+
+/// Callback chamado a cada página; um erro interrompe a paginação.
+pub type OnPagina<'a> = &'a mut (dyn FnMut(PaginaCatalogo) -> Result<(), BackendError> + Send);
+
+// ─── Traits ─────────────────────────────────────────────────────────────────
+
+/// Catálogo de músicas e download dos vídeos.
+pub trait CatalogBackend: Send + Sync {
+    /// Catálogo completo (`alteradas_desde = None`) ou só as linhas com
+    /// `updated_at >= alteradas_desde`, em ordem crescente de `updated_at`,
+    /// entregue página a página em `on_pagina`. Retorna o total recebido.
+    fn fetch_musicas<'a>(
+        &'a self,
+        alteradas_desde: Option<&'a str>,
+        on_pagina: OnPagina<'a>,
+    ) -> BoxFuture<'a, Result<i64, BackendError>>;
+
+    /// Baixa `url` para `dest`. O arquivo só substitui `dest` se conferir com
+    /// `integridade`; caso contrário retorna `BackendError::Integridade`.
+    fn download_file<'a>(
+        &'a self,
+        url: &'a str,
+        dest: &'a str,
+        integridade: &'a Integridade,
+        progresso: OnProgresso<'a>,
+    ) -> BoxFuture<'a, Result<ArquivoBaixado, BackendError>>;
+
+    /// Músicas mais tocadas (todas as máquinas), da mais para a menos tocada.
+    fn fetch_popularidade<'a>(&'a self, limite: i64) -> BoxFuture<'a, Result<Vec<PopularidadeRemota>, BackendError>>;
+}
+
+/// Chaves de ativação e assinaturas.
+pub trait LicenseBackend: Send + Sync {
+    fn buscar_chave<'a>(&'a self, chave: &'a str) -> BoxFuture<'a, Result<Option<ChaveRemota>, BackendError>>;
+
+    #[allow(dead_code)]
+    fn check_assinatura<'a>(&'a self, user_id: &'a str) -> BoxFuture<'a, Result<Option<AssinaturaRemota>, BackendError>>;
+
+    /// Atualiza `ultimo_uso` da chave.
+    fn update_ultimo_uso<'a>(&'a self, chave_id: &'a str) -> BoxFuture<'a, Result<(), BackendError>>;
+
+    /// Vincula o machine_id a uma chave (primeira ativação nesta máquina).
+    /// Só deve ser chamado quando machine_id da chave for None.
+    fn vincular_machine_id<'a>(&'a self, chave_id: &'a str, machine_id: &'a str) -> BoxFuture<'a, Result<(), BackendError>>;
+
+    /// Segredo da rede local da conta dona da chave (rede_local.rs). Só vem
+    /// para chave ativa já vinculada a `machine_id`; None caso contrário.
+    fn segredo_rede_local<'a>(&'a self, chave: &'a str, machine_id: &'a str) -> BoxFuture<'a, Result<Option<String>, BackendError>>;
+
+    /// Envia execuções para o histórico da conta dona da chave. Quantas
+    /// entraram (as de músicas fora do catálogo ficam de fora); None se a
+    /// chave não está ativa ou vinculada a `machine_id`.
+    fn registrar_historico<'a>(
+        &'a self,
+        chave: &'a str,
+        machine_id: &'a str,
+        itens: &'a [ItemHistorico],
+    ) -> BoxFuture<'a, Result<Option<i64>, BackendError>>;
+}
+
+// ─── Estado do Tauri ────────────────────────────────────────────────────────
+
+#[derive(Clone)]
+pub struct Backend {
+    pub catalogo: Arc<dyn CatalogBackend>,
+    pub licenca: Arc<dyn LicenseBackend>,
+    /// Estado de rede compartilhado por todas as chamadas remotas
+    pub conectividade: Arc<Connectivity>,
+}
+
+impl Backend {
+    /// Escolhe a implementação a partir do ambiente (já com o .env carregado).
+    /// `BLUE_KARAOKE_FAKE_BACKEND=<arquivo.json>` usa o backend em memória
+    /// com os dados do arquivo; caso contrário, o Supabase.
+    pub fn from_env() -> Self {
+        let conectividade = Arc::new(Connectivity::new());
+        if let Ok(path) = std::env::var("BLUE_KARAOKE_FAKE_BACKEND") {
+            match fake::FakeBackend::from_file(&path) {
+                Ok(fake) => {
+                    log::warn!("[BACKEND] Usando backend fake: {}", path);
+                    let fake = Arc::new(fake);
+                    return Self { catalogo: fake.clone(), licenca: fake, conectividade };
+                }
+                Err(e) => log::error!("[BACKEND] Backend fake inválido ({}): {}", path, e),
+            }
+        }
+        let http = Arc::new(HttpClient::new(HttpConfig::from_env(), conectividade.clone()));
+        let supabase = Arc::new(crate::supabase::PostgrestBackend::new(
+            crate::supabase::SupabaseConfig::from_env(),
+            http,
+        ));
+        Self { catalogo: supabase.clone(), licenca: supabase, conectividade }
+    }
+}
@@ -0,0 +1,345 @@
+// Conferência da biblioteca: pasta `musicas/` contra `musicas_local`.
+//
+// Os dois podem se desencontrar (arquivo apagado à mão, cópia interrompida,
+// disco cheio em versões antigas sem `.part`). A conferência lista:
+//
+//   sem_arquivo  — registro cujo vídeo não existe
+//   orfaos       — vídeo sem registro
+//   vazios       — arquivo com 0 bytes
+//   truncados    — menor que o tamanho esperado, ou MP4 com caixa cortada
+//   divergentes  — maior que o tamanho registrado no download
+//
+// e, com `reparar`, volta a baixar o que faltar, indexa os órfãos que estão
+// no catálogo e descarta (quarentena ou apaga) os corrompidos. Só confere
+// tamanho e estrutura; o SHA-256 fica com `reverificar_musicas`.
+
+use crate::commands::sync;
+use crate::db;
+use crate::midia;
+use crate::quarentena;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Uma conferência por vez.
+static EM_ANDAMENTO: AtomicBool = AtomicBool::new(false);
+
+/// A cada quantos itens o progresso é publicado.
+const PASSO_PROGRESSO: usize = 25;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ProblemaArquivo {
+    pub codigo: String,
+    pub arquivo: String,
+    pub tamanho: u64,
+    /// Tamanho registrado no download ou, sem ele, o do catálogo
+    pub esperado: Option<i64>,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct RelatorioBiblioteca {
+    pub registros: usize,
+    pub arquivos: usize,
+    #[serde(rename = "semArquivo")]
+    pub sem_arquivo: Vec<String>,
+    pub orfaos: Vec<ProblemaArquivo>,
+    pub vazios: Vec<ProblemaArquivo>,
+    pub truncados: Vec<ProblemaArquivo>,
+    pub divergentes: Vec<ProblemaArquivo>,
+    /// Preenchido só quando a conferência foi chamada com `reparar`
+    pub reparo: Option<ResultadoReparo>,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct ResultadoReparo {
+    /// Códigos postos na fila de downloads
+    pub reenfileiradas: Vec<String>,
+    pub reindexadas: Vec<String>,
+    /// Arquivos corrompidos postos em quarentena ou apagados
+    pub descartadas: Vec<String>,
+    /// Órfãos fora do catálogo: ficam onde estão
+    pub ignoradas: Vec<String>,
+    pub erros: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ProgressoBiblioteca {
+    /// "registros", "arquivos" ou "reparo"
+    pub etapa: &'static str,
+    pub atual: usize,
+    pub total: usize,
+}
+
+/// O que fazer com os arquivos corrompidos.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Descarte {
+    Quarentena,
+    Apagar,
+}
+
+impl Descarte {
+    pub fn from_str(s: Option<&str>) -> Result<Self, String> {
+        match s.unwrap_or("quarentena") {
+            "quarentena" => Ok(Self::Quarentena),
+            "apagar" => Ok(Self::Apagar),
+            outro => Err(format!("Descarte desconhecido: {} (use quarentena ou apagar)", outro)),
+        }
+    }
+}
+
+/// Confere (e, com `reparar`, conserta) a biblioteca. Bloqueante: chamar
+/// fora do runtime async.
+pub fn verificar(
+    data_dir: &str,
+    reparar: Option<Descarte>,
+    progresso: &(dyn Fn(ProgressoBiblioteca) + Send + Sync),
+) -> Result<RelatorioBiblioteca, String> {
+    if EM_ANDAMENTO.swap(true, Ordering::SeqCst) {
+        return Err("Conferência da biblioteca já em andamento".to_string());
+    }
+    let _liberar = Liberar;
+    conferir(data_dir, progresso).map(|mut relatorio| {
+        if let Some(descarte) = reparar {
+            relatorio.reparo = Some(consertar(data_dir, &relatorio, descarte, progresso));
+        }
+        relatorio
+    })
+}
+
+/// Libera `EM_ANDAMENTO` ao sair de `verificar`, inclusive num panic.
+struct Liberar;
+
+impl Drop for Liberar {
+    fn drop(&mut self) {
+        EM_ANDAMENTO.store(false, Ordering::SeqCst);
+    }
+}
+
+fn conferir(
+    data_dir: &str,
+    progresso: &(dyn Fn(ProgressoBiblioteca) + Send + Sync),
+) -> Result<RelatorioBiblioteca, String> {
+    let musicas_dir = Path::new(data_dir).join("musicas");
+    let mut relatorio = RelatorioBiblioteca::default();
+
+    let musicas = db::listar_musicas_local()?;
+    relatorio.registros = musicas.len();
+    let mut registrados: HashSet<PathBuf> = HashSet::new();
+    for (i, m) in musicas.iter().enumerate() {
+        if i.is_multiple_of(PASSO_PROGRESSO) {
+            progresso(ProgressoBiblioteca { etapa: "registros", atual: i, total: musicas.len() });
+        }
+        let path = PathBuf::from(&m.arquivo);
+        registrados.insert(path.clone());
+        let Ok(meta) = std::fs::metadata(&path) else {
+            relatorio.sem_arquivo.push(m.codigo.clone());
+            continue;
+        };
+        let esperado = match m.tamanho.filter(|t| *t > 0) {
+            Some(t) => Some(t),
+            None => db::get_catalogo_by_codigo(&m.codigo)?.and_then(|c| c.tamanho).filter(|t| *t > 0),
+        };
+        let problema = ProblemaArquivo { codigo: m.codigo.clone(), arquivo: m.arquivo.clone(), tamanho: meta.len(), esperado };
+        match classificar(&path, meta.len(), esperado) {
+            Some(Defeito::Vazio) => relatorio.vazios.push(problema),
+            Some(Defeito::Truncado) => relatorio.truncados.push(problema),
+            Some(Defeito::Divergente) => relatorio.divergentes.push(problema),
+            None => {}
+        }
+    }
+
+    let arquivos: Vec<PathBuf> = midia::listar(&musicas_dir).unwrap_or_default();
+    relatorio.arquivos = arquivos.len();
+    for (i, path) in arquivos.iter().enumerate() {
+        if i.is_multiple_of(PASSO_PROGRESSO) {
+            progresso(ProgressoBiblioteca { etapa: "arquivos", atual: i, total: arquivos.len() });
+        }
+        if registrados.contains(path) {
+            continue;
+        }
+        let codigo = midia::codigo_do_arquivo(path).unwrap_or_default();
+        // Registro com outro caminho (ex.: pasta de dados movida) não é órfão
+        if db::get_musica_local(&codigo)?.is_some_and(|m| Path::new(&m.arquivo).exists()) {
+            continue;
+        }
+        relatorio.orfaos.push(ProblemaArquivo {
+            esperado: db::get_catalogo_by_codigo(&codigo)?.and_then(|c| c.tamanho).filter(|t| *t > 0),
+            codigo,
+            arquivo: path.to_string_lossy().to_string(),
+            tamanho: std::fs::metadata(path).map(|m| m.len()).unwrap_or(0),
+        });
+    }
+    progresso(ProgressoBiblioteca { etapa: "arquivos", atual: arquivos.len(), total: arquivos.len() });
+
+    log::info!(
+        "[BIBLIOTECA] {} registros, {} arquivos: {} sem arquivo, {} órfãos, {} vazios, {} truncados, {} divergentes",
+        relatorio.registros, relatorio.arquivos, relatorio.sem_arquivo.len(), relatorio.orfaos.len(),
+        relatorio.vazios.len(), relatorio.truncados.len(), relatorio.divergentes.len()
+    );
+    Ok(relatorio)
+}
+
+enum Defeito {
+    Vazio,
+    Truncado,
+    Divergente,
+}
+
+fn classificar(path: &Path, tamanho: u64, esperado: Option<i64>) -> Option<Defeito> {
+    if tamanho == 0 {
+        return Some(Defeito::Vazio);
+    }
+    match esperado.map(|e| tamanho.cmp(&(e as u64))) {
+        Some(std::cmp::Ordering::Less) => Some(Defeito::Truncado),
+        Some(std::cmp::Ordering::Greater) => Some(Defeito::Divergente),
+        _ if mp4_incompleto(path) => Some(Defeito::Truncado),
+        _ => None,
+    }
+}
+
+/// Percorre as caixas de primeiro nível do MP4 (ftyp, moov, mdat...) e diz
+/// se alguma passa do fim do arquivo, sinal de cópia cortada. Arquivo que
+/// não começa com `ftyp` não é julgado aqui.
+pub fn mp4_incompleto(path: &Path) -> bool {
+    let Ok(mut file) = File::open(path) else {
+        return false;
+    };
+    let Ok(len) = file.metadata().map(|m| m.len()) else {
+        return false;
+    };
+    let mut pos = 0u64;
+    while pos < len {
+        if len - pos < 8 {
+            return true;
+        }
+        let mut cabecalho = [0u8; 8];
+        if file.seek(SeekFrom::Start(pos)).is_err() || file.read_exact(&mut cabecalho).is_err() {
+            return false;
+        }
+        let tipo = &cabecalho[4..8];
+        if (pos == 0 && tipo != b"ftyp") || !tipo.iter().all(|b| b.is_ascii_alphanumeric() || *b == b' ') {
+            return false;
+        }
+        let tamanho = match u32::from_be_bytes(cabecalho[..4].try_into().unwrap()) {
+            // Caixa vai até o fim do arquivo
+            0 => return false,
+            // Tamanho de 64 bits logo depois do tipo
+            1 => {
+                let mut grande = [0u8; 8];
+                if file.read_exact(&mut grande).is_err() {
+                    return true;
+                }
+                u64::from_be_bytes(grande)
+            }
+            t => t as u64,
+        };
+        if tamanho < 8 {
+            return false;
+        }
+        if tamanho > len - pos {
+            return true;
+        }
+        pos += tamanho;
+    }
+    false
+}
+
+fn consertar(
+    data_dir: &str,
+    relatorio: &RelatorioBiblioteca,
+    descarte: Descarte,
+    progresso: &(dyn Fn(ProgressoBiblioteca) + Send + Sync),
+) -> ResultadoReparo {
+    let mut out = ResultadoReparo::default();
+    let total = relatorio.sem_arquivo.len()
+        + relatorio.orfaos.len()
+        + relatorio.vazios.len()
+        + relatorio.truncados.len()
+        + relatorio.divergentes.len();
+    let mut feitos = 0;
+    let avancar = |feitos: &mut usize| {
+        *feitos += 1;
+        if feitos.is_multiple_of(PASSO_PROGRESSO) || *feitos == total {
+            progresso(ProgressoBiblioteca { etapa: "reparo", atual: *feitos, total });
+        }
+    };
+    let no_catalogo = |codigo: &str| matches!(db::get_catalogo_by_codigo(codigo), Ok(Some(_)));
+
+    for codigo in &relatorio.sem_arquivo {
+        match db::remover_musica_local(codigo) {
+            Ok(_) if no_catalogo(codigo) => out.reenfileiradas.push(codigo.clone()),
+            Ok(_) => {}
+            Err(e) => out.erros.push(format!("{}: {}", codigo, e)),
+        }
+        avancar(&mut feitos);
+    }
+
+    // Vazio não tem o que guardar: apaga sempre
+    for p in &relatorio.vazios {
+        match std::fs::remove_file(&p.arquivo).map_err(|e| e.to_string()).and_then(|_| db::remover_musica_local(&p.codigo)) {
+            Ok(_) => {
+                out.descartadas.push(p.codigo.clone());
+                if no_catalogo(&p.codigo) {
+                    out.reenfileiradas.push(p.codigo.clone());
+                }
+            }
+            Err(e) => out.erros.push(format!("{}: {}", p.codigo, e)),
+        }
+        avancar(&mut feitos);
+    }
+
+    for p in relatorio.truncados.iter().chain(&relatorio.divergentes) {
+        let descartada = match (descarte, db::get_musica_local(&p.codigo)) {
+            (Descarte::Quarentena, Ok(Some(m))) => quarentena::isolar_corrompida(data_dir, &m),
+            (_, Err(e)) => {
+                out.erros.push(format!("{}: {}", p.codigo, e));
+                false
+            }
+            _ => std::fs::remove_file(&p.arquivo).is_ok() && db::remover_musica_local(&p.codigo).is_ok(),
+        };
+        if descartada {
+            out.descartadas.push(p.codigo.clone());
+            if no_catalogo(&p.codigo) {
+                out.reenfileiradas.push(p.codigo.clone());
+            }
+        } else {
+            out.erros.push(format!("{}: não foi possível descartar {}", p.codigo, p.arquivo));
+        }
+        avancar(&mut feitos);
+    }
+
+    for p in &relatorio.orfaos {
+        let catalogo = db::get_catalogo_by_codigo(&p.codigo).ok().flatten();
+        match catalogo {
+            None => out.ignoradas.push(p.arquivo.clone()),
+            Some(musica) => {
+                let path = Path::new(&p.arquivo);
+                if classificar(path, p.tamanho, p.esperado).is_some() {
+                    // Órfão quebrado: não vale indexar, baixa de novo
+                    match std::fs::remove_file(path) {
+                        Ok(_) => {
+                            out.descartadas.push(p.codigo.clone());
+                            out.reenfileiradas.push(p.codigo.clone());
+                        }
+                        Err(e) => out.erros.push(format!("{}: {}", p.arquivo, e)),
+                    }
+                } else {
+                    match sync::indexar_arquivo(path, &musica) {
+                        Ok(_) => out.reindexadas.push(p.codigo.clone()),
+                        Err(e) => out.erros.push(format!("{}: {}", p.codigo, e)),
+                    }
+                }
+            }
+        }
+        avancar(&mut feitos);
+    }
+
+    log::info!(
+        "[BIBLIOTECA] Reparo: {} reenfileiradas, {} reindexadas, {} descartadas, {} ignoradas, {} erros",
+        out.reenfileiradas.len(), out.reindexadas.len(), out.descartadas.len(), out.ignoradas.len(), out.erros.len()
+    );
+    out
+}
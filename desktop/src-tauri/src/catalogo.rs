@@ -0,0 +1,219 @@
+// Sincronização incremental do catálogo remoto para `catalogo_remoto`.
+//
+// Na primeira vez baixa o catálogo inteiro; depois só as linhas com
+// `updated_at >= catalogo_hwm` (high-water mark em config_local). Status,
+// download e reindexação leem do espelho local em vez de buscar
+// `musicas?select=*` a cada chamada.
+//
+// O catálogo chega paginado e em ordem de updated_at: cada página é gravada
+// e avança o high-water mark na mesma transação, então uma sincronização
+// interrompida continua de onde parou.
+//
+// Remoções chegam como tombstones (`deleted_at`) na sincronização incremental;
+// linhas apagadas de verdade no servidor só aparecem numa sincronização
+// completa (feita ao menos uma vez por dia), como linhas não vistas. Em ambos
+// os casos as músicas baixadas vão para a quarentena.
+
+use crate::backend::{timestamp_cmp, BackendError, CatalogBackend, MusicaRemota, PaginaCatalogo};
+use crate::db;
+use crate::quarentena;
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+const CHAVE_HWM: &str = "catalogo_hwm";
+const CHAVE_ULTIMA_COMPLETA: &str = "catalogo_ultima_completa";
+const CHAVE_POPULARIDADE: &str = "popularidade_atualizada_em";
+
+/// Quantas músicas do ranking global entram na prioridade de download.
+const LIMITE_POPULARIDADE: i64 = 500;
+
+/// O ranking muda devagar; não precisa ser buscado a cada atualização da fila.
+const INTERVALO_POPULARIDADE_MS: i64 = 12 * 60 * 60 * 1000;
+
+/// Intervalo máximo entre sincronizações completas, que detectam exclusões
+/// sem tombstone.
+const INTERVALO_COMPLETA_MS: i64 = 24 * 60 * 60 * 1000;
+
+/// Intervalo mínimo entre sincronizações automáticas (não forçadas).
+const INTERVALO_MINIMO: Duration = Duration::from_secs(60);
+
+/// Uma sincronização por vez; chamadas concorrentes esperam a atual terminar.
+static SYNC_LOCK: Lazy<tokio::sync::Mutex<()>> = Lazy::new(|| tokio::sync::Mutex::new(()));
+static ULTIMA_SYNC: Lazy<Mutex<Option<Instant>>> = Lazy::new(|| Mutex::new(None));
+
+#[derive(Debug, Default, Serialize)]
+pub struct CatalogSyncResult {
+    /// false quando a sincronização foi pulada (feita há menos de INTERVALO_MINIMO)
+    pub executada: bool,
+    pub incremental: bool,
+    pub recebidas: i64,
+    pub novas: i64,
+    pub atualizadas: i64,
+    #[serde(rename = "locaisAtualizadas")]
+    pub locais_atualizadas: i64,
+    #[serde(rename = "totalCatalogo")]
+    pub total_catalogo: i64,
+    /// Músicas que saíram do catálogo e foram tiradas da biblioteca
+    pub removidas: Vec<String>,
+    /// Músicas baixadas cujo vídeo mudou e entraram na fila de download
+    pub substituidas: Vec<String>,
+    /// Músicas que voltaram ao catálogo e saíram da quarentena
+    pub restauradas: Vec<String>,
+    /// Arquivos apagados da quarentena por prazo vencido
+    pub expurgadas: i64,
+}
+
+/// Andamento de uma sincronização, emitido a cada página recebida.
+#[derive(Debug, Clone, Serialize)]
+pub struct ProgressoCatalogo {
+    pub completa: bool,
+    pub pagina: i64,
+    pub recebidas: i64,
+    /// Total informado pelo servidor (None se ele não informar)
+    pub total: Option<i64>,
+}
+
+/// Sincroniza o catálogo. Sem `forcar`, não faz nada se a última
+/// sincronização bem-sucedida foi há menos de um minuto.
+pub async fn sincronizar(catalogo: &dyn CatalogBackend, data_dir: &str, forcar: bool) -> Result<CatalogSyncResult, String> {
+    sincronizar_com(catalogo, data_dir, forcar, false, &|_| {}).await
+}
+
+/// Como `sincronizar`; `completa` ignora o high-water mark e relê o catálogo
+/// inteiro, e `progresso` recebe o andamento página a página.
+pub async fn sincronizar_com(
+    catalogo: &dyn CatalogBackend,
+    data_dir: &str,
+    forcar: bool,
+    completa: bool,
+    progresso: &(dyn Fn(ProgressoCatalogo) + Send + Sync),
+) -> Result<CatalogSyncResult, String> {
+    let _guard = SYNC_LOCK.lock().await;
+
+    if !forcar && !completa {
+        if let Some(t) = *ULTIMA_SYNC.lock().unwrap() {
+            if t.elapsed() < INTERVALO_MINIMO {
+                return Ok(CatalogSyncResult {
+                    total_catalogo: db::count_catalogo()?,
+                    ..Default::default()
+                });
+            }
+        }
+    }
+
+    let inicio = chrono::Utc::now().timestamp_millis();
+    let ultima_completa = db::get_config(CHAVE_ULTIMA_COMPLETA)?.and_then(|v| v.parse::<i64>().ok());
+    let completa = completa || ultima_completa.is_none_or(|t| inicio - t >= INTERVALO_COMPLETA_MS);
+
+    // Sem nenhuma linha local, o high-water mark não vale (banco recriado)
+    let hwm = if !completa && db::count_catalogo()? > 0 { db::get_config(CHAVE_HWM)? } else { None };
+
+    let mut result = CatalogSyncResult {
+        executada: true,
+        incremental: hwm.is_some(),
+        ..Default::default()
+    };
+    let mut hwm_atual = hwm.clone();
+    let mut pagina = 0;
+    let mut on_pagina = |p: PaginaCatalogo| -> Result<(), BackendError> {
+        pagina += 1;
+        hwm_atual = p
+            .musicas
+            .iter()
+            .filter_map(|m| m.updated_at.as_deref())
+            .chain(hwm_atual.as_deref())
+            .max_by(|a, b| timestamp_cmp(a, b))
+            .map(str::to_string);
+        let linhas: Vec<db::MusicaCatalogo> = p.musicas.iter().map(para_catalogo).collect();
+        let aplicado = db::aplicar_catalogo(&linhas, hwm_atual.as_deref()).map_err(BackendError::Io)?;
+        result.recebidas = p.recebidas;
+        result.novas += aplicado.novas;
+        result.atualizadas += aplicado.atualizadas;
+        result.locais_atualizadas += aplicado.locais_atualizadas;
+        result.removidas.extend(aplicado.removidas);
+        result.substituidas.extend(aplicado.substituidas);
+        progresso(ProgressoCatalogo { completa: hwm.is_none(), pagina, recebidas: p.recebidas, total: p.total });
+        Ok(())
+    };
+    catalogo.fetch_musicas(hwm.as_deref(), &mut on_pagina).await?;
+
+    let mut removidas = quarentena::isolar(data_dir, &result.removidas, "removida");
+    if hwm.is_none() && result.recebidas > 0 {
+        // Catálogo inteiro relido: o que não veio foi apagado no servidor.
+        // Resposta vazia é tratada como suspeita (permissão, filtro), não como
+        // catálogo apagado.
+        let ausentes = db::remover_catalogo_nao_visto(inicio)?;
+        removidas.extend(quarentena::isolar(data_dir, &ausentes, "ausente"));
+        db::set_config(CHAVE_ULTIMA_COMPLETA, &inicio.to_string())?;
+    }
+    result.removidas = removidas;
+    result.restauradas = quarentena::restaurar_reaparecidas()?;
+    result.expurgadas = quarentena::expurgar_vencidas()?;
+
+    *ULTIMA_SYNC.lock().unwrap() = Some(Instant::now());
+
+    result.total_catalogo = db::count_catalogo()?;
+    log::info!(
+        "[CATALOGO] Sync {}: {} recebidas, {} novas, {} atualizadas ({} já baixadas), {} removidas, {} substituídas, {} restauradas, total {}",
+        if result.incremental { "incremental" } else { "completa" },
+        result.recebidas, result.novas, result.atualizadas, result.locais_atualizadas,
+        result.removidas.len(), result.substituidas.len(), result.restauradas.len(), result.total_catalogo
+    );
+    Ok(result)
+}
+
+/// Versão do espelho local: o `updated_at` mais recente já aplicado.
+pub fn versao() -> Option<String> {
+    db::get_config(CHAVE_HWM).ok().flatten()
+}
+
+/// Atualiza o ranking global de execuções usado para ordenar a fila de
+/// downloads. Falha de rede não é erro: a fila só fica sem esse critério.
+pub async fn atualizar_popularidade(catalogo: &dyn CatalogBackend) -> Result<usize, String> {
+    let agora = chrono::Utc::now().timestamp_millis();
+    let ultima = db::get_config(CHAVE_POPULARIDADE)?.and_then(|v| v.parse::<i64>().ok());
+    if ultima.is_some_and(|t| agora - t < INTERVALO_POPULARIDADE_MS) {
+        return Ok(0);
+    }
+    let ranking = match catalogo.fetch_popularidade(LIMITE_POPULARIDADE).await {
+        Ok(r) => r,
+        Err(e) => {
+            log::warn!("[CATALOGO] Ranking de popularidade indisponível: {}", e);
+            return Ok(0);
+        }
+    };
+    let ranking: Vec<(String, i64)> = ranking.into_iter().map(|p| (p.musica_id, p.total)).collect();
+    db::aplicar_popularidade(&ranking)?;
+    db::set_config(CHAVE_POPULARIDADE, &agora.to_string())?;
+    log::info!("[CATALOGO] Ranking de popularidade: {} músicas", ranking.len());
+    Ok(ranking.len())
+}
+
+/// Número que pode vir do Supabase como número ou texto.
+fn valor_i64(v: &Option<serde_json::Value>) -> Option<i64> {
+    match v.as_ref()? {
+        serde_json::Value::Number(n) => n.as_i64().or_else(|| n.as_f64().map(|f| f.round() as i64)),
+        serde_json::Value::String(s) => s.trim().parse::<f64>().ok().map(|f| f.round() as i64),
+        _ => None,
+    }
+}
+
+fn para_catalogo(m: &MusicaRemota) -> db::MusicaCatalogo {
+    db::MusicaCatalogo {
+        id: m.id.clone(),
+        codigo: m.codigo.clone(),
+        artista: m.artista.clone(),
+        titulo: m.titulo.clone(),
+        arquivo: m.arquivo.clone(),
+        nome_arquivo: m.nome_arquivo.clone(),
+        tamanho: valor_i64(&m.tamanho),
+        duracao: valor_i64(&m.duracao),
+        user_id: m.user_id.clone(),
+        updated_at: m.updated_at.clone(),
+        created_at: m.created_at.clone(),
+        sha256: m.sha256.as_deref().map(|h| h.trim().to_ascii_lowercase()).filter(|h| !h.is_empty()),
+        removida: m.deleted_at.is_some(),
+    }
+}
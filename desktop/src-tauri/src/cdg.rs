@@ -0,0 +1,445 @@
+// Decodificador CD+G (gráficos do MP3+G).
+//
+// O `.cdg` é a sequência dos pacotes de subcódigo do CD: 24 bytes cada,
+// 300 pacotes por segundo de áudio. Os pacotes com comando 9 desenham numa
+// tela de 300×216 pixels com paleta de 16 cores (12 bits), em blocos de
+// 6×12; a borda de 6 px nas laterais e 12 px em cima e embaixo nunca mostra
+// o conteúdo rolado.
+//
+// No mpv o `.cdg` toca direto (com `--audio-file` apontando para o MP3).
+// Sem o mpv, o frontend toca o MP3 no `<audio>` e pede aqui o quadro do
+// instante atual: `quadro` mantém um decodificador por arquivo e só avança
+// (ou recomeça, se o áudio voltou) até o pacote daquele instante.
+
+use base64::Engine;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+pub const LARGURA: usize = 300;
+pub const ALTURA: usize = 216;
+pub const TAMANHO_PACOTE: usize = 24;
+pub const PACOTES_POR_SEGUNDO: u64 = 300;
+
+const BORDA_X: usize = 6;
+const BORDA_Y: usize = 12;
+const BLOCO_L: usize = 6;
+const BLOCO_A: usize = 12;
+const COLUNAS: usize = LARGURA / BLOCO_L;
+const LINHAS: usize = ALTURA / BLOCO_A;
+
+const COMANDO_CDG: u8 = 0x09;
+const MEMORY_PRESET: u8 = 1;
+const BORDER_PRESET: u8 = 2;
+const TILE_BLOCK: u8 = 6;
+const SCROLL_PRESET: u8 = 20;
+const SCROLL_COPY: u8 = 24;
+const DEFINE_TRANSPARENT: u8 = 28;
+const LOAD_COLOR_LOW: u8 = 30;
+const LOAD_COLOR_HIGH: u8 = 31;
+const TILE_BLOCK_XOR: u8 = 38;
+
+/// Estado da tela CD+G.
+pub struct Decodificador {
+    /// Índice na paleta de cada pixel, linha a linha
+    pixels: Vec<u8>,
+    /// Cores em 12 bits (0x0RGB)
+    paleta: [u16; 16],
+    borda: u8,
+    /// Deslocamento fino da área visível (0..6 e 0..12)
+    desloc_x: usize,
+    desloc_y: usize,
+    /// Muda a cada pacote que altera o que aparece na tela
+    versao: u64,
+}
+
+impl Default for Decodificador {
+    fn default() -> Self {
+        Self {
+            pixels: vec![0; LARGURA * ALTURA],
+            paleta: [0; 16],
+            borda: 0,
+            desloc_x: 0,
+            desloc_y: 0,
+            versao: 0,
+        }
+    }
+}
+
+impl Decodificador {
+    pub fn versao(&self) -> u64 {
+        self.versao
+    }
+
+    /// Cor de um índice da paleta em RGB de 8 bits.
+    pub fn cor(&self, indice: u8) -> [u8; 3] {
+        let c = self.paleta[(indice & 0x0F) as usize];
+        [((c >> 8) & 0x0F) as u8 * 17, ((c >> 4) & 0x0F) as u8 * 17, (c & 0x0F) as u8 * 17]
+    }
+
+    /// Aplica um pacote. Pacotes de outros subcanais (e instruções
+    /// desconhecidas) são ignorados.
+    pub fn aplicar(&mut self, pacote: &[u8]) {
+        if pacote.len() < TAMANHO_PACOTE || pacote[0] & 0x3F != COMANDO_CDG {
+            return;
+        }
+        let dados: [u8; 16] = std::array::from_fn(|i| pacote[4 + i] & 0x3F);
+        match pacote[1] & 0x3F {
+            MEMORY_PRESET => self.pixels.fill(dados[0] & 0x0F),
+            BORDER_PRESET => self.borda = dados[0] & 0x0F,
+            TILE_BLOCK => self.bloco(&dados, false),
+            TILE_BLOCK_XOR => self.bloco(&dados, true),
+            SCROLL_PRESET => self.rolar(&dados, false),
+            SCROLL_COPY => self.rolar(&dados, true),
+            // Transparência só importa sobre vídeo; aqui o fundo é a própria tela
+            DEFINE_TRANSPARENT => return,
+            LOAD_COLOR_LOW => self.carregar_cores(&dados, 0),
+            LOAD_COLOR_HIGH => self.carregar_cores(&dados, 8),
+            _ => return,
+        }
+        self.versao += 1;
+    }
+
+    fn bloco(&mut self, dados: &[u8; 16], xor: bool) {
+        let (cor0, cor1) = (dados[0] & 0x0F, dados[1] & 0x0F);
+        let (linha, coluna) = ((dados[2] & 0x1F) as usize, dados[3] as usize);
+        if linha >= LINHAS || coluna >= COLUNAS {
+            return;
+        }
+        for (dy, bits) in dados[4..16].iter().enumerate() {
+            let base = (linha * BLOCO_A + dy) * LARGURA + coluna * BLOCO_L;
+            for dx in 0..BLOCO_L {
+                let cor = if bits & (0x20 >> dx) != 0 { cor1 } else { cor0 };
+                let pixel = &mut self.pixels[base + dx];
+                *pixel = if xor { (*pixel ^ cor) & 0x0F } else { cor };
+            }
+        }
+    }
+
+    /// Scroll Preset/Copy: desloca a tela um bloco inteiro (o que sai entra
+    /// do outro lado na cópia, ou vira `cor` no preset) e acerta o
+    /// deslocamento fino.
+    fn rolar(&mut self, dados: &[u8; 16], copia: bool) {
+        let cor = dados[0] & 0x0F;
+        let (h, v) = (dados[1], dados[2]);
+        self.desloc_x = ((h & 0x07) as usize).min(BLOCO_L - 1);
+        self.desloc_y = ((v & 0x0F) as usize).min(BLOCO_A - 1);
+        let dx: isize = match (h & 0x30) >> 4 {
+            1 => BLOCO_L as isize,
+            2 => -(BLOCO_L as isize),
+            _ => 0,
+        };
+        let dy: isize = match (v & 0x30) >> 4 {
+            1 => BLOCO_A as isize,
+            2 => -(BLOCO_A as isize),
+            _ => 0,
+        };
+        if dx == 0 && dy == 0 {
+            return;
+        }
+        let anterior = self.pixels.clone();
+        for y in 0..ALTURA {
+            for x in 0..LARGURA {
+                let (ox, oy) = (x as isize - dx, y as isize - dy);
+                let dentro = (0..LARGURA as isize).contains(&ox) && (0..ALTURA as isize).contains(&oy);
+                self.pixels[y * LARGURA + x] = if dentro {
+                    anterior[oy as usize * LARGURA + ox as usize]
+                } else if copia {
+                    let ox = ox.rem_euclid(LARGURA as isize) as usize;
+                    let oy = oy.rem_euclid(ALTURA as isize) as usize;
+                    anterior[oy * LARGURA + ox]
+                } else {
+                    cor
+                };
+            }
+        }
+    }
+
+    fn carregar_cores(&mut self, dados: &[u8; 16], inicio: usize) {
+        for i in 0..8 {
+            let (alto, baixo) = (dados[2 * i] as u16, dados[2 * i + 1] as u16);
+            let r = (alto & 0x3C) >> 2;
+            let g = ((alto & 0x03) << 2) | ((baixo & 0x30) >> 4);
+            let b = baixo & 0x0F;
+            self.paleta[inicio + i] = (r << 8) | (g << 4) | b;
+        }
+    }
+
+    /// Índice da paleta de cada pixel da tela como aparece: borda na cor da
+    /// borda, área útil com o deslocamento fino aplicado.
+    pub fn tela(&self) -> Vec<u8> {
+        let mut out = vec![self.borda; LARGURA * ALTURA];
+        for y in BORDA_Y..ALTURA - BORDA_Y {
+            for x in BORDA_X..LARGURA - BORDA_X {
+                let (ox, oy) = (x + self.desloc_x, y + self.desloc_y);
+                out[y * LARGURA + x] = self.pixels[oy * LARGURA + ox];
+            }
+        }
+        out
+    }
+}
+
+// ─── Reprodução no frontend ─────────────────────────────────────────────────
+
+#[derive(Debug, Serialize)]
+pub struct QuadroCdg {
+    pub largura: usize,
+    pub altura: usize,
+    /// Muda sempre que a tela muda; o frontend repassa para não receber de
+    /// novo o mesmo quadro
+    pub versao: u64,
+    /// 16 cores em "#rrggbb"
+    pub paleta: Vec<String>,
+    /// Índices da paleta, dois pixels por byte (o da esquerda no nibble
+    /// alto), em base64
+    pub pixels: String,
+}
+
+struct Sessao {
+    arquivo: PathBuf,
+    dados: Vec<u8>,
+    decodificador: Decodificador,
+    /// Próximo pacote a aplicar
+    pacote: usize,
+}
+
+static SESSAO: Mutex<Option<Sessao>> = Mutex::new(None);
+
+/// `.cdg` que acompanha o áudio `arquivo`.
+pub fn arquivo_cdg(arquivo: &Path) -> Option<PathBuf> {
+    crate::midia::companheiro(arquivo, "cdg")
+}
+
+/// Quadro de `arquivo` (o `.cdg`) em `posicao_ms` do áudio. None se a tela
+/// ainda está na `versao` que o frontend já tem.
+pub fn quadro(arquivo: &Path, posicao_ms: u64, versao: Option<u64>) -> Result<Option<QuadroCdg>, String> {
+    let mut sessao = SESSAO.lock().unwrap();
+    if sessao.as_ref().is_none_or(|s| s.arquivo != arquivo) {
+        let dados = std::fs::read(arquivo).map_err(|e| format!("{}: {}", arquivo.display(), e))?;
+        *sessao = Some(Sessao { arquivo: arquivo.to_path_buf(), dados, decodificador: Decodificador::default(), pacote: 0 });
+    }
+    let s = sessao.as_mut().expect("sessão criada acima");
+
+    let total = s.dados.len() / TAMANHO_PACOTE;
+    let alvo = ((posicao_ms * PACOTES_POR_SEGUNDO / 1000) as usize).min(total);
+    // Voltou no tempo (reiniciar): recomeça do zero
+    if alvo < s.pacote {
+        s.decodificador = Decodificador::default();
+        s.pacote = 0;
+    }
+    for pacote in s.dados[s.pacote * TAMANHO_PACOTE..alvo * TAMANHO_PACOTE].chunks_exact(TAMANHO_PACOTE) {
+        s.decodificador.aplicar(pacote);
+    }
+    s.pacote = alvo;
+
+    let d = &s.decodificador;
+    if versao == Some(d.versao()) {
+        return Ok(None);
+    }
+    let empacotados: Vec<u8> = d.tela().chunks_exact(2).map(|p| (p[0] << 4) | (p[1] & 0x0F)).collect();
+    Ok(Some(QuadroCdg {
+        largura: LARGURA,
+        altura: ALTURA,
+        versao: d.versao(),
+        paleta: (0..16).map(|i| d.cor(i)).map(|[r, g, b]| format!("#{:02x}{:02x}{:02x}", r, g, b)).collect(),
+        pixels: base64::engine::general_purpose::STANDARD.encode(empacotados),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pacote(instrucao: u8, dados: &[u8]) -> [u8; TAMANHO_PACOTE] {
+        let mut p = [0u8; TAMANHO_PACOTE];
+        p[0] = COMANDO_CDG;
+        p[1] = instrucao;
+        p[4..4 + dados.len()].copy_from_slice(dados);
+        p
+    }
+
+    fn pixel(d: &Decodificador, x: usize, y: usize) -> u8 {
+        d.pixels[y * LARGURA + x]
+    }
+
+    #[test]
+    fn memory_preset_preenche_a_tela() {
+        let mut d = Decodificador::default();
+        d.aplicar(&pacote(MEMORY_PRESET, &[5, 0]));
+        assert!(d.pixels.iter().all(|p| *p == 5));
+        assert_eq!(d.versao(), 1);
+    }
+
+    #[test]
+    fn ignora_outros_subcanais_e_pacotes_curtos() {
+        let mut d = Decodificador::default();
+        let mut p = pacote(MEMORY_PRESET, &[5]);
+        p[0] = 0x08;
+        d.aplicar(&p);
+        d.aplicar(&pacote(MEMORY_PRESET, &[5])[..10]);
+        d.aplicar(&pacote(0x3F, &[5]));
+        assert!(d.pixels.iter().all(|p| *p == 0));
+        assert_eq!(d.versao(), 0);
+    }
+
+    #[test]
+    fn bits_altos_do_comando_e_dos_dados_sao_ignorados() {
+        let mut d = Decodificador::default();
+        let mut p = pacote(MEMORY_PRESET, &[0xC7]);
+        p[0] |= 0xC0;
+        p[1] |= 0xC0;
+        d.aplicar(&p);
+        assert!(d.pixels.iter().all(|p| *p == 7));
+    }
+
+    #[test]
+    fn carrega_paleta_de_12_bits() {
+        let mut d = Decodificador::default();
+        // Cor 0: R=0xF, G=0x0, B=0x0; cor 1: R=0x0, G=0xF, B=0x0; cor 2: azul
+        let mut dados = [0u8; 16];
+        dados[0] = 0x3C;
+        dados[1] = 0x00;
+        dados[2] = 0x03;
+        dados[3] = 0x30;
+        dados[4] = 0x00;
+        dados[5] = 0x0F;
+        d.aplicar(&pacote(LOAD_COLOR_LOW, &dados));
+        assert_eq!(d.cor(0), [255, 0, 0]);
+        assert_eq!(d.cor(1), [0, 255, 0]);
+        assert_eq!(d.cor(2), [0, 0, 255]);
+
+        dados[0] = 0x3F;
+        dados[1] = 0x3F;
+        d.aplicar(&pacote(LOAD_COLOR_HIGH, &dados));
+        assert_eq!(d.cor(8), [255, 255, 255]);
+        assert_eq!(d.cor(0), [255, 0, 0]);
+    }
+
+    #[test]
+    fn tile_block_desenha_seis_por_doze() {
+        let mut d = Decodificador::default();
+        // Linha 1, coluna 2; primeira linha do bloco 100001, resto 111111
+        let mut dados = [0u8; 16];
+        dados[0] = 3;
+        dados[1] = 9;
+        dados[2] = 1;
+        dados[3] = 2;
+        dados[4] = 0b10_0001;
+        for b in &mut dados[5..16] {
+            *b = 0x3F;
+        }
+        d.aplicar(&pacote(TILE_BLOCK, &dados));
+        let (x0, y0) = (2 * BLOCO_L, BLOCO_A);
+        assert_eq!(pixel(&d, x0, y0), 9);
+        assert_eq!(pixel(&d, x0 + 1, y0), 3);
+        assert_eq!(pixel(&d, x0 + 5, y0), 9);
+        assert_eq!(pixel(&d, x0 + 3, y0 + 11), 9);
+        // Fora do bloco não mexe
+        assert_eq!(pixel(&d, x0 + 6, y0), 0);
+        assert_eq!(pixel(&d, x0, y0 + 12), 0);
+    }
+
+    #[test]
+    fn tile_block_fora_da_tela_e_ignorado() {
+        let mut d = Decodificador::default();
+        d.aplicar(&pacote(TILE_BLOCK, &[1, 2, 18, 0, 0x3F]));
+        d.aplicar(&pacote(TILE_BLOCK, &[1, 2, 0, 50, 0x3F]));
+        assert!(d.pixels.iter().all(|p| *p == 0));
+    }
+
+    #[test]
+    fn tile_block_xor_combina_com_a_tela() {
+        let mut d = Decodificador::default();
+        d.aplicar(&pacote(MEMORY_PRESET, &[0b0101]));
+        let mut dados = [0u8; 16];
+        dados[0] = 0b0011;
+        dados[1] = 0b1111;
+        dados[4] = 0b10_0000;
+        d.aplicar(&pacote(TILE_BLOCK_XOR, &dados));
+        assert_eq!(pixel(&d, 0, 0), 0b0101 ^ 0b1111);
+        assert_eq!(pixel(&d, 1, 0), 0b0101 ^ 0b0011);
+        assert_eq!(pixel(&d, 0, 1), 0b0101 ^ 0b0011);
+    }
+
+    #[test]
+    fn scroll_preset_preenche_o_que_entra() {
+        let mut d = Decodificador::default();
+        d.aplicar(&pacote(TILE_BLOCK, &[9]));
+        // Rola um bloco para a direita, entrando a cor 4
+        d.aplicar(&pacote(SCROLL_PRESET, &[4, 0x10, 0]));
+        assert_eq!(pixel(&d, 0, 0), 4);
+        assert_eq!(pixel(&d, BLOCO_L, 0), 9);
+        assert_eq!(pixel(&d, BLOCO_L - 1, BLOCO_A - 1), 4);
+        assert_eq!(pixel(&d, 2 * BLOCO_L, 0), 0);
+    }
+
+    #[test]
+    fn scroll_copy_da_a_volta() {
+        let mut d = Decodificador::default();
+        let mut dados = [0u8; 16];
+        dados[1] = 7;
+        dados[4..16].fill(0x3F);
+        d.aplicar(&pacote(TILE_BLOCK, &dados));
+        // Para cima um bloco: a primeira linha de blocos vai para a última
+        d.aplicar(&pacote(SCROLL_COPY, &[0, 0, 0x20]));
+        assert_eq!(pixel(&d, 0, 0), 0);
+        assert_eq!(pixel(&d, 0, ALTURA - BLOCO_A), 7);
+        assert_eq!(pixel(&d, BLOCO_L - 1, ALTURA - 1), 7);
+    }
+
+    #[test]
+    fn deslocamento_fino_e_borda_na_tela_visivel() {
+        let mut d = Decodificador::default();
+        d.aplicar(&pacote(BORDER_PRESET, &[2]));
+        // Pixel (7, 13) = 9; com deslocamento (1, 1) aparece em (6, 12)
+        d.pixels[13 * LARGURA + 7] = 9;
+        d.aplicar(&pacote(SCROLL_COPY, &[0, 0x01, 0x01]));
+        let tela = d.tela();
+        assert_eq!(tela[12 * LARGURA + 6], 9);
+        assert_eq!(tela[0], 2);
+        assert_eq!(tela[(ALTURA - 1) * LARGURA + LARGURA - 1], 2);
+        assert_eq!(tela[12 * LARGURA + 5], 2);
+    }
+
+    #[test]
+    fn deslocamento_fino_limitado_ao_bloco() {
+        let mut d = Decodificador::default();
+        d.aplicar(&pacote(SCROLL_COPY, &[0, 0x07, 0x0F]));
+        assert_eq!((d.desloc_x, d.desloc_y), (BLOCO_L - 1, BLOCO_A - 1));
+        // Não pode ler fora da memória
+        assert_eq!(d.tela().len(), LARGURA * ALTURA);
+    }
+
+    #[test]
+    fn quadro_avanca_volta_e_reaproveita_versao() {
+        let dir = std::env::temp_dir().join(format!("bk-cdg-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let arquivo = dir.join("teste.cdg");
+        // 1 s de pacotes: preset com cor 1 no início e cor 2 em 0,5 s
+        let mut dados = vec![0u8; PACOTES_POR_SEGUNDO as usize * TAMANHO_PACOTE];
+        dados[..TAMANHO_PACOTE].copy_from_slice(&pacote(MEMORY_PRESET, &[1]));
+        let meio = PACOTES_POR_SEGUNDO as usize / 2 * TAMANHO_PACOTE;
+        dados[meio..meio + TAMANHO_PACOTE].copy_from_slice(&pacote(MEMORY_PRESET, &[2]));
+        std::fs::write(&arquivo, &dados).unwrap();
+
+        let pixels = |q: &QuadroCdg| base64::engine::general_purpose::STANDARD.decode(&q.pixels).unwrap();
+        // Par de pixels dentro da área visível
+        let i = (20 * LARGURA + 100) / 2;
+
+        let q = quadro(&arquivo, 100, None).unwrap().unwrap();
+        assert_eq!((q.largura, q.altura, q.paleta.len()), (LARGURA, ALTURA, 16));
+        assert_eq!(pixels(&q).len(), LARGURA * ALTURA / 2);
+        assert_eq!(pixels(&q)[i], 0x11);
+        assert!(quadro(&arquivo, 200, Some(q.versao)).unwrap().is_none());
+
+        let q2 = quadro(&arquivo, 900, Some(q.versao)).unwrap().unwrap();
+        assert_eq!(pixels(&q2)[i], 0x22);
+        // Além do fim fica no último pacote
+        assert!(quadro(&arquivo, 60_000, Some(q2.versao)).unwrap().is_none());
+
+        let q3 = quadro(&arquivo, 100, Some(q2.versao)).unwrap().unwrap();
+        assert_eq!(pixels(&q3)[i], 0x11);
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
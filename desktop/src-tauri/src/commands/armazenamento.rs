@@ -0,0 +1,43 @@
+use crate::armazenamento::{self, StatusArmazenamento};
+use crate::db;
+use crate::download_manager::DownloadManager;
+use crate::AppState;
+
+/// Espaço usado, limite, reserva de disco, política e músicas fixadas.
+#[tauri::command]
+pub fn status_armazenamento(state: tauri::State<'_, AppState>) -> Result<StatusArmazenamento, String> {
+    armazenamento::status(&state.data_dir)
+}
+
+/// `limite_mb`: tamanho máximo da biblioteca (0 = sem limite). `reserva_mb`:
+/// espaço que sempre fica livre no disco. `politica`: "menos_tocadas" ou
+/// "sem_tocar". Diminuir o limite apaga músicas na hora para caber.
+#[tauri::command]
+pub async fn configurar_armazenamento(
+    limite_mb: Option<u64>,
+    reserva_mb: Option<u64>,
+    politica: Option<String>,
+    state: tauri::State<'_, AppState>,
+    manager: tauri::State<'_, DownloadManager>,
+) -> Result<StatusArmazenamento, String> {
+    armazenamento::configurar(limite_mb, reserva_mb, politica.as_deref())?;
+    tauri::async_runtime::spawn_blocking(armazenamento::aplicar_limite)
+        .await
+        .map_err(|e| e.to_string())??;
+    manager.espaco_alterado();
+    armazenamento::status(&state.data_dir)
+}
+
+/// Fixa (protege da liberação de espaço) ou desafixa uma música.
+#[tauri::command]
+pub fn fixar_musica(
+    codigo: String,
+    fixada: bool,
+    manager: tauri::State<'_, DownloadManager>,
+) -> Result<Vec<String>, String> {
+    db::fixar_musica(&codigo, fixada)?;
+    if !fixada {
+        manager.espaco_alterado();
+    }
+    db::listar_fixadas()
+}
@@ -1,6 +1,9 @@
+use crate::backend::{Backend, LicenseBackend};
 use crate::db;
-use crate::supabase;
+use crate::rede_local;
+use crate::AppState;
 use serde::Serialize;
+use std::path::Path;
 
 #[derive(Serialize)]
 pub struct AtivacaoStatus {
@@ -17,7 +20,7 @@ pub struct AtivacaoStatus {
 }
 
 #[tauri::command]
-pub async fn verificar_ativacao() -> Result<AtivacaoStatus, String> {
+pub async fn verificar_ativacao(backend: tauri::State<'_, Backend>) -> Result<AtivacaoStatus, String> {
     // Check local first
     let ativacao = db::get_ativacao()?;
     
@@ -32,88 +35,110 @@ pub async fn verificar_ativacao() -> Result<AtivacaoStatus, String> {
             horas_restantes: None,
         }),
         Some(atv) => {
-            // Always try online validation first (Supabase is source of truth)
-            // This updates the local SQLite with fresh data (admin may have added days)
-            if let Ok(online) = try_online_validation(&atv.chave).await {
-                log::info!("[ATIVACAO] Online validation succeeded: dias={:?}, horas={:?}", 
-                    online.dias_restantes, online.horas_restantes);
-                return Ok(online);
-            }
-            
-            log::info!("[ATIVACAO] Offline mode - using local data");
-            
-            // Offline: use local SQLite data
-            let now = chrono::Utc::now().timestamp_millis();
-            
-            if atv.tipo == "assinatura" {
-                if let Some(exp) = atv.data_expiracao {
-                    if exp < now {
-                        return Ok(AtivacaoStatus {
-                            ativada: false,
-                            expirada: true,
-                            modo: "offline".to_string(),
-                            chave: Some(atv.chave.clone()),
-                            tipo: atv.tipo.clone(),
-                            dias_restantes: Some(0),
-                            horas_restantes: None,
-                        });
-                    }
-                    let diff = exp - now;
-                    let dias = (diff as f64 / (1000.0 * 60.0 * 60.0 * 24.0)).ceil() as i64;
-                    
-                    return Ok(AtivacaoStatus {
-                        ativada: true,
-                        expirada: false,
-                        modo: "offline".to_string(),
-                        chave: Some(atv.chave.clone()),
-                        tipo: atv.tipo.clone(),
-                        dias_restantes: Some(dias),
-                        horas_restantes: None,
-                    });
-                }
-                // data_expiracao missing but has dias_restantes from last sync
-                if let Some(dias) = atv.dias_restantes {
-                    // Estimate from last validation time
-                    let elapsed_days = ((now - atv.data_validacao) as f64 / (1000.0 * 60.0 * 60.0 * 24.0)).floor() as i64;
-                    let remaining = (dias - elapsed_days).max(0);
-                    return Ok(AtivacaoStatus {
-                        ativada: remaining > 0,
-                        expirada: remaining <= 0,
-                        modo: "offline".to_string(),
-                        chave: Some(atv.chave.clone()),
-                        tipo: atv.tipo.clone(),
-                        dias_restantes: Some(remaining),
-                        horas_restantes: None,
-                    });
-                }
-            } else if atv.tipo == "maquina" {
-                if let Some(horas) = atv.horas_restantes {
-                    let elapsed = (now - atv.data_validacao) as f64 / (1000.0 * 60.0 * 60.0);
-                    let remaining = horas - elapsed;
-                    return Ok(AtivacaoStatus {
-                        ativada: remaining > 0.0,
-                        expirada: remaining <= 0.0,
-                        modo: "offline".to_string(),
-                        chave: Some(atv.chave.clone()),
-                        tipo: atv.tipo.clone(),
-                        dias_restantes: None,
-                        horas_restantes: Some(remaining.max(0.0)),
-                    });
+            let mut evento = novo_evento("verificar_ativacao", Some(&atv.chave));
+            let result = verificar_ativacao_salva(backend.licenca.as_ref(), atv, &mut evento).await;
+            match &result {
+                Ok(status) => preencher_com_status(&mut evento, status),
+                Err(e) => {
+                    evento.resultado = "erro".to_string();
+                    evento.detalhe = Some(e.clone());
                 }
             }
+            registrar_evento(evento);
+            result
+        }
+    }
+}
+
+/// Valida a ativação já salva: online quando possível, senão com os dados locais.
+pub(crate) async fn verificar_ativacao_salva(
+    licenca: &dyn LicenseBackend,
+    atv: db::Ativacao,
+    evento: &mut db::AtivacaoEvento,
+) -> Result<AtivacaoStatus, String> {
+    // Always try online validation first (Supabase is source of truth)
+    // This updates the local SQLite with fresh data (admin may have added days)
+    match try_online_validation(licenca, &atv.chave, evento).await {
+        Ok(online) => {
+            log::info!("[ATIVACAO] Online validation succeeded: dias={:?}, horas={:?}", 
+                online.dias_restantes, online.horas_restantes);
+            return Ok(online);
+        }
+        Err(e) => evento.detalhe = Some(e),
+    }
+    
+    log::info!("[ATIVACAO] Offline mode - using local data");
+    
+    // Offline: use local SQLite data
+    let now = chrono::Utc::now().timestamp_millis();
+    
+    if atv.tipo == "assinatura" {
+        if let Some(exp) = atv.data_expiracao {
+            if exp < now {
+                return Ok(AtivacaoStatus {
+                    ativada: false,
+                    expirada: true,
+                    modo: "offline".to_string(),
+                    chave: Some(atv.chave.clone()),
+                    tipo: atv.tipo.clone(),
+                    dias_restantes: Some(0),
+                    horas_restantes: None,
+                });
+            }
+            let diff = exp - now;
+            let dias = (diff as f64 / (1000.0 * 60.0 * 60.0 * 24.0)).ceil() as i64;
             
-            // Fallback: activated but no time info
-            Ok(AtivacaoStatus {
+            return Ok(AtivacaoStatus {
                 ativada: true,
                 expirada: false,
                 modo: "offline".to_string(),
-                chave: Some(atv.chave),
-                tipo: atv.tipo,
-                dias_restantes: atv.dias_restantes,
-                horas_restantes: atv.horas_restantes,
-            })
+                chave: Some(atv.chave.clone()),
+                tipo: atv.tipo.clone(),
+                dias_restantes: Some(dias),
+                horas_restantes: None,
+            });
+        }
+        // data_expiracao missing but has dias_restantes from last sync
+        if let Some(dias) = atv.dias_restantes {
+            // Estimate from last validation time
+            let elapsed_days = ((now - atv.data_validacao) as f64 / (1000.0 * 60.0 * 60.0 * 24.0)).floor() as i64;
+            let remaining = (dias - elapsed_days).max(0);
+            return Ok(AtivacaoStatus {
+                ativada: remaining > 0,
+                expirada: remaining <= 0,
+                modo: "offline".to_string(),
+                chave: Some(atv.chave.clone()),
+                tipo: atv.tipo.clone(),
+                dias_restantes: Some(remaining),
+                horas_restantes: None,
+            });
+        }
+    } else if atv.tipo == "maquina" {
+        if let Some(horas) = atv.horas_restantes {
+            let elapsed = (now - atv.data_validacao) as f64 / (1000.0 * 60.0 * 60.0);
+            let remaining = horas - elapsed;
+            return Ok(AtivacaoStatus {
+                ativada: remaining > 0.0,
+                expirada: remaining <= 0.0,
+                modo: "offline".to_string(),
+                chave: Some(atv.chave.clone()),
+                tipo: atv.tipo.clone(),
+                dias_restantes: None,
+                horas_restantes: Some(remaining.max(0.0)),
+            });
         }
     }
+    
+    // Fallback: activated but no time info
+    Ok(AtivacaoStatus {
+        ativada: true,
+        expirada: false,
+        modo: "offline".to_string(),
+        chave: Some(atv.chave),
+        tipo: atv.tipo,
+        dias_restantes: atv.dias_restantes,
+        horas_restantes: atv.horas_restantes,
+    })
 }
 
 /// Parse datetime string in various formats (RFC3339, ISO8601 with/without tz, date-only)
@@ -139,9 +164,13 @@ fn parse_datetime(s: &str) -> Option<chrono::DateTime<chrono::Utc>> {
     None
 }
 
-async fn try_online_validation(chave: &str) -> Result<AtivacaoStatus, String> {
+async fn try_online_validation(
+    licenca: &dyn LicenseBackend,
+    chave: &str,
+    evento: &mut db::AtivacaoEvento,
+) -> Result<AtivacaoStatus, String> {
     log::info!("[ATIVACAO] Trying online validation for key: {}...", &chave[..chave.len().min(8)]);
-    let result = supabase::validar_chave_supabase(chave).await?;
+    let result = licenca.buscar_chave(chave).await?;
     match result {
         None => Err("Key not found".to_string()),
         Some(chave_data) => {
@@ -163,15 +192,18 @@ async fn try_online_validation(chave: &str) -> Result<AtivacaoStatus, String> {
 
             // ── Verificação de machine binding ─────────────────────────────
             let machine_id = db::get_or_create_machine_id().ok();
+            evento.machine_id_local = machine_id.clone();
             if let (Some(ref local_mid), Some(ref remote_mid)) = (&machine_id, &chave_data.machine_id) {
                 if local_mid != remote_mid {
                     log::warn!("[ATIVACAO] Machine conflict: local={}, remote={}", local_mid, remote_mid);
+                    evento.resultado = "conflito_maquina".to_string();
+                    evento.machine_id_remoto = Some(remote_mid.clone());
                     return Err("Chave em uso em outro dispositivo. Contate o administrador.".to_string());
                 }
             }
             // Primeira ativação nesta máquina: vincula o machine_id
             if let (Some(ref mid), None) = (&machine_id, &chave_data.machine_id) {
-                supabase::vincular_machine_id(&chave_data.id, mid).await.ok();
+                licenca.vincular_machine_id(&chave_data.id, mid).await.ok();
             }
             
             let now = chrono::Utc::now();
@@ -232,9 +264,10 @@ async fn try_online_validation(chave: &str) -> Result<AtivacaoStatus, String> {
                 data_expiracao_ms,
             )?;
             log::info!("[ATIVACAO] Local DB updated from Supabase");
+            rede_local::atualizar_segredo(licenca, &chave_data.chave, machine_id.as_deref()).await;
             
             // Update last use timestamp on Supabase
-            supabase::update_ultimo_uso(&chave_data.id).await.ok();
+            licenca.update_ultimo_uso(&chave_data.id).await.ok();
             
             Ok(AtivacaoStatus {
                 ativada: true,
@@ -266,18 +299,52 @@ pub struct ChaveInfo {
 }
 
 #[tauri::command]
-pub async fn validar_chave(chave: String) -> Result<ValidacaoResult, String> {
+pub async fn validar_chave(chave: String, backend: tauri::State<'_, Backend>) -> Result<ValidacaoResult, String> {
     // Normalize key
     let normalizada = chave.trim().to_uppercase().replace(" ", "-");
     
-    let result = supabase::validar_chave_supabase(&normalizada).await;
+    let mut evento = novo_evento("validar_chave", Some(&normalizada));
+    evento.modo = "online".to_string();
+    let result = validar_chave_online(backend.licenca.as_ref(), &normalizada, &mut evento).await;
+    match &result {
+        Ok(r) => {
+            if evento.resultado.is_empty() {
+                evento.resultado = if r.valida { "ativada" } else { "invalida" }.to_string();
+            }
+            if evento.detalhe.is_none() {
+                evento.detalhe = r.error.clone();
+            }
+            if let Some(info) = &r.chave {
+                evento.dias_restantes = info.dias_restantes;
+                evento.horas_restantes = info.horas_restantes;
+            }
+        }
+        Err(e) => {
+            evento.resultado = "erro".to_string();
+            evento.detalhe = Some(e.clone());
+        }
+    }
+    registrar_evento(evento);
+    result
+}
+
+pub(crate) async fn validar_chave_online(
+    licenca: &dyn LicenseBackend,
+    normalizada: &str,
+    evento: &mut db::AtivacaoEvento,
+) -> Result<ValidacaoResult, String> {
+    let result = licenca.buscar_chave(normalizada).await;
     
     match result {
-        Err(e) => Ok(ValidacaoResult {
-            valida: false,
-            error: Some(format!("Erro de conexao: {}", e)),
-            chave: None,
-        }),
+        Err(e) => {
+            evento.modo = "offline".to_string();
+            evento.resultado = "erro".to_string();
+            Ok(ValidacaoResult {
+                valida: false,
+                error: Some(format!("Erro de conexao: {}", e)),
+                chave: None,
+            })
+        }
         Ok(None) => Ok(ValidacaoResult {
             valida: false,
             error: Some("Chave nao encontrada".to_string()),
@@ -285,6 +352,7 @@ pub async fn validar_chave(chave: String) -> Result<ValidacaoResult, String> {
         }),
         Ok(Some(chave_data)) => {
             if chave_data.status != "ativa" {
+                evento.resultado = "expirada".to_string();
                 return Ok(ValidacaoResult {
                     valida: false,
                     error: Some("Chave expirada ou inativa".to_string()),
@@ -294,9 +362,12 @@ pub async fn validar_chave(chave: String) -> Result<ValidacaoResult, String> {
 
             // ── Verificação de machine binding ─────────────────────────────
             let machine_id = db::get_or_create_machine_id().ok();
+            evento.machine_id_local = machine_id.clone();
             if let (Some(ref local_mid), Some(ref remote_mid)) = (&machine_id, &chave_data.machine_id) {
                 if local_mid != remote_mid {
                     log::warn!("[ATIVACAO] Tentativa de usar chave de outra máquina");
+                    evento.resultado = "conflito_maquina".to_string();
+                    evento.machine_id_remoto = Some(remote_mid.clone());
                     return Ok(ValidacaoResult {
                         valida: false,
                         error: Some("Chave em uso em outro dispositivo. Contate o administrador para desbloquear.".to_string()),
@@ -306,7 +377,7 @@ pub async fn validar_chave(chave: String) -> Result<ValidacaoResult, String> {
             }
             // Primeira ativação: vincula machine_id
             if let (Some(ref mid), None) = (&machine_id, &chave_data.machine_id) {
-                supabase::vincular_machine_id(&chave_data.id, mid).await.ok();
+                licenca.vincular_machine_id(&chave_data.id, mid).await.ok();
             }
 
             let now = chrono::Utc::now();
@@ -339,8 +410,9 @@ pub async fn validar_chave(chave: String) -> Result<ValidacaoResult, String> {
                 horas_restantes,
                 data_expiracao_ms,
             )?;
+            rede_local::atualizar_segredo(licenca, &chave_data.chave, machine_id.as_deref()).await;
             
-            supabase::update_ultimo_uso(&chave_data.id).await.ok();
+            licenca.update_ultimo_uso(&chave_data.id).await.ok();
             
             Ok(ValidacaoResult {
                 valida: true,
@@ -357,5 +429,114 @@ pub async fn validar_chave(chave: String) -> Result<ValidacaoResult, String> {
 
 #[tauri::command]
 pub fn remover_ativacao() -> Result<(), String> {
-    db::remover_ativacao_db()
+    let chave = db::get_ativacao()?.map(|a| a.chave);
+    db::remover_ativacao_db()?;
+    rede_local::definir_segredo(None);
+    let mut evento = novo_evento("remover_ativacao", chave.as_deref());
+    evento.resultado = "removida".to_string();
+    registrar_evento(evento);
+    Ok(())
+}
+
+// ─── Auditoria ──────────────────────────────────────────────────────────────
+
+/// Mascara a chave para log/suporte: mantém os 4 primeiros e os 4 últimos
+/// caracteres (ABCD-****-****-MNOP).
+fn mascarar_chave(chave: &str) -> String {
+    let total = chave.chars().filter(|c| c.is_ascii_alphanumeric()).count();
+    let (inicio, fim) = if total > 8 { (4, 4) } else { (total.min(2), 0) };
+    let mut visto = 0;
+    chave
+        .chars()
+        .map(|c| {
+            if !c.is_ascii_alphanumeric() {
+                return c;
+            }
+            visto += 1;
+            if visto <= inicio || visto > total - fim { c } else { '*' }
+        })
+        .collect()
+}
+
+pub(crate) fn novo_evento(acao: &str, chave: Option<&str>) -> db::AtivacaoEvento {
+    db::AtivacaoEvento {
+        id: uuid::Uuid::new_v4().to_string(),
+        data_evento: chrono::Utc::now().timestamp_millis(),
+        acao: acao.to_string(),
+        chave_mascarada: chave.map(mascarar_chave),
+        modo: "offline".to_string(),
+        resultado: String::new(),
+        detalhe: None,
+        machine_id_local: db::get_or_create_machine_id().ok(),
+        machine_id_remoto: None,
+        dias_restantes: None,
+        horas_restantes: None,
+    }
+}
+
+fn preencher_com_status(evento: &mut db::AtivacaoEvento, status: &AtivacaoStatus) {
+    evento.modo = status.modo.clone();
+    evento.dias_restantes = status.dias_restantes;
+    evento.horas_restantes = status.horas_restantes;
+    if evento.resultado.is_empty() {
+        evento.resultado = if status.ativada {
+            "ativada"
+        } else if status.expirada {
+            "expirada"
+        } else {
+            "invalida"
+        }
+        .to_string();
+    }
+}
+
+/// Grava o evento; falha de auditoria nunca interrompe a ativação.
+fn registrar_evento(evento: db::AtivacaoEvento) {
+    log::info!(
+        "[ATIVACAO] Evento: acao={} modo={} resultado={} chave={:?}",
+        evento.acao, evento.modo, evento.resultado, evento.chave_mascarada
+    );
+    if let Err(e) = db::registrar_evento_ativacao(&evento) {
+        log::error!("[ATIVACAO] Falha ao registrar evento: {}", e);
+    }
+}
+
+#[tauri::command]
+pub fn listar_eventos_ativacao(limite: Option<i64>) -> Result<Vec<db::AtivacaoEvento>, String> {
+    db::listar_eventos_ativacao(limite.unwrap_or(200))
+}
+
+/// Gera um pacote de suporte (JSON) em `<data_dir>/suporte` com o estado da
+/// ativação e o histórico de eventos. Retorna o caminho do arquivo.
+#[tauri::command]
+pub fn exportar_pacote_suporte(state: tauri::State<'_, AppState>) -> Result<String, String> {
+    let ativacao = db::get_ativacao()?.map(|a| {
+        serde_json::json!({
+            "chaveMascarada": mascarar_chave(&a.chave),
+            "tipo": a.tipo,
+            "diasRestantes": a.dias_restantes,
+            "horasRestantes": a.horas_restantes,
+            "dataExpiracao": a.data_expiracao,
+            "dataValidacao": a.data_validacao,
+        })
+    });
+    let pacote = serde_json::json!({
+        "geradoEm": chrono::Utc::now().to_rfc3339(),
+        "versaoApp": env!("CARGO_PKG_VERSION"),
+        "sistema": std::env::consts::OS,
+        "arquitetura": std::env::consts::ARCH,
+        "machineId": db::get_or_create_machine_id().ok(),
+        "ativacao": ativacao,
+        "musicasOffline": db::count_musicas_local().ok(),
+        "eventos": db::listar_eventos_ativacao(1000)?,
+    });
+
+    let dir = Path::new(&state.data_dir).join("suporte");
+    std::fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+    let nome = format!("suporte-{}.json", chrono::Local::now().format("%Y%m%d-%H%M%S"));
+    let path = dir.join(nome);
+    let conteudo = serde_json::to_string_pretty(&pacote).map_err(|e| e.to_string())?;
+    std::fs::write(&path, conteudo).map_err(|e| e.to_string())?;
+    log::info!("[ATIVACAO] Pacote de suporte gerado: {}", path.display());
+    Ok(path.to_string_lossy().to_string())
 }
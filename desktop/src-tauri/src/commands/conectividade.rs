@@ -0,0 +1,8 @@
+use crate::backend::Backend;
+use crate::http::ConectividadeStatus;
+
+/// Estado atual da rede. Mudanças também chegam pelo evento "conectividade-alterada".
+#[tauri::command]
+pub fn get_conectividade(backend: tauri::State<'_, Backend>) -> ConectividadeStatus {
+    backend.conectividade.status()
+}
@@ -0,0 +1,68 @@
+use crate::download_manager::{DownloadManager, StatusDownloads};
+
+/// Estado da fila. Mudanças também chegam pelo evento "downloads-status".
+#[tauri::command]
+pub fn status_downloads(manager: tauri::State<'_, DownloadManager>) -> StatusDownloads {
+    manager.status()
+}
+
+/// Libera o gerenciador para baixar (e atualiza a fila a partir do catálogo).
+#[tauri::command]
+pub fn retomar_downloads(manager: tauri::State<'_, DownloadManager>) -> StatusDownloads {
+    manager.retomar();
+    manager.status()
+}
+
+#[tauri::command]
+pub fn pausar_downloads(manager: tauri::State<'_, DownloadManager>) -> StatusDownloads {
+    manager.pausar();
+    manager.status()
+}
+
+/// Cancela uma música da fila; sem `codigo`, cancela a fila inteira.
+#[tauri::command]
+pub fn cancelar_download(
+    codigo: Option<String>,
+    manager: tauri::State<'_, DownloadManager>,
+) -> Result<StatusDownloads, String> {
+    manager.cancelar(codigo.as_deref())?;
+    Ok(manager.status())
+}
+
+/// Devolve à fila músicas canceladas ou que esgotaram as tentativas.
+#[tauri::command]
+pub fn reenfileirar_download(
+    codigo: Option<String>,
+    manager: tauri::State<'_, DownloadManager>,
+) -> Result<StatusDownloads, String> {
+    manager.reenfileirar(codigo.as_deref())?;
+    Ok(manager.status())
+}
+
+/// Põe a música na frente da fila, mesmo se estava cancelada ou em erro.
+#[tauri::command]
+pub fn priorizar_download(
+    codigo: String,
+    manager: tauri::State<'_, DownloadManager>,
+) -> Result<StatusDownloads, String> {
+    manager.priorizar(&codigo)?;
+    Ok(manager.status())
+}
+
+/// `paralelos`: downloads simultâneos (1 a 8). `limite_kbps`: teto de banda
+/// somando todos os downloads, em kbit/s (0 = sem limite).
+#[tauri::command]
+pub fn configurar_downloads(
+    paralelos: Option<usize>,
+    limite_kbps: Option<u64>,
+    manager: tauri::State<'_, DownloadManager>,
+) -> Result<StatusDownloads, String> {
+    manager.configurar(paralelos, limite_kbps)?;
+    Ok(manager.status())
+}
+
+/// Relê o catálogo agora e põe na fila o que falta. Retorna quantas entraram.
+#[tauri::command]
+pub async fn atualizar_fila_downloads(manager: tauri::State<'_, DownloadManager>) -> Result<i64, String> {
+    manager.atualizar_fila().await
+}
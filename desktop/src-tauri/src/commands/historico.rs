@@ -1,6 +1,63 @@
+use crate::backend::{Backend, ItemHistorico, LicenseBackend};
+use crate::commands::player;
 use crate::db;
+use once_cell::sync::Lazy;
 
+/// Execuções por chamada ao servidor.
+const LOTE_ENVIO: i64 = 200;
+
+/// Um envio por vez: dois ao mesmo tempo mandariam as mesmas execuções.
+static ENVIO: Lazy<tokio::sync::Mutex<()>> = Lazy::new(|| tokio::sync::Mutex::new(()));
+
+/// Registra a música tocada, com o tom em que foi cantada, e envia ao
+/// histórico da conta o que ainda não foi.
 #[tauri::command]
-pub fn salvar_historico(codigo: String) -> Result<(), String> {
-    db::salvar_historico_db(&codigo)
+pub async fn salvar_historico(codigo: String, backend: tauri::State<'_, Backend>) -> Result<(), String> {
+    db::salvar_historico_db(&codigo, player::tom())?;
+    let licenca = backend.licenca.clone();
+    tauri::async_runtime::spawn(async move { enviar_pendentes(licenca.as_ref()).await });
+    Ok(())
+}
+
+/// Envia as execuções pendentes. Sem ativação ou sem rede, ficam para o
+/// próximo envio.
+pub async fn enviar_pendentes(licenca: &dyn LicenseBackend) {
+    let _envio = ENVIO.lock().await;
+    let (Ok(Some(ativacao)), Ok(machine_id)) = (db::get_ativacao(), db::get_or_create_machine_id()) else {
+        return;
+    };
+    loop {
+        let pendentes = match db::historico_nao_enviado(LOTE_ENVIO) {
+            Ok(p) if p.is_empty() => return,
+            Ok(p) => p,
+            Err(e) => {
+                log::warn!("[HISTORICO] {}", e);
+                return;
+            }
+        };
+        let completo = pendentes.len() as i64 == LOTE_ENVIO;
+        let ids: Vec<String> = pendentes.iter().map(|h| h.id.clone()).collect();
+        let itens: Vec<ItemHistorico> = pendentes
+            .into_iter()
+            .map(|h| ItemHistorico { codigo: h.codigo, data_execucao: h.data_execucao, tom: h.tom })
+            .collect();
+        match licenca.registrar_historico(&ativacao.chave, &machine_id, &itens).await {
+            Ok(Some(gravados)) => log::info!("[HISTORICO] {} execuções enviadas", gravados),
+            Ok(None) => {
+                log::warn!("[HISTORICO] Chave não aceita pelo servidor; histórico fica local");
+                return;
+            }
+            Err(e) => {
+                log::warn!("[HISTORICO] Não enviado: {}", e);
+                return;
+            }
+        }
+        if let Err(e) = db::marcar_historico_enviado(&ids) {
+            log::warn!("[HISTORICO] {}", e);
+            return;
+        }
+        if !completo {
+            return;
+        }
+    }
 }
@@ -4,3 +4,9 @@ pub mod ativacao;
 pub mod sync;
 pub mod video;
 pub mod player;
+pub mod conectividade;
+pub mod downloads;
+pub mod armazenamento;
+pub mod pacote_offline;
+pub mod rede_local;
+pub mod normalizacao;
@@ -1,4 +1,8 @@
 use crate::db;
+use crate::download_manager::DownloadManager;
+use crate::letra;
+use crate::midia;
+use crate::sondagem::InfoMidia;
 use crate::AppState;
 use std::path::Path;
 
@@ -29,7 +33,11 @@ pub fn buscar_musicas(query: String) -> Result<Vec<db::MusicaSimple>, String> {
 }
 
 #[tauri::command]
-pub fn get_musica_by_codigo(codigo: String, state: tauri::State<'_, AppState>) -> Result<Option<db::MusicaSimple>, String> {
+pub fn get_musica_by_codigo(
+    codigo: String,
+    state: tauri::State<'_, AppState>,
+    manager: tauri::State<'_, DownloadManager>,
+) -> Result<Option<db::MusicaSimple>, String> {
     let codigo = codigo.trim();
     // Normalizar código numérico para 5 dígitos (1009 → 01009) para consistência com a base
     let codigo = if codigo.chars().all(|c| c.is_ascii_digit()) && codigo.len() >= 4 && codigo.len() <= 5 {
@@ -40,19 +48,26 @@ pub fn get_musica_by_codigo(codigo: String, state: tauri::State<'_, AppState>) -
     if let Some(m) = db::get_musica_by_codigo_db(&codigo)? {
         return Ok(Some(m));
     }
-    // Fallback: arquivo existe na pasta mas não está no banco (ex.: 01001.mp4)
+    // Fallback: arquivo existe na pasta mas não está no banco (ex.: 01001.mkv)
     let musicas_dir = Path::new(&state.data_dir).join("musicas");
     for variante in codigo_variantes(&codigo) {
-        let path = musicas_dir.join(format!("{}.mp4", variante));
-        if path.exists() {
-            let arquivo = path.to_string_lossy().to_string();
-            return Ok(Some(db::MusicaSimple {
-                codigo: variante.clone(),
-                artista: "Desconhecido".to_string(),
-                titulo: variante,
-                arquivo,
-            }));
-        }
+        let Some(path) = midia::localizar(&musicas_dir, &variante) else { continue };
+        let Some(tipo) = midia::identificar(&path) else { continue };
+        return Ok(Some(db::MusicaSimple {
+            codigo: variante.clone(),
+            artista: "Desconhecido".to_string(),
+            titulo: variante,
+            arquivo: path.to_string_lossy().to_string(),
+            extensao: tipo.extensao,
+            tipo_midia: tipo.tipo.como_str().to_string(),
+            letra: letra::arquivo_letra(&path).map(|p| p.to_string_lossy().to_string()),
+            duracao: None,
+        }));
+    }
+    // Existe no catálogo mas ainda não foi baixada: passa na frente da fila
+    if db::get_catalogo_by_codigo(&codigo)?.is_some() {
+        db::registrar_demanda(&codigo, "pedido")?;
+        manager.priorizar(&codigo)?;
     }
     Ok(None)
 }
@@ -66,3 +81,33 @@ pub fn musica_aleatoria() -> Result<Option<String>, String> {
 pub fn get_all_musicas_count() -> Result<i64, String> {
     db::count_musicas_local()
 }
+
+/// Busca no catálogo completo (inclusive músicas ainda não baixadas),
+/// a partir do espelho local; funciona offline.
+#[tauri::command]
+pub fn buscar_catalogo(query: String) -> Result<Vec<db::MusicaCatalogoBusca>, String> {
+    if query.trim().len() < 2 {
+        return Ok(vec![]);
+    }
+    let resultados = db::buscar_catalogo_db(&query)?;
+    // Buscadas e ainda não baixadas sobem na ordem de download
+    for m in resultados.iter().filter(|m| !m.baixada) {
+        db::registrar_demanda(&m.codigo, "busca")?;
+    }
+    Ok(resultados)
+}
+
+#[tauri::command]
+pub fn listar_nao_baixadas(limite: Option<i64>, offset: Option<i64>) -> Result<Vec<db::MusicaCatalogo>, String> {
+    db::listar_nao_baixadas(limite.unwrap_or(100).clamp(1, 1000), offset.unwrap_or(0).max(0))
+}
+
+/// Duração, resolução, codecs e loudness medidos no arquivo da música;
+/// None se não está baixada ou ainda não foi sondada.
+#[tauri::command]
+pub fn get_info_midia(codigo: String) -> Result<Option<InfoMidia>, String> {
+    match db::get_musica_by_codigo_db(codigo.trim())? {
+        Some(m) => db::get_info_midia(&m.codigo),
+        None => Ok(None),
+    }
+}
@@ -0,0 +1,15 @@
+use crate::normalizacao::{self, StatusNormalizacao};
+
+/// Normalização ligada, alvo (LUFS) e quantas músicas já foram medidas.
+#[tauri::command]
+pub fn status_normalizacao() -> Result<StatusNormalizacao, String> {
+    normalizacao::status()
+}
+
+/// `ativa`: liga/desliga o ganho por música. `alvo_lufs`: loudness que todas
+/// devem ter ao tocar (-30 a -8; padrão -16). Vale a partir da próxima música.
+#[tauri::command]
+pub fn configurar_normalizacao(ativa: Option<bool>, alvo_lufs: Option<f64>) -> Result<StatusNormalizacao, String> {
+    normalizacao::configurar(ativa, alvo_lufs)?;
+    normalizacao::status()
+}
@@ -0,0 +1,66 @@
+use crate::pacote_offline::{self, ProgressoPacote, ResultadoExportacao, ResultadoImportacao};
+use crate::AppState;
+use serde::Serialize;
+use std::path::PathBuf;
+use tauri::Emitter;
+
+/// Exporta as músicas baixadas (todas, ou só `codigos`) para a pasta
+/// `destino` (ex.: um pendrive), com manifesto assinado. O andamento é
+/// emitido no evento "pacote-progresso".
+#[tauri::command]
+pub async fn exportar_pacote_offline(
+    destino: String,
+    codigos: Option<Vec<String>>,
+    app: tauri::AppHandle,
+) -> Result<ResultadoExportacao, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let progresso = move |p: ProgressoPacote| {
+            app.emit("pacote-progresso", p).ok();
+        };
+        pacote_offline::exportar(&PathBuf::from(destino), codigos, &progresso)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Importa um pacote offline da pasta `origem`, conferindo assinatura,
+/// tamanho e hash de cada vídeo. O andamento é emitido em "pacote-progresso".
+#[tauri::command]
+pub async fn importar_pacote_offline(
+    origem: String,
+    state: tauri::State<'_, AppState>,
+    app: tauri::AppHandle,
+) -> Result<ResultadoImportacao, String> {
+    let data_dir = state.data_dir.clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        let progresso = move |p: ProgressoPacote| {
+            app.emit("pacote-progresso", p).ok();
+        };
+        pacote_offline::importar(&data_dir, &PathBuf::from(origem), &progresso)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[derive(Serialize)]
+pub struct ChavesPacote {
+    /// Chave desta máquina: cadastre nas máquinas que vão importar os pacotes dela
+    #[serde(rename = "chavePublica")]
+    pub chave_publica: String,
+    pub confiaveis: Vec<String>,
+}
+
+#[tauri::command]
+pub fn chaves_pacote_offline() -> Result<ChavesPacote, String> {
+    Ok(ChavesPacote {
+        chave_publica: pacote_offline::chave_publica()?,
+        confiaveis: pacote_offline::chaves_confiaveis()?,
+    })
+}
+
+/// Passa a aceitar (ou deixa de aceitar) pacotes assinados por `chave`.
+#[tauri::command]
+pub fn confiar_chave_pacote(chave: String, confiar: bool) -> Result<ChavesPacote, String> {
+    pacote_offline::confiar_chave(&chave, confiar)?;
+    chaves_pacote_offline()
+}
@@ -17,8 +17,24 @@
 // Resultado: o usuário vê o vídeo mpv através das áreas transparentes do WebView2.
 //
 // Fallback: se mpv não for encontrado, o frontend usa <video> HTML5.
-
+//
+// Controle: o mpv não tem teclas próprias (--no-input-default-bindings); o
+// frontend manda pausar, buscar, volume etc. pelo IPC JSON (mpv_ipc.rs).
+// O tom muda ao vivo com o filtro rubberband (@tom), que altera a altura sem
+// mexer no andamento e só existe enquanto o tom não é o original.
+
+use crate::cdg;
+use crate::ferramentas;
+use crate::letra;
+use crate::midia::{self, TipoMidia};
+use crate::mpv_ipc;
+use crate::normalizacao;
+use crate::AppState;
+use serde::Serialize;
+use serde_json::json;
+use std::path::Path;
 use std::process::{Child, Command, Stdio};
+use std::sync::atomic::{AtomicBool, AtomicI32, Ordering};
 use std::sync::Mutex;
 use tauri::{AppHandle, Manager, State};
 
@@ -39,6 +55,23 @@ impl NativePlayerState {
     }
 }
 
+/// Tela de reprodução aberta (mpv ou <video>): as tarefas de fundo pesadas
+/// esperam para não disputar CPU e disco com a música.
+static TOCANDO: AtomicBool = AtomicBool::new(false);
+
+pub fn tocando() -> bool {
+    TOCANDO.load(Ordering::Relaxed)
+}
+
+/// Tom da música atual, em semitons (0 = original). Volta a 0 a cada música.
+static TOM: AtomicI32 = AtomicI32::new(0);
+const TOM_MIN: i32 = -6;
+const TOM_MAX: i32 = 6;
+
+pub fn tom() -> i32 {
+    TOM.load(Ordering::Relaxed)
+}
+
 // ─── Win32 via raw FFI (Windows only) ──────────────────────────────────────
 //
 // Usamos FFI direto (sem crate externo) para evitar conflitos de versão com
@@ -217,39 +250,12 @@ mod win_bg {
 
 // ─── Helpers ────────────────────────────────────────────────────────────────
 
-fn mpv_exe_name() -> &'static str {
-    if cfg!(target_os = "windows") { "mpv.exe" } else { "mpv" }
-}
+/// Fundo das faixas só de áudio: as ondas do próprio som (o vídeo acaba
+/// junto com o áudio, então o fim da música continua sendo detectado).
+const FUNDO_AUDIO: &str = "--lavfi-complex=[aid1]asplit[ao][a];[a]showwaves=s=1280x720:mode=cline:rate=30:colors=0x1e3a8a[vo]";
 
-fn find_mpv(app: &AppHandle) -> Option<std::path::PathBuf> {
-    let name = mpv_exe_name();
-
-    if let Ok(dir) = app.path().resource_dir() {
-        let c = dir.join(name);
-        if c.exists() {
-            log::info!("[player] mpv em resources: {}", c.display());
-            return Some(c);
-        }
-    }
-    if let Ok(exe) = std::env::current_exe() {
-        if let Some(dir) = exe.parent() {
-            let c = dir.join(name);
-            if c.exists() {
-                log::info!("[player] mpv ao lado do exe: {}", c.display());
-                return Some(c);
-            }
-        }
-    }
-    let sep = if cfg!(target_os = "windows") { ";" } else { ":" };
-    let found = std::env::var("PATH")
-        .ok()?
-        .split(sep)
-        .map(|d| std::path::PathBuf::from(d).join(name))
-        .find(|p| p.exists());
-    if let Some(ref p) = found {
-        log::info!("[player] mpv no PATH: {}", p.display());
-    }
-    found
+fn find_mpv() -> Option<std::path::PathBuf> {
+    ferramentas::localizar("mpv")
 }
 
 /// Retorna (x, y, largura, altura) do monitor onde a janela Tauri está.
@@ -270,14 +276,15 @@ fn get_monitor_rect(app: &AppHandle) -> (i32, i32, i32, i32) {
 // ─── Comandos Tauri ─────────────────────────────────────────────────────────
 
 #[tauri::command]
-pub fn native_player_available(app: AppHandle) -> bool {
-    find_mpv(&app).is_some()
+pub fn native_player_available() -> bool {
+    find_mpv().is_some()
 }
 
 #[tauri::command]
 pub async fn play_native(
     path: String,
     state: State<'_, NativePlayerState>,
+    app_state: State<'_, AppState>,
     app: AppHandle,
 ) -> Result<(), String> {
     // Para processo anterior
@@ -297,7 +304,7 @@ pub async fn play_native(
         *hwnd_guard = 0;
     }
 
-    let mpv = find_mpv(&app).ok_or_else(|| "mpv não encontrado".to_string())?;
+    let mpv = find_mpv().ok_or_else(|| "mpv não encontrado".to_string())?;
     let (mon_x, mon_y, mon_w, mon_h) = get_monitor_rect(&app);
 
     log::info!(
@@ -323,8 +330,37 @@ pub async fn play_native(
     #[cfg(not(target_os = "windows"))]
     let embed_arg: String = format!("--geometry={}x{}+{}+{}", mon_w, mon_h, mon_x, mon_y);
 
+    // Argumentos próprios do tipo de mídia
+    let mut entrada = path.clone();
+    let mut extras: Vec<String> = Vec::new();
+    match midia::identificar(Path::new(&path)).map(|m| m.tipo) {
+        // MP3+G: o mpv abre o .cdg (vídeo) com o MP3 como trilha de áudio
+        Some(TipoMidia::Cdg) => {
+            if let Some(cdg) = cdg::arquivo_cdg(Path::new(&path)) {
+                entrada = cdg.to_string_lossy().to_string();
+                extras.push(format!("--audio-file={}", path));
+            }
+        }
+        // Só áudio: as ondas do som de fundo e a letra (.ass/.lrc) por cima
+        Some(TipoMidia::Audio) => {
+            extras.push(FUNDO_AUDIO.into());
+            if let Some(arquivo) = letra::arquivo_letra(Path::new(&path)) {
+                let codigo = midia::codigo_do_arquivo(Path::new(&path)).unwrap_or_default();
+                match letra::legenda_mpv(&app_state.data_dir, &codigo, &arquivo) {
+                    Ok(legenda) => extras.push(format!("--sub-file={}", legenda.display())),
+                    Err(e) => log::warn!("[player] Letra ignorada: {}", e),
+                }
+            }
+        }
+        _ => {}
+    }
+    // Ganho da normalização (o volume do operador continua em 100)
+    if let Some(ganho) = normalizacao::arg_mpv(&path) {
+        extras.push(ganho);
+    }
+
     let args: Vec<String> = vec![
-        path.clone(),
+        entrada,
         "--no-border".into(),
         "--no-osc".into(),
         "--no-osd-bar".into(),
@@ -332,6 +368,7 @@ pub async fn play_native(
         "--no-input-default-bindings".into(),
         "--no-input-vo-keyboard".into(),
         "--no-terminal".into(),
+        format!("--input-ipc-server={}", mpv_ipc::endereco()),
         "--volume=100".into(),
         "--keep-open=no".into(),
         "--force-window=yes".into(),
@@ -350,6 +387,8 @@ pub async fn play_native(
     #[cfg(not(target_os = "windows"))]
     let args = { let mut a = args; a.push("--fullscreen".into()); a };
 
+    let args = { let mut a = args; a.extend(extras); a };
+
     log::info!("[player] Iniciando mpv: {:?}", args);
 
     // Eleva a janela Tauri ANTES de iniciar o mpv: a janela Win32 de vídeo
@@ -360,6 +399,8 @@ pub async fn play_native(
         log::info!("[player] Tauri definida como HWND_TOPMOST");
     }
 
+    mpv_ipc::limpar();
+    TOM.store(0, Ordering::Relaxed);
     let child = Command::new(&mpv)
         .args(&args)
         .stdin(Stdio::null())
@@ -388,6 +429,7 @@ pub fn stop_native(state: State<'_, NativePlayerState>, app: AppHandle) {
         win_bg::destroy(*hwnd_guard);
         *hwnd_guard = 0;
     }
+    mpv_ipc::limpar();
     if let Some(win) = app.get_webview_window("main") {
         win.set_always_on_top(false).ok();
     }
@@ -409,3 +451,126 @@ pub fn native_player_ended(state: State<'_, NativePlayerState>) -> bool {
         true
     }
 }
+
+/// O frontend avisa quando a tela de reprodução abre e fecha (vale também
+/// para o fallback <video>, que o Rust não enxerga).
+#[tauri::command]
+pub fn reproducao_ativa(ativa: bool) {
+    TOCANDO.store(ativa, Ordering::Relaxed);
+    if ativa {
+        TOM.store(0, Ordering::Relaxed);
+    }
+}
+
+// ─── Controle via IPC ───────────────────────────────────────────────────────
+
+#[derive(Debug, Serialize)]
+pub struct EstadoNative {
+    /// Segundos; None antes do arquivo abrir
+    pub posicao: Option<f64>,
+    pub duracao: Option<f64>,
+    pub pausado: bool,
+    /// 0–130 (100 = original)
+    pub volume: f64,
+    pub mudo: bool,
+}
+
+/// O IPC bloqueia (até o limite de resposta): fora da thread dos comandos.
+async fn ipc<T: Send + 'static>(f: impl FnOnce() -> Result<T, String> + Send + 'static) -> Result<T, String> {
+    tauri::async_runtime::spawn_blocking(f).await.map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub async fn pausar_native() -> Result<(), String> {
+    ipc(|| mpv_ipc::definir("pause", json!(true))).await
+}
+
+#[tauri::command]
+pub async fn retomar_native() -> Result<(), String> {
+    ipc(|| mpv_ipc::definir("pause", json!(false))).await
+}
+
+/// Pausa ou retoma, o que for o contrário do estado atual (atalho de pausa).
+#[tauri::command]
+pub async fn alternar_pausa_native() -> Result<(), String> {
+    ipc(|| mpv_ipc::comando(json!(["cycle", "pause"])).map(|_| ())).await
+}
+
+/// Vai para `posicao` segundos (ou anda `posicao` segundos com `relativo`).
+#[tauri::command]
+pub async fn buscar_native(posicao: f64, relativo: Option<bool>) -> Result<(), String> {
+    let modo = if relativo.unwrap_or(false) { "relative" } else { "absolute" };
+    ipc(move || mpv_ipc::comando(json!(["seek", posicao, modo])).map(|_| ())).await
+}
+
+/// Volta ao início e continua tocando.
+#[tauri::command]
+pub async fn reiniciar_native() -> Result<(), String> {
+    ipc(|| {
+        mpv_ipc::comando(json!(["seek", 0, "absolute"]))?;
+        mpv_ipc::definir("pause", json!(false))
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn volume_native(volume: f64) -> Result<(), String> {
+    ipc(move || mpv_ipc::definir("volume", json!(volume.clamp(0.0, 130.0)))).await
+}
+
+#[tauri::command]
+pub async fn mudo_native(mudo: bool) -> Result<(), String> {
+    ipc(move || mpv_ipc::definir("mute", json!(mudo))).await
+}
+
+/// Posição, duração, pausa, volume e mudo do mpv.
+#[tauri::command]
+pub async fn estado_native() -> Result<EstadoNative, String> {
+    ipc(|| {
+        // Posição e duração ficam indisponíveis até o arquivo abrir
+        let numero = |p: &str| mpv_ipc::obter(p).ok().and_then(|v| v.as_f64());
+        Ok(EstadoNative {
+            pausado: mpv_ipc::obter("pause")?.as_bool().unwrap_or(false),
+            volume: mpv_ipc::obter("volume")?.as_f64().unwrap_or(100.0),
+            mudo: mpv_ipc::obter("mute")?.as_bool().unwrap_or(false),
+            posicao: numero("time-pos"),
+            duracao: numero("duration"),
+        })
+    })
+    .await
+}
+
+fn aplicar_tom(semitons: i32) -> Result<(), String> {
+    if semitons == 0 {
+        // Tom original: tira o filtro (sem ele não há o que remover)
+        mpv_ipc::comando(json!(["af", "remove", "@tom"])).ok();
+        return Ok(());
+    }
+    let escala = format!("{:.6}", 2f64.powf(semitons as f64 / 12.0));
+    match mpv_ipc::comando(json!(["af-command", "tom", "set-pitch", escala])) {
+        Ok(_) => Ok(()),
+        // Primeira mudança na música: o filtro ainda não existe
+        Err(_) => mpv_ipc::comando(json!(["af", "add", format!("@tom:rubberband=pitch-scale={}", escala)])).map(|_| ()),
+    }
+}
+
+/// Muda o tom da música em reprodução para `semitons` (-6 a +6) sem mudar o
+/// andamento. Retorna o tom aplicado.
+#[tauri::command]
+pub async fn definir_tom_native(semitons: i32) -> Result<i32, String> {
+    if !(TOM_MIN..=TOM_MAX).contains(&semitons) {
+        return Err(format!("Tom fora da faixa ({} a +{} semitons)", TOM_MIN, TOM_MAX));
+    }
+    ipc(move || aplicar_tom(semitons)).await?;
+    TOM.store(semitons, Ordering::Relaxed);
+    log::info!("[player] Tom: {:+}", semitons);
+    Ok(semitons)
+}
+
+/// Volta ao tom original.
+#[tauri::command]
+pub async fn resetar_tom_native() -> Result<(), String> {
+    ipc(|| aplicar_tom(0)).await?;
+    TOM.store(0, Ordering::Relaxed);
+    Ok(())
+}
@@ -0,0 +1,14 @@
+use crate::rede_local::{self, StatusRedeLocal};
+
+/// Modo rede local, conta vinculada e máquinas encontradas.
+#[tauri::command]
+pub fn status_rede_local() -> StatusRedeLocal {
+    rede_local::status()
+}
+
+/// Liga ou desliga a troca de vídeos com as outras máquinas da conta.
+#[tauri::command]
+pub fn configurar_rede_local(ativa: bool) -> Result<StatusRedeLocal, String> {
+    rede_local::set_ativa(ativa)?;
+    Ok(rede_local::status())
+}
@@ -1,8 +1,18 @@
+use crate::backend::{ArquivoBaixado, Backend, BackendError, CatalogBackend, Integridade, OnProgresso};
+use crate::biblioteca;
+use crate::catalogo;
 use crate::db;
-use crate::supabase;
+use crate::download;
+use crate::download_manager::DownloadManager;
+use crate::midia;
+use crate::quarentena;
+use crate::rede_local;
+use crate::sondagem;
 use crate::AppState;
 use serde::Serialize;
+use std::collections::HashSet;
 use std::path::Path;
+use tauri::Emitter;
 
 #[derive(Serialize)]
 pub struct OfflineStatus {
@@ -16,21 +26,39 @@ pub struct OfflineStatus {
     pub storage_used: i64,
     #[serde(rename = "storageUsedMB")]
     pub storage_used_mb: f64,
+    /// Última atualização do espelho do catálogo (ms); None se nunca sincronizado
+    #[serde(rename = "catalogoSincronizadoEm")]
+    pub catalogo_sincronizado_em: Option<i64>,
 }
 
+/// Status a partir do espelho local do catálogo, sem esperar a rede. O
+/// espelho é atualizado em segundo plano (no máximo uma vez por minuto).
 #[tauri::command]
-pub async fn get_offline_status(_state: tauri::State<'_, AppState>) -> Result<OfflineStatus, String> {
+pub async fn get_offline_status(
+    state: tauri::State<'_, AppState>,
+    backend: tauri::State<'_, Backend>,
+) -> Result<OfflineStatus, String> {
+    let catalogo = backend.catalogo.clone();
+    let data_dir = state.data_dir.clone();
+    tauri::async_runtime::spawn(async move {
+        if let Err(e) = catalogo::sincronizar(catalogo.as_ref(), &data_dir, false).await {
+            log::warn!("[SYNC] Catálogo não sincronizado: {}", e);
+        }
+    });
+    offline_status()
+}
+
+pub fn offline_status() -> Result<OfflineStatus, String> {
     let local_count = db::count_musicas_local()?;
     let storage = db::storage_used()?;
     
-    let mut total = local_count;
-    let mut online_only = 0i64;
-    
-    // Try to get remote count
-    if let Ok(remote) = supabase::fetch_all_musicas().await {
-        total = remote.len() as i64;
-        online_only = (total - local_count).max(0);
-    }
+    // Sem espelho (nunca sincronizado), só dá para contar o que está na máquina
+    let remote_count = db::count_catalogo()?;
+    let (total, online_only) = if remote_count > 0 {
+        (remote_count, db::count_nao_baixadas()?)
+    } else {
+        (local_count, 0)
+    };
     
     Ok(OfflineStatus {
         total_musicas: total,
@@ -38,84 +66,202 @@ pub async fn get_offline_status(_state: tauri::State<'_, AppState>) -> Result<Of
         musicas_online: online_only,
         storage_used: storage,
         storage_used_mb: storage as f64 / (1024.0 * 1024.0),
+        catalogo_sincronizado_em: db::catalogo_sincronizado_em()?,
     })
 }
 
-#[derive(Serialize)]
-pub struct DownloadResult {
-    pub downloaded: i32,
-    pub remaining: i64,
-    pub errors: Vec<String>,
-}
-
-#[tauri::command]
-pub async fn download_batch(size: Option<i32>, state: tauri::State<'_, AppState>) -> Result<DownloadResult, String> {
-    let batch_size = size.unwrap_or(3);
-    let data_dir = &state.data_dir;
-    let musicas_dir = Path::new(data_dir).join("musicas");
-    std::fs::create_dir_all(&musicas_dir).map_err(|e| e.to_string())?;
+/// Músicas do catálogo local que ainda não estão na máquina ou cujo vídeo
+/// mudou no catálogo. As apagadas por falta de espaço ficam de fora.
+pub fn musicas_pendentes(musicas_dir: &Path) -> Result<Vec<db::MusicaCatalogo>, String> {
+    let remote = db::listar_catalogo()?;
+    let substituidas: HashSet<String> = db::musicas_precisam_atualizar()?.into_iter().collect();
+    let liberadas: HashSet<String> = db::listar_liberadas()?.into_iter().collect();
     
-    // Fetch all remote musicas
-    let remote = supabase::fetch_all_musicas().await?;
-    
-    // Filter to ones not downloaded yet
+    // Filter to ones not downloaded yet (or whose video changed remotely)
     let mut pending = Vec::new();
-    for m in &remote {
-        let local_path = musicas_dir.join(format!("{}.mp4", m.codigo));
-        if !local_path.exists() || !db::musica_existe(&m.codigo).unwrap_or(true) {
+    for m in remote {
+        if liberadas.contains(&m.codigo) {
+            continue;
+        }
+        let local_path = midia::localizar(musicas_dir, &m.codigo);
+        if local_path.is_none() || !db::musica_existe(&m.codigo).unwrap_or(true) || substituidas.contains(&m.codigo) {
             pending.push(m);
         }
     }
-    
-    let total_remaining = pending.len() as i64;
-    let batch: Vec<_> = pending.into_iter().take(batch_size as usize).collect();
-    
-    log::info!("Downloading batch: {} of {} pending", batch.len(), total_remaining);
-    
-    let mut downloaded = 0;
-    let mut errors = Vec::new();
-    
-    for musica in batch {
-        let dest = musicas_dir.join(format!("{}.mp4", musica.codigo));
-        let dest_str = dest.to_string_lossy().to_string();
-
-        match supabase::download_file(&musica.arquivo, &dest_str).await {
-            Ok(size) => {
-                let db_musica = db::Musica {
-                    id: musica.id.clone(),
-                    codigo: musica.codigo.clone(),
-                    artista: musica.artista.clone(),
-                    titulo: musica.titulo.clone(),
-                    arquivo: dest_str.clone(),
-                    nome_arquivo: musica.nome_arquivo.clone(),
-                    tamanho: Some(size as i64),
-                    duracao: musica.duracao.as_ref().and_then(|v| v.as_i64()),
-                    user_id: musica.user_id.clone(),
-                };
-                
-                match db::insert_musica(&db_musica) {
-                    Ok(_) => {
-                        log::info!("[DOWNLOAD] {} downloaded ({} bytes)", musica.codigo, size);
-                        downloaded += 1;
-                    }
-                    Err(e) => {
-                        // Rollback: delete file if DB insert failed
-                        std::fs::remove_file(&dest).ok();
-                        errors.push(format!("{}: DB error: {}", musica.codigo, e));
-                    }
-                }
-            }
-            Err(e) => {
-                errors.push(format!("{}: {}", musica.codigo, e));
-            }
+    Ok(pending)
+}
+
+/// Baixa uma música do catálogo, confere com tamanho/hash e indexa no SQLite.
+/// Retorna o tamanho gravado.
+pub async fn baixar_musica(
+    catalogo: &dyn CatalogBackend,
+    musicas_dir: &Path,
+    musica: &db::MusicaCatalogo,
+    progresso: OnProgresso<'_>,
+) -> Result<u64, String> {
+    let dest = midia::caminho_destino(musicas_dir, &musica.codigo, &musica.arquivo);
+    let dest_str = dest.to_string_lossy().to_string();
+    let integridade = Integridade {
+        tamanho: musica.tamanho.filter(|t| *t > 0).map(|t| t as u64),
+        sha256: musica.sha256.clone(),
+    };
+
+    // Outra máquina da casa com o mesmo vídeo poupa a internet
+    if let Some(baixado) = rede_local::baixar_de_par(&musica.codigo, &dest, &integridade, progresso).await {
+        return registrar_baixada(musica, &dest, baixado);
+    }
+
+    // Arquivo que não confere é descartado e baixado de novo uma vez
+    let mut resultado = catalogo.download_file(&musica.arquivo, &dest_str, &integridade, progresso).await;
+    if let Err(BackendError::Integridade(e)) = &resultado {
+        log::warn!("[DOWNLOAD] {} rejeitada ({}); tentando de novo", musica.codigo, e);
+        resultado = catalogo.download_file(&musica.arquivo, &dest_str, &integridade, progresso).await;
+    }
+    let baixado = resultado?;
+    registrar_baixada(musica, &dest, baixado)
+}
+
+fn registrar_baixada(musica: &db::MusicaCatalogo, dest: &Path, baixado: ArquivoBaixado) -> Result<u64, String> {
+    let dest_str = dest.to_string_lossy().to_string();
+    let anterior = db::get_musica_local(&musica.codigo).ok().flatten().map(|m| m.arquivo);
+    let db_musica = db::Musica {
+        id: musica.id.clone(),
+        codigo: musica.codigo.clone(),
+        artista: musica.artista.clone(),
+        titulo: musica.titulo.clone(),
+        arquivo: dest_str.clone(),
+        nome_arquivo: musica.nome_arquivo.clone(),
+        tamanho: Some(baixado.tamanho as i64),
+        duracao: musica.duracao,
+        user_id: musica.user_id.clone(),
+        arquivo_remoto: Some(musica.arquivo.clone()),
+        sha256: Some(baixado.sha256),
+    };
+    if let Err(e) = db::insert_musica(&db_musica) {
+        // Rollback: delete file if DB insert failed
+        std::fs::remove_file(dest).ok();
+        return Err(format!("DB error: {}", e));
+    }
+    // Versão nova em outro contêiner (ex.: .avi → .mp4): a antiga sai
+    // (com os companheiros: .cdg e letras de um .mp3 não servem ao vídeo novo)
+    if let Some(anterior) = anterior.filter(|a| *a != dest_str) {
+        std::fs::remove_file(&anterior).ok();
+        for companheiro in midia::companheiros(Path::new(&anterior)) {
+            std::fs::remove_file(companheiro).ok();
         }
     }
-    
-    Ok(DownloadResult {
-        downloaded,
-        remaining: total_remaining - downloaded as i64,
-        errors,
+    db::remover_demanda(&musica.codigo).ok();
+    sondagem::avisar();
+    log::info!("[DOWNLOAD] {} downloaded ({} bytes)", musica.codigo, baixado.tamanho);
+    Ok(baixado.tamanho)
+}
+
+/// Força a sincronização do catálogo: incremental quando já existe um espelho
+/// local, ou completa com `completa = true`. O andamento é emitido no evento
+/// "catalogo-progresso".
+#[tauri::command]
+pub async fn sincronizar_catalogo(
+    completa: Option<bool>,
+    state: tauri::State<'_, AppState>,
+    backend: tauri::State<'_, Backend>,
+    app: tauri::AppHandle,
+) -> Result<catalogo::CatalogSyncResult, String> {
+    let progresso = move |p: catalogo::ProgressoCatalogo| {
+        app.emit("catalogo-progresso", p).ok();
+    };
+    catalogo::sincronizar_com(backend.catalogo.as_ref(), &state.data_dir, true, completa.unwrap_or(false), &progresso).await
+}
+
+/// Músicas removidas do catálogo aguardando o fim do prazo de quarentena.
+#[tauri::command]
+pub fn listar_quarentena() -> Result<Vec<db::MusicaQuarentena>, String> {
+    db::listar_quarentena()
+}
+
+/// Lê (sem `dias`) ou altera o prazo de quarentena, em dias. 0 apaga as
+/// músicas removidas assim que a remoção é detectada.
+#[tauri::command]
+pub fn configurar_quarentena(dias: Option<i64>) -> Result<i64, String> {
+    if let Some(dias) = dias {
+        quarentena::set_dias(dias)?;
+    }
+    Ok(quarentena::dias())
+}
+
+#[derive(Serialize)]
+pub struct VerificacaoResult {
+    pub verificadas: i64,
+    /// Baixadas antes do hash ser guardado; não dá para conferir
+    #[serde(rename = "semHash")]
+    pub sem_hash: i64,
+    /// Arquivos que não conferem (ou sumiram); marcados para novo download
+    pub corrompidas: Vec<String>,
+}
+
+/// Recalcula o SHA-256 dos vídeos baixados (todos, ou só `codigos`) e compara
+/// com o hash guardado no download.
+#[tauri::command]
+pub async fn reverificar_musicas(codigos: Option<Vec<String>>) -> Result<VerificacaoResult, String> {
+    tauri::async_runtime::spawn_blocking(move || reverificar(codigos))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+pub fn reverificar(codigos: Option<Vec<String>>) -> Result<VerificacaoResult, String> {
+    let musicas = match codigos {
+        Some(codigos) => codigos
+            .iter()
+            .filter_map(|c| db::get_musica_local(c).transpose())
+            .collect::<Result<Vec<_>, _>>()?,
+        None => db::listar_musicas_local()?,
+    };
+    let mut out = VerificacaoResult { verificadas: 0, sem_hash: 0, corrompidas: Vec::new() };
+    for m in musicas {
+        let Some(esperado) = m.sha256.as_deref() else {
+            out.sem_hash += 1;
+            continue;
+        };
+        let confere = download::sha256_arquivo(Path::new(&m.arquivo)).is_ok_and(|h| h.eq_ignore_ascii_case(esperado));
+        out.verificadas += 1;
+        if !confere {
+            log::warn!("[VERIFICACAO] {} não confere com o hash do download", m.codigo);
+            db::marcar_precisa_atualizar(&m.codigo)?;
+            out.corrompidas.push(m.codigo);
+        }
+    }
+    Ok(out)
+}
+
+/// Confere a pasta `musicas/` contra o banco (registros sem arquivo, órfãos,
+/// vazios, truncados, tamanho divergente). Com `reparar`, põe na fila o que
+/// precisa ser baixado de novo, indexa os órfãos e descarta os corrompidos
+/// (`descarte`: "quarentena", o padrão, ou "apagar"). O andamento é emitido
+/// no evento "biblioteca-progresso".
+#[tauri::command]
+pub async fn verificar_biblioteca(
+    reparar: Option<bool>,
+    descarte: Option<String>,
+    state: tauri::State<'_, AppState>,
+    manager: tauri::State<'_, DownloadManager>,
+    app: tauri::AppHandle,
+) -> Result<biblioteca::RelatorioBiblioteca, String> {
+    let descarte = biblioteca::Descarte::from_str(descarte.as_deref())?;
+    let reparar = reparar.unwrap_or(false).then_some(descarte);
+    let data_dir = state.data_dir.clone();
+    let relatorio = tauri::async_runtime::spawn_blocking(move || {
+        let progresso = move |p: biblioteca::ProgressoBiblioteca| {
+            app.emit("biblioteca-progresso", p).ok();
+        };
+        biblioteca::verificar(&data_dir, reparar, &progresso)
     })
+    .await
+    .map_err(|e| e.to_string())??;
+    if let Some(reparo) = &relatorio.reparo {
+        if !reparo.reenfileiradas.is_empty() {
+            manager.enfileirar(&reparo.reenfileiradas)?;
+        }
+    }
+    Ok(relatorio)
 }
 
 #[derive(Serialize)]
@@ -126,62 +272,67 @@ pub struct ReindexResult {
 }
 
 #[tauri::command]
-pub async fn reindex_musicas(state: tauri::State<'_, AppState>) -> Result<ReindexResult, String> {
-    let data_dir = &state.data_dir;
+pub async fn reindex_musicas(
+    state: tauri::State<'_, AppState>,
+    backend: tauri::State<'_, Backend>,
+) -> Result<ReindexResult, String> {
+    reindexar(backend.catalogo.as_ref(), &state.data_dir).await
+}
+
+/// Grava em `musicas_local` uma mídia que já está no disco, com os metadados
+/// da música do catálogo.
+pub fn indexar_arquivo(path: &Path, musica: &db::MusicaCatalogo) -> Result<(), String> {
+    let size = std::fs::metadata(path).map(|m| m.len() as i64).unwrap_or(0);
+    db::insert_musica(&db::Musica {
+        id: musica.id.clone(),
+        codigo: musica.codigo.clone(),
+        artista: musica.artista.clone(),
+        titulo: musica.titulo.clone(),
+        arquivo: path.to_string_lossy().to_string(),
+        nome_arquivo: musica.nome_arquivo.clone(),
+        tamanho: Some(size),
+        duracao: musica.duracao,
+        user_id: musica.user_id.clone(),
+        // Origem do arquivo desconhecida: assume a versão atual do catálogo
+        arquivo_remoto: Some(musica.arquivo.clone()),
+        // Hash só de download conferido; este arquivo nunca passou por um
+        sha256: None,
+    })?;
+    sondagem::avisar();
+    Ok(())
+}
+
+/// Indexa mídias (qualquer contêiner suportado) presentes em `musicas/` que
+/// não estão no SQLite.
+pub async fn reindexar(catalogo: &dyn CatalogBackend, data_dir: &str) -> Result<ReindexResult, String> {
     let musicas_dir = Path::new(data_dir).join("musicas");
     
     if !musicas_dir.exists() {
         return Ok(ReindexResult { total: 0, reindexed: 0, errors: vec![] });
     }
     
-    let files: Vec<_> = std::fs::read_dir(&musicas_dir)
-        .map_err(|e| e.to_string())?
-        .filter_map(|e| e.ok())
-        .filter(|e| e.path().extension().map(|ext| ext == "mp4").unwrap_or(false))
-        .collect();
+    let files = midia::listar(&musicas_dir).map_err(|e| e.to_string())?;
     
     let total = files.len() as i32;
     let mut reindexed = 0;
     let mut errors = Vec::new();
     
-    // Get remote data for reindexing
-    let remote = match supabase::fetch_all_musicas().await {
-        Ok(r) => r,
-        Err(e) => {
+    // Metadados vêm do catálogo local; sem ele (nunca sincronizado) não há como indexar
+    if let Err(e) = catalogo::sincronizar(catalogo, data_dir, false).await {
+        if db::count_catalogo()? == 0 {
             return Ok(ReindexResult { total, reindexed: 0, errors: vec![format!("Offline: {}", e)] });
         }
-    };
+    }
     
     for file in &files {
-        let codigo = file.path().file_stem()
-            .and_then(|s| s.to_str())
-            .unwrap_or("")
-            .to_string();
-        
-        if codigo.is_empty() { continue; }
+        let Some(codigo) = midia::codigo_do_arquivo(file) else { continue };
         
         // Skip if already in DB
         if db::musica_existe(&codigo).unwrap_or(true) { continue; }
         
-        // Find in remote data
-        if let Some(musica) = remote.iter().find(|m| m.codigo == codigo) {
-            let path = file.path();
-            let file_path = path.to_string_lossy().to_string();
-            let size = std::fs::metadata(&path).map(|m| m.len() as i64).unwrap_or(0);
-
-            let db_musica = db::Musica {
-                id: musica.id.clone(),
-                codigo: musica.codigo.clone(),
-                artista: musica.artista.clone(),
-                titulo: musica.titulo.clone(),
-                arquivo: file_path,
-                nome_arquivo: musica.nome_arquivo.clone(),
-                tamanho: Some(size),
-                duracao: musica.duracao.as_ref().and_then(|v| v.as_i64()),
-                user_id: musica.user_id.clone(),
-            };
-            
-            match db::insert_musica(&db_musica) {
+        // Find in catalog
+        if let Ok(Some(musica)) = db::get_catalogo_by_codigo(&codigo) {
+            match indexar_arquivo(file, &musica) {
                 Ok(_) => {
                     log::info!("[REINDEX] {} indexed", codigo);
                     reindexed += 1;
@@ -1,3 +1,7 @@
+use crate::cdg;
+use crate::letra;
+use crate::midia;
+use crate::miniaturas;
 use crate::AppState;
 use std::path::Path;
 
@@ -32,11 +36,42 @@ pub fn get_video_path(codigo: String, state: tauri::State<'_, AppState>) -> Resu
     };
 
     for variante in codigo_variantes(&codigo) {
-        let video_path = musicas_dir.join(format!("{}.mp4", variante));
-        if video_path.exists() {
-            return Ok(video_path.to_string_lossy().to_string());
+        if let Some(path) = midia::localizar(&musicas_dir, &variante) {
+            return Ok(path.to_string_lossy().to_string());
         }
     }
 
     Err(format!("Video not found: {}", codigo))
 }
+
+/// Quadro CD+G de uma música MP3+G (`arquivo` é o áudio) no instante
+/// `posicao_ms`, para o player sem mpv desenhar num canvas. None quando a
+/// tela não mudou desde `versao`.
+#[tauri::command]
+pub async fn cdg_quadro(arquivo: String, posicao_ms: u64, versao: Option<u64>) -> Result<Option<cdg::QuadroCdg>, String> {
+    // Lê e decodifica o .cdg: fora da thread principal
+    tauri::async_runtime::spawn_blocking(move || {
+        let cdg = cdg::arquivo_cdg(Path::new(&arquivo)).ok_or_else(|| format!("CDG not found: {}", arquivo))?;
+        cdg::quadro(&cdg, posicao_ms, versao)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Letra sincronizada (.ass/.lrc) que acompanha o áudio `arquivo`, para o
+/// player sem mpv. None se a faixa não tem letra.
+#[tauri::command]
+pub fn get_letra(arquivo: String) -> Result<Option<letra::Letra>, String> {
+    letra::arquivo_letra(Path::new(&arquivo)).map(|l| letra::ler(&l)).transpose()
+}
+
+/// Caminho da miniatura (JPEG) da música `codigo`. None se não está baixada,
+/// é só áudio ou ainda não foi gerada (fica para a thread de miniaturas).
+#[tauri::command]
+pub async fn get_miniatura(codigo: String, state: tauri::State<'_, AppState>) -> Result<Option<String>, String> {
+    let data_dir = state.data_dir.clone();
+    let caminho = tauri::async_runtime::spawn_blocking(move || miniaturas::caminho(&data_dir, codigo.trim()))
+        .await
+        .map_err(|e| e.to_string())??;
+    Ok(caminho.map(|p| p.to_string_lossy().to_string()))
+}
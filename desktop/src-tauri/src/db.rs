@@ -1,3 +1,6 @@
+use crate::letra;
+use crate::midia;
+use crate::sondagem::InfoMidia;
 use rusqlite::{Connection, params};
 use serde::{Deserialize, Serialize};
 use std::sync::Mutex;
@@ -58,11 +61,110 @@ pub fn init_db(data_dir: &str) -> Result<(), String> {
             updated_at INTEGER NOT NULL
         );
 
+        -- Espelho do catálogo remoto (tabela musicas do Supabase), sincronizado
+        -- de forma incremental por updated_at. Independe do que foi baixado.
+        CREATE TABLE IF NOT EXISTS catalogo_remoto (
+            id TEXT PRIMARY KEY,
+            codigo TEXT NOT NULL UNIQUE,
+            artista TEXT NOT NULL,
+            titulo TEXT NOT NULL,
+            arquivo TEXT NOT NULL,
+            nome_arquivo TEXT,
+            tamanho INTEGER,
+            duracao INTEGER,
+            user_id TEXT,
+            remote_updated_at TEXT,
+            synced_at INTEGER NOT NULL
+        );
+
+        -- Músicas removidas do catálogo remoto: o vídeo fica em musicas/.quarentena
+        -- até expira_em e volta para a biblioteca se a música reaparecer.
+        CREATE TABLE IF NOT EXISTS musicas_quarentena (
+            codigo TEXT PRIMARY KEY,
+            id TEXT NOT NULL,
+            artista TEXT NOT NULL,
+            titulo TEXT NOT NULL,
+            arquivo_original TEXT NOT NULL,
+            arquivo_quarentena TEXT NOT NULL,
+            nome_arquivo TEXT,
+            tamanho INTEGER,
+            duracao INTEGER,
+            user_id TEXT,
+            arquivo_remoto TEXT,
+            sha256 TEXT,
+            motivo TEXT NOT NULL,
+            quarentena_em INTEGER NOT NULL,
+            expira_em INTEGER NOT NULL
+        );
+
+        -- Fila do gerenciador de downloads (sobrevive a reinícios)
+        CREATE TABLE IF NOT EXISTS fila_downloads (
+            codigo TEXT PRIMARY KEY,
+            estado TEXT NOT NULL DEFAULT 'pendente',
+            tentativas INTEGER NOT NULL DEFAULT 0,
+            erro TEXT,
+            proxima_tentativa INTEGER,
+            created_at INTEGER NOT NULL,
+            updated_at INTEGER NOT NULL
+        );
+
+        -- Músicas pedidas (código digitado) ou buscadas que ainda não estavam
+        -- na máquina; vão para a frente da fila de downloads
+        CREATE TABLE IF NOT EXISTS demanda_musicas (
+            codigo TEXT PRIMARY KEY,
+            pedidos INTEGER NOT NULL DEFAULT 0,
+            buscas INTEGER NOT NULL DEFAULT 0,
+            ultimo_em INTEGER NOT NULL
+        );
+
+        -- Músicas que o dono protegeu da liberação de espaço. Fica fora de
+        -- musicas_local para sobreviver a um novo download da música.
+        CREATE TABLE IF NOT EXISTS musicas_fixadas (
+            codigo TEXT PRIMARY KEY,
+            fixada_em INTEGER NOT NULL
+        );
+
+        -- Músicas apagadas para liberar espaço; a fila automática não as baixa
+        -- de novo (só se forem pedidas ou priorizadas)
+        CREATE TABLE IF NOT EXISTS musicas_liberadas (
+            codigo TEXT PRIMARY KEY,
+            tamanho INTEGER,
+            liberada_em INTEGER NOT NULL
+        );
+
+        -- Downloads interrompidos que podem ser retomados com Range
+        CREATE TABLE IF NOT EXISTS downloads_parciais (
+            destino TEXT PRIMARY KEY,
+            url TEXT NOT NULL,
+            etag TEXT,
+            last_modified TEXT,
+            tamanho_total INTEGER,
+            updated_at INTEGER NOT NULL
+        );
+
+        -- Auditoria local de validações de chave (suporte)
+        CREATE TABLE IF NOT EXISTS ativacao_eventos (
+            id TEXT PRIMARY KEY,
+            data_evento INTEGER NOT NULL,
+            acao TEXT NOT NULL,
+            chave_mascarada TEXT,
+            modo TEXT NOT NULL,
+            resultado TEXT NOT NULL,
+            detalhe TEXT,
+            machine_id_local TEXT,
+            machine_id_remoto TEXT,
+            dias_restantes INTEGER,
+            horas_restantes REAL
+        );
+
         CREATE INDEX IF NOT EXISTS idx_musicas_codigo ON musicas_local(codigo);
         CREATE INDEX IF NOT EXISTS idx_historico_codigo ON historico_local(codigo);
         CREATE INDEX IF NOT EXISTS idx_historico_synced ON historico_local(synced_at);
+        CREATE INDEX IF NOT EXISTS idx_ativacao_eventos_data ON ativacao_eventos(data_evento);
     ").map_err(|e| e.to_string())?;
 
+    migrar(&conn).map_err(|e| e.to_string())?;
+
     log::info!("Database initialized at {:?}", db_path);
 
     let mut guard = DB.lock().unwrap();
@@ -70,6 +172,49 @@ pub fn init_db(data_dir: &str) -> Result<(), String> {
     Ok(())
 }
 
+/// Colunas adicionadas depois da criação das tabelas (bancos já existentes).
+fn migrar(conn: &Connection) -> rusqlite::Result<()> {
+    // Versão remota baixada: URL e hash do conteúdo, para detectar substituições
+    adicionar_coluna(conn, "musicas_local", "arquivo_remoto", "TEXT")?;
+    adicionar_coluna(conn, "musicas_local", "sha256", "TEXT")?;
+    adicionar_coluna(conn, "musicas_local", "precisa_atualizar", "INTEGER NOT NULL DEFAULT 0")?;
+    adicionar_coluna(conn, "catalogo_remoto", "sha256", "TEXT")?;
+    // Prioridade de download: lançamentos, ranking global e "furar fila"
+    adicionar_coluna(conn, "catalogo_remoto", "remote_created_at", "TEXT")?;
+    adicionar_coluna(conn, "catalogo_remoto", "popularidade", "INTEGER NOT NULL DEFAULT 0")?;
+    adicionar_coluna(conn, "fila_downloads", "prioridade", "INTEGER NOT NULL DEFAULT 0")?;
+    // Contêiner real do arquivo (as linhas antigas são todas .mp4)
+    adicionar_coluna(conn, "musicas_local", "extensao", "TEXT NOT NULL DEFAULT 'mp4'")?;
+    adicionar_coluna(conn, "musicas_local", "tipo_midia", "TEXT NOT NULL DEFAULT 'video'")?;
+    // Letra sincronizada (.ass/.lrc) das faixas só de áudio
+    adicionar_coluna(conn, "musicas_local", "letra", "TEXT")?;
+    // Sondagem do arquivo (sondagem.rs); sondada_em NULL = ainda não sondado
+    adicionar_coluna(conn, "musicas_local", "largura", "INTEGER")?;
+    adicionar_coluna(conn, "musicas_local", "altura", "INTEGER")?;
+    adicionar_coluna(conn, "musicas_local", "codec_video", "TEXT")?;
+    adicionar_coluna(conn, "musicas_local", "codec_audio", "TEXT")?;
+    adicionar_coluna(conn, "musicas_local", "canais_audio", "INTEGER")?;
+    adicionar_coluna(conn, "musicas_local", "loudness_lufs", "REAL")?;
+    adicionar_coluna(conn, "musicas_local", "pico_dbtp", "REAL")?;
+    adicionar_coluna(conn, "musicas_local", "sondada_em", "INTEGER")?;
+    // Tom (semitons) em que a música foi cantada
+    adicionar_coluna(conn, "historico_local", "tom", "INTEGER NOT NULL DEFAULT 0")?;
+    Ok(())
+}
+
+fn adicionar_coluna(conn: &Connection, tabela: &str, coluna: &str, tipo: &str) -> rusqlite::Result<()> {
+    let mut stmt = conn.prepare(&format!("PRAGMA table_info({})", tabela))?;
+    let existe = stmt
+        .query_map([], |row| row.get::<_, String>(1))?
+        .filter_map(Result::ok)
+        .any(|c| c == coluna);
+    if !existe {
+        conn.execute_batch(&format!("ALTER TABLE {} ADD COLUMN {} {}", tabela, coluna, tipo))?;
+        log::info!("[DB] Coluna adicionada: {}.{}", tabela, coluna);
+    }
+    Ok(())
+}
+
 pub fn with_db<F, R>(f: F) -> Result<R, String>
 where
     F: FnOnce(&Connection) -> Result<R, rusqlite::Error>,
@@ -94,6 +239,10 @@ pub struct Musica {
     pub duracao: Option<i64>,
     #[serde(rename = "userId")]
     pub user_id: Option<String>,
+    /// URL de onde o vídeo foi baixado
+    #[serde(rename = "arquivoRemoto")]
+    pub arquivo_remoto: Option<String>,
+    pub sha256: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -102,6 +251,102 @@ pub struct MusicaSimple {
     pub artista: String,
     pub titulo: String,
     pub arquivo: String,
+    pub extensao: String,
+    /// "video" ou "audio"
+    #[serde(rename = "tipoMidia")]
+    pub tipo_midia: String,
+    /// Caminho do .ass/.lrc que acompanha o áudio
+    pub letra: Option<String>,
+    /// Segundos (do catálogo ou medida no arquivo)
+    pub duracao: Option<i64>,
+}
+
+/// Linha do catálogo remoto (`catalogo_remoto`); `arquivo` é a URL do vídeo.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MusicaCatalogo {
+    pub id: String,
+    pub codigo: String,
+    pub artista: String,
+    pub titulo: String,
+    pub arquivo: String,
+    #[serde(rename = "nomeArquivo")]
+    pub nome_arquivo: Option<String>,
+    pub tamanho: Option<i64>,
+    pub duracao: Option<i64>,
+    #[serde(rename = "userId")]
+    pub user_id: Option<String>,
+    #[serde(rename = "updatedAt")]
+    pub updated_at: Option<String>,
+    #[serde(rename = "createdAt")]
+    pub created_at: Option<String>,
+    /// Hash do conteúdo do vídeo (SHA-256 hex), quando o catálogo informa
+    pub sha256: Option<String>,
+    /// Tombstone: a música foi apagada no catálogo remoto
+    #[serde(skip)]
+    pub removida: bool,
+}
+
+/// Estado para retomar um download interrompido (`<destino>.part`).
+#[derive(Debug, Clone)]
+pub struct DownloadParcial {
+    pub destino: String,
+    pub url: String,
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+    /// Tamanho do arquivo completo, se o servidor informou
+    pub tamanho_total: Option<i64>,
+    pub updated_at: i64,
+}
+
+/// Resultado de busca no catálogo remoto espelhado.
+#[derive(Debug, Serialize, Clone)]
+pub struct MusicaCatalogoBusca {
+    pub codigo: String,
+    pub artista: String,
+    pub titulo: String,
+    pub duracao: Option<i64>,
+    /// true se o vídeo já está na máquina
+    pub baixada: bool,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct CatalogoAplicado {
+    pub novas: i64,
+    pub atualizadas: i64,
+    /// Músicas já baixadas cujos metadados (artista/título...) mudaram
+    #[serde(rename = "locaisAtualizadas")]
+    pub locais_atualizadas: i64,
+    /// Códigos removidos do catálogo (tombstones)
+    pub removidas: Vec<String>,
+    /// Códigos já baixados cujo vídeo mudou e precisa ser baixado de novo
+    pub substituidas: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MusicaQuarentena {
+    pub codigo: String,
+    pub id: String,
+    pub artista: String,
+    pub titulo: String,
+    #[serde(rename = "arquivoOriginal")]
+    pub arquivo_original: String,
+    #[serde(rename = "arquivoQuarentena")]
+    pub arquivo_quarentena: String,
+    #[serde(rename = "nomeArquivo")]
+    pub nome_arquivo: Option<String>,
+    pub tamanho: Option<i64>,
+    pub duracao: Option<i64>,
+    #[serde(rename = "userId")]
+    pub user_id: Option<String>,
+    #[serde(rename = "arquivoRemoto")]
+    pub arquivo_remoto: Option<String>,
+    pub sha256: Option<String>,
+    /// "removida" (tombstone) ou "ausente" (sumiu numa sincronização completa)
+    pub motivo: String,
+    #[serde(rename = "quarentenaEm")]
+    pub quarentena_em: i64,
+    #[serde(rename = "expiraEm")]
+    pub expira_em: i64,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -115,13 +360,38 @@ pub struct Ativacao {
     pub data_validacao: i64,
 }
 
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AtivacaoEvento {
+    pub id: String,
+    #[serde(rename = "dataEvento")]
+    pub data_evento: i64,
+    /// "validar_chave", "verificar_ativacao" ou "remover_ativacao"
+    pub acao: String,
+    #[serde(rename = "chaveMascarada")]
+    pub chave_mascarada: Option<String>,
+    /// "online" ou "offline"
+    pub modo: String,
+    /// "ativada", "expirada", "invalida", "conflito_maquina", "removida" ou "erro"
+    pub resultado: String,
+    pub detalhe: Option<String>,
+    #[serde(rename = "machineIdLocal")]
+    pub machine_id_local: Option<String>,
+    /// Preenchido apenas quando a chave está vinculada a outra máquina
+    #[serde(rename = "machineIdRemoto")]
+    pub machine_id_remoto: Option<String>,
+    #[serde(rename = "diasRestantes")]
+    pub dias_restantes: Option<i64>,
+    #[serde(rename = "horasRestantes")]
+    pub horas_restantes: Option<f64>,
+}
+
 // -- Queries --
 
 pub fn buscar_musicas_db(query: &str) -> Result<Vec<MusicaSimple>, String> {
     let termo = format!("%{}%", query.trim());
     let mut result = with_db(|conn| {
         let mut stmt = conn.prepare(
-            "SELECT codigo, artista, titulo, arquivo FROM musicas_local
+            "SELECT codigo, artista, titulo, arquivo, extensao, tipo_midia, letra, duracao FROM musicas_local
              WHERE artista LIKE ?1 OR titulo LIKE ?1 OR codigo LIKE ?1
              LIMIT 50"
         )?;
@@ -131,6 +401,10 @@ pub fn buscar_musicas_db(query: &str) -> Result<Vec<MusicaSimple>, String> {
                 artista: row.get(1)?,
                 titulo: row.get(2)?,
                 arquivo: row.get(3)?,
+                extensao: row.get(4)?,
+                tipo_midia: row.get(5)?,
+                letra: row.get(6)?,
+                duracao: row.get(7)?,
             })
         })?;
         let mut out = Vec::new();
@@ -183,7 +457,7 @@ pub fn get_musica_by_codigo_db(codigo: &str) -> Result<Option<MusicaSimple>, Str
 fn get_musica_by_codigo_db_exact(codigo: &str) -> Result<Option<MusicaSimple>, String> {
     with_db(|conn| {
         let mut stmt = conn.prepare(
-            "SELECT codigo, artista, titulo, arquivo FROM musicas_local WHERE codigo = ?1"
+            "SELECT codigo, artista, titulo, arquivo, extensao, tipo_midia, letra, duracao FROM musicas_local WHERE codigo = ?1"
         )?;
         let mut rows = stmt.query_map(params![codigo], |row| {
             Ok(MusicaSimple {
@@ -191,6 +465,10 @@ fn get_musica_by_codigo_db_exact(codigo: &str) -> Result<Option<MusicaSimple>, S
                 artista: row.get(1)?,
                 titulo: row.get(2)?,
                 arquivo: row.get(3)?,
+                extensao: row.get(4)?,
+                tipo_midia: row.get(5)?,
+                letra: row.get(6)?,
+                duracao: row.get(7)?,
             })
         })?;
         match rows.next() {
@@ -220,11 +498,17 @@ pub fn count_musicas_local() -> Result<i64, String> {
 }
 
 pub fn insert_musica(musica: &Musica) -> Result<(), String> {
+    // Extensão, tipo e letra saem do próprio arquivo
+    let midia = midia::identificar(Path::new(&musica.arquivo));
+    let extensao = midia.as_ref().map(|m| m.extensao.clone()).unwrap_or_else(|| midia::EXTENSAO_PADRAO.to_string());
+    let tipo = midia.map(|m| m.tipo).unwrap_or(midia::TipoMidia::Video);
+    let letra = letra::arquivo_letra(Path::new(&musica.arquivo)).map(|p| p.to_string_lossy().to_string());
     with_db(|conn| {
         conn.execute(
             "INSERT OR REPLACE INTO musicas_local 
-             (id, codigo, artista, titulo, arquivo, nome_arquivo, tamanho, duracao, user_id, synced_at, created_at, updated_at)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
+             (id, codigo, artista, titulo, arquivo, nome_arquivo, tamanho, duracao, user_id, synced_at, created_at, updated_at,
+              arquivo_remoto, sha256, extensao, tipo_midia, letra)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17)",
             params![
                 musica.id,
                 musica.codigo,
@@ -238,6 +522,11 @@ pub fn insert_musica(musica: &Musica) -> Result<(), String> {
                 chrono::Utc::now().timestamp_millis(),
                 musica.tamanho.unwrap_or(chrono::Utc::now().timestamp_millis()),
                 chrono::Utc::now().timestamp_millis(),
+                musica.arquivo_remoto,
+                musica.sha256,
+                extensao,
+                tipo.como_str(),
+                letra,
             ],
         )?;
         Ok(())
@@ -255,19 +544,53 @@ pub fn musica_existe(codigo: &str) -> Result<bool, String> {
     })
 }
 
-pub fn salvar_historico_db(codigo: &str) -> Result<(), String> {
+pub fn salvar_historico_db(codigo: &str, tom: i32) -> Result<(), String> {
     with_db(|conn| {
         let id = uuid::Uuid::new_v4().to_string();
         let now = chrono::Utc::now().timestamp_millis();
         conn.execute(
-            "INSERT INTO historico_local (id, codigo, data_execucao, created_at)
-             VALUES (?1, ?2, ?3, ?4)",
-            params![id, codigo, now, now],
+            "INSERT INTO historico_local (id, codigo, data_execucao, created_at, tom)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![id, codigo, now, now, tom],
         )?;
         Ok(())
     })
 }
 
+/// Execução registrada nesta máquina.
+#[derive(Debug, Clone)]
+pub struct HistoricoLocal {
+    pub id: String,
+    pub codigo: String,
+    pub data_execucao: i64,
+    pub tom: i32,
+}
+
+/// Execuções ainda não enviadas ao histórico da conta, das mais antigas.
+pub fn historico_nao_enviado(limite: i64) -> Result<Vec<HistoricoLocal>, String> {
+    with_db(|conn| {
+        let mut stmt = conn.prepare(
+            "SELECT id, codigo, data_execucao, tom FROM historico_local
+             WHERE synced_at IS NULL ORDER BY data_execucao LIMIT ?1",
+        )?;
+        let rows = stmt.query_map(params![limite], |row| {
+            Ok(HistoricoLocal { id: row.get(0)?, codigo: row.get(1)?, data_execucao: row.get(2)?, tom: row.get(3)? })
+        })?;
+        rows.collect()
+    })
+}
+
+pub fn marcar_historico_enviado(ids: &[String]) -> Result<(), String> {
+    with_db(|conn| {
+        let now = chrono::Utc::now().timestamp_millis();
+        let tx = conn.unchecked_transaction()?;
+        for id in ids {
+            tx.execute("UPDATE historico_local SET synced_at = ?1 WHERE id = ?2", params![now, id])?;
+        }
+        tx.commit()
+    })
+}
+
 pub fn get_ativacao() -> Result<Option<Ativacao>, String> {
     with_db(|conn| {
         let mut stmt = conn.prepare(
@@ -312,6 +635,71 @@ pub fn remover_ativacao_db() -> Result<(), String> {
     })
 }
 
+/// Quantidade máxima de eventos de ativação mantidos no SQLite.
+const MAX_EVENTOS_ATIVACAO: i64 = 1000;
+
+pub fn registrar_evento_ativacao(evento: &AtivacaoEvento) -> Result<(), String> {
+    with_db(|conn| {
+        conn.execute(
+            "INSERT INTO ativacao_eventos
+             (id, data_evento, acao, chave_mascarada, modo, resultado, detalhe,
+              machine_id_local, machine_id_remoto, dias_restantes, horas_restantes)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+            params![
+                evento.id,
+                evento.data_evento,
+                evento.acao,
+                evento.chave_mascarada,
+                evento.modo,
+                evento.resultado,
+                evento.detalhe,
+                evento.machine_id_local,
+                evento.machine_id_remoto,
+                evento.dias_restantes,
+                evento.horas_restantes,
+            ],
+        )?;
+        // Mantém apenas os eventos mais recentes
+        conn.execute(
+            "DELETE FROM ativacao_eventos WHERE id NOT IN
+             (SELECT id FROM ativacao_eventos ORDER BY data_evento DESC LIMIT ?1)",
+            params![MAX_EVENTOS_ATIVACAO],
+        )?;
+        Ok(())
+    })
+}
+
+/// Eventos de ativação do mais recente para o mais antigo.
+pub fn listar_eventos_ativacao(limite: i64) -> Result<Vec<AtivacaoEvento>, String> {
+    with_db(|conn| {
+        let mut stmt = conn.prepare(
+            "SELECT id, data_evento, acao, chave_mascarada, modo, resultado, detalhe,
+                    machine_id_local, machine_id_remoto, dias_restantes, horas_restantes
+             FROM ativacao_eventos ORDER BY data_evento DESC LIMIT ?1"
+        )?;
+        let rows = stmt.query_map(params![limite], |row| {
+            Ok(AtivacaoEvento {
+                id: row.get(0)?,
+                data_evento: row.get(1)?,
+                acao: row.get(2)?,
+                chave_mascarada: row.get(3)?,
+                modo: row.get(4)?,
+                resultado: row.get(5)?,
+                detalhe: row.get(6)?,
+                machine_id_local: row.get(7)?,
+                machine_id_remoto: row.get(8)?,
+                dias_restantes: row.get(9)?,
+                horas_restantes: row.get(10)?,
+            })
+        })?;
+        let mut out = Vec::new();
+        for r in rows {
+            out.push(r?);
+        }
+        Ok(out)
+    })
+}
+
 /// Retorna o machine_id desta máquina.
 /// Na primeira chamada, gera um UUID v4 aleatório e persiste no SQLite.
 /// Nas chamadas subsequentes, retorna o valor armazenado.
@@ -345,6 +733,706 @@ pub fn get_or_create_machine_id() -> Result<String, String> {
     })
 }
 
+pub fn get_config(chave: &str) -> Result<Option<String>, String> {
+    with_db(|conn| {
+        let mut stmt = conn.prepare("SELECT valor FROM config_local WHERE chave = ?1")?;
+        let mut rows = stmt.query_map(params![chave], |row| row.get::<_, String>(0))?;
+        match rows.next() {
+            Some(r) => Ok(Some(r?)),
+            None => Ok(None),
+        }
+    })
+}
+
+pub fn set_config(chave: &str, valor: &str) -> Result<(), String> {
+    with_db(|conn| {
+        conn.execute(
+            "INSERT OR REPLACE INTO config_local (chave, valor, updated_at) VALUES (?1, ?2, ?3)",
+            params![chave, valor, chrono::Utc::now().timestamp_millis()],
+        )?;
+        Ok(())
+    })
+}
+
+pub fn remover_config(chave: &str) -> Result<(), String> {
+    with_db(|conn| {
+        conn.execute("DELETE FROM config_local WHERE chave = ?1", params![chave])?;
+        Ok(())
+    })
+}
+
+/// Grava as linhas recebidas do catálogo remoto, propaga metadados para as
+/// músicas já baixadas e avança o high-water mark, tudo na mesma transação.
+/// Tombstones saem do espelho; vídeos trocados ficam com `precisa_atualizar`.
+pub fn aplicar_catalogo(musicas: &[MusicaCatalogo], high_water_mark: Option<&str>) -> Result<CatalogoAplicado, String> {
+    with_db(|conn| {
+        let tx = conn.unchecked_transaction()?;
+        let now = chrono::Utc::now().timestamp_millis();
+        let mut out = CatalogoAplicado::default();
+        for m in musicas {
+            if m.removida {
+                if tx.execute("DELETE FROM catalogo_remoto WHERE id = ?1", params![m.id])? > 0
+                    || musica_local_por_id(&tx, &m.id)?.is_some()
+                {
+                    out.removidas.push(m.codigo.clone());
+                }
+                continue;
+            }
+            let existe: bool = tx.query_row(
+                "SELECT COUNT(*) FROM catalogo_remoto WHERE id = ?1",
+                params![m.id],
+                |row| row.get::<_, i64>(0),
+            )? > 0;
+            // Código reaproveitado por outra linha remota: a antiga sai do espelho
+            tx.execute(
+                "DELETE FROM catalogo_remoto WHERE codigo = ?1 AND id != ?2",
+                params![m.codigo, m.id],
+            )?;
+            tx.execute(
+                "INSERT INTO catalogo_remoto
+                 (id, codigo, artista, titulo, arquivo, nome_arquivo, tamanho, duracao, user_id, remote_updated_at, synced_at, sha256,
+                  remote_created_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)
+                 ON CONFLICT(id) DO UPDATE SET
+                    codigo = excluded.codigo, artista = excluded.artista, titulo = excluded.titulo,
+                    arquivo = excluded.arquivo, nome_arquivo = excluded.nome_arquivo,
+                    tamanho = excluded.tamanho, duracao = excluded.duracao, user_id = excluded.user_id,
+                    remote_updated_at = excluded.remote_updated_at, synced_at = excluded.synced_at,
+                    sha256 = excluded.sha256, remote_created_at = excluded.remote_created_at",
+                params![
+                    m.id, m.codigo, m.artista, m.titulo, m.arquivo, m.nome_arquivo,
+                    m.tamanho, m.duracao, m.user_id, m.updated_at, now, m.sha256, m.created_at,
+                ],
+            )?;
+            if existe {
+                out.atualizadas += 1;
+            } else {
+                out.novas += 1;
+            }
+            // Correções de artista/título valem também para o que já foi baixado
+            out.locais_atualizadas += tx.execute(
+                "UPDATE musicas_local SET artista = ?2, titulo = ?3, nome_arquivo = ?4,
+                    duracao = COALESCE(?5, duracao), updated_at = ?6
+                 WHERE id = ?1 AND (artista != ?2 OR titulo != ?3
+                    OR COALESCE(nome_arquivo, '') != COALESCE(?4, '')
+                    OR (?5 IS NOT NULL AND COALESCE(duracao, -1) != ?5))",
+                params![m.id, m.artista, m.titulo, m.nome_arquivo, m.duracao, now],
+            )? as i64;
+            // Código reaproveitado por outra música: o vídeo local é de outra
+            if tx.execute(
+                "UPDATE musicas_local SET precisa_atualizar = 1, updated_at = ?3
+                 WHERE codigo = ?1 AND id != ?2 AND precisa_atualizar = 0",
+                params![m.codigo, m.id, now],
+            )? > 0
+            {
+                out.substituidas.push(m.codigo.clone());
+            }
+            // Vídeo trocado no catálogo (outra URL ou outro hash): baixar de novo.
+            // O arquivo atual continua tocável até o novo chegar.
+            if tx.execute(
+                "UPDATE musicas_local SET precisa_atualizar = 1, updated_at = ?4
+                 WHERE id = ?1 AND precisa_atualizar = 0 AND (
+                    (arquivo_remoto IS NOT NULL AND arquivo_remoto != ?2)
+                    OR (?3 IS NOT NULL AND sha256 IS NOT NULL AND sha256 != ?3))",
+                params![m.id, m.arquivo, m.sha256, now],
+            )? > 0
+            {
+                out.substituidas.push(m.codigo.clone());
+            }
+        }
+        if let Some(hwm) = high_water_mark {
+            tx.execute(
+                "INSERT OR REPLACE INTO config_local (chave, valor, updated_at) VALUES ('catalogo_hwm', ?1, ?2)",
+                params![hwm, now],
+            )?;
+        }
+        tx.commit()?;
+        Ok(out)
+    })
+}
+
+pub fn count_catalogo() -> Result<i64, String> {
+    with_db(|conn| {
+        conn.query_row("SELECT COUNT(*) FROM catalogo_remoto", [], |row| row.get(0))
+    })
+}
+
+/// Músicas do catálogo que ainda não estão na máquina.
+pub fn count_nao_baixadas() -> Result<i64, String> {
+    with_db(|conn| {
+        conn.query_row(
+            "SELECT COUNT(*) FROM catalogo_remoto c
+             WHERE NOT EXISTS (SELECT 1 FROM musicas_local l WHERE l.codigo = c.codigo)",
+            [],
+            |row| row.get(0),
+        )
+    })
+}
+
+/// Momento (ms) da última gravação no espelho do catálogo.
+pub fn catalogo_sincronizado_em() -> Result<Option<i64>, String> {
+    with_db(|conn| conn.query_row("SELECT MAX(synced_at) FROM catalogo_remoto", [], |row| row.get(0)))
+}
+
+pub fn listar_nao_baixadas(limite: i64, offset: i64) -> Result<Vec<MusicaCatalogo>, String> {
+    with_db(|conn| {
+        let mut stmt = conn.prepare(
+            "SELECT id, codigo, artista, titulo, arquivo, nome_arquivo, tamanho, duracao, user_id, remote_updated_at, sha256, remote_created_at
+             FROM catalogo_remoto c
+             WHERE NOT EXISTS (SELECT 1 FROM musicas_local l WHERE l.codigo = c.codigo)
+             ORDER BY artista, titulo
+             LIMIT ?1 OFFSET ?2"
+        )?;
+        let rows = stmt.query_map(params![limite, offset], map_musica_catalogo)?;
+        rows.collect()
+    })
+}
+
+/// Busca no catálogo inteiro (baixadas ou não), sem depender da rede.
+pub fn buscar_catalogo_db(query: &str) -> Result<Vec<MusicaCatalogoBusca>, String> {
+    let termo = format!("%{}%", query.trim());
+    with_db(|conn| {
+        let mut stmt = conn.prepare(
+            "SELECT c.codigo, c.artista, c.titulo, c.duracao,
+                    EXISTS (SELECT 1 FROM musicas_local l WHERE l.codigo = c.codigo)
+             FROM catalogo_remoto c
+             WHERE c.artista LIKE ?1 OR c.titulo LIKE ?1 OR c.codigo LIKE ?1
+             ORDER BY c.artista, c.titulo
+             LIMIT 50"
+        )?;
+        let rows = stmt.query_map(params![&termo], |row| {
+            Ok(MusicaCatalogoBusca {
+                codigo: row.get(0)?,
+                artista: row.get(1)?,
+                titulo: row.get(2)?,
+                duracao: row.get(3)?,
+                baixada: row.get(4)?,
+            })
+        })?;
+        rows.collect()
+    })
+}
+
+/// Catálogo inteiro na ordem de prioridade de download.
+pub fn listar_catalogo() -> Result<Vec<MusicaCatalogo>, String> {
+    with_db(|conn| {
+        let mut stmt = conn.prepare(&format!(
+            "SELECT c.id, c.codigo, c.artista, c.titulo, c.arquivo, c.nome_arquivo, c.tamanho, c.duracao, c.user_id,
+                    c.remote_updated_at, c.sha256, c.remote_created_at
+             FROM catalogo_remoto c
+             LEFT JOIN demanda_musicas d ON d.codigo = c.codigo
+             ORDER BY {}, c.codigo",
+            ORDEM_PRIORIDADE
+        ))?;
+        let rows = stmt.query_map([], map_musica_catalogo)?;
+        let mut out = Vec::new();
+        for r in rows {
+            out.push(r?);
+        }
+        Ok(out)
+    })
+}
+
+pub fn get_catalogo_by_codigo(codigo: &str) -> Result<Option<MusicaCatalogo>, String> {
+    with_db(|conn| {
+        let mut stmt = conn.prepare(
+            "SELECT id, codigo, artista, titulo, arquivo, nome_arquivo, tamanho, duracao, user_id, remote_updated_at, sha256, remote_created_at
+             FROM catalogo_remoto WHERE codigo = ?1"
+        )?;
+        let mut rows = stmt.query_map(params![codigo], map_musica_catalogo)?;
+        match rows.next() {
+            Some(r) => Ok(Some(r?)),
+            None => Ok(None),
+        }
+    })
+}
+
+fn musica_local_por_id(conn: &Connection, id: &str) -> rusqlite::Result<Option<String>> {
+    let mut stmt = conn.prepare("SELECT codigo FROM musicas_local WHERE id = ?1")?;
+    let mut rows = stmt.query_map(params![id], |row| row.get::<_, String>(0))?;
+    match rows.next() {
+        Some(r) => Ok(Some(r?)),
+        None => Ok(None),
+    }
+}
+
+/// Remove do espelho as linhas não vistas numa sincronização completa que
+/// começou em `inicio` (apagadas de verdade no servidor). Retorna os códigos.
+pub fn remover_catalogo_nao_visto(inicio: i64) -> Result<Vec<String>, String> {
+    with_db(|conn| {
+        let codigos: Vec<String> = {
+            let mut stmt = conn.prepare("SELECT codigo FROM catalogo_remoto WHERE synced_at < ?1")?;
+            let rows = stmt.query_map(params![inicio], |row| row.get::<_, String>(0))?;
+            rows.collect::<Result<_, _>>()?
+        };
+        conn.execute("DELETE FROM catalogo_remoto WHERE synced_at < ?1", params![inicio])?;
+        Ok(codigos)
+    })
+}
+
+pub fn marcar_precisa_atualizar(codigo: &str) -> Result<(), String> {
+    with_db(|conn| {
+        conn.execute("UPDATE musicas_local SET precisa_atualizar = 1 WHERE codigo = ?1", params![codigo])?;
+        Ok(())
+    })
+}
+
+/// Músicas baixadas cujo vídeo mudou no catálogo.
+pub fn musicas_precisam_atualizar() -> Result<Vec<String>, String> {
+    with_db(|conn| {
+        let mut stmt = conn.prepare("SELECT codigo FROM musicas_local WHERE precisa_atualizar = 1")?;
+        let rows = stmt.query_map([], |row| row.get::<_, String>(0))?;
+        rows.collect()
+    })
+}
+
+pub fn get_musica_local(codigo: &str) -> Result<Option<Musica>, String> {
+    with_db(|conn| {
+        let mut stmt = conn.prepare(
+            "SELECT id, codigo, artista, titulo, arquivo, nome_arquivo, tamanho, duracao, user_id, arquivo_remoto, sha256
+             FROM musicas_local WHERE codigo = ?1"
+        )?;
+        let mut rows = stmt.query_map(params![codigo], map_musica)?;
+        match rows.next() {
+            Some(r) => Ok(Some(r?)),
+            None => Ok(None),
+        }
+    })
+}
+
+pub fn listar_musicas_local() -> Result<Vec<Musica>, String> {
+    with_db(|conn| {
+        let mut stmt = conn.prepare(
+            "SELECT id, codigo, artista, titulo, arquivo, nome_arquivo, tamanho, duracao, user_id, arquivo_remoto, sha256
+             FROM musicas_local ORDER BY codigo"
+        )?;
+        let rows = stmt.query_map([], map_musica)?;
+        rows.collect()
+    })
+}
+
+fn map_musica(row: &rusqlite::Row) -> rusqlite::Result<Musica> {
+    Ok(Musica {
+        id: row.get(0)?,
+        codigo: row.get(1)?,
+        artista: row.get(2)?,
+        titulo: row.get(3)?,
+        arquivo: row.get(4)?,
+        nome_arquivo: row.get(5)?,
+        tamanho: row.get(6)?,
+        duracao: row.get(7)?,
+        user_id: row.get(8)?,
+        arquivo_remoto: row.get(9)?,
+        sha256: row.get(10)?,
+    })
+}
+
+pub fn remover_musica_local(codigo: &str) -> Result<(), String> {
+    with_db(|conn| {
+        conn.execute("DELETE FROM musicas_local WHERE codigo = ?1", params![codigo])?;
+        Ok(())
+    })
+}
+
+pub fn inserir_quarentena(q: &MusicaQuarentena) -> Result<(), String> {
+    with_db(|conn| {
+        conn.execute(
+            "INSERT OR REPLACE INTO musicas_quarentena
+             (codigo, id, artista, titulo, arquivo_original, arquivo_quarentena, nome_arquivo,
+              tamanho, duracao, user_id, arquivo_remoto, sha256, motivo, quarentena_em, expira_em)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15)",
+            params![
+                q.codigo, q.id, q.artista, q.titulo, q.arquivo_original, q.arquivo_quarentena,
+                q.nome_arquivo, q.tamanho, q.duracao, q.user_id, q.arquivo_remoto, q.sha256,
+                q.motivo, q.quarentena_em, q.expira_em,
+            ],
+        )?;
+        Ok(())
+    })
+}
+
+pub fn listar_quarentena() -> Result<Vec<MusicaQuarentena>, String> {
+    with_db(|conn| {
+        let mut stmt = conn.prepare(
+            "SELECT codigo, id, artista, titulo, arquivo_original, arquivo_quarentena, nome_arquivo,
+                    tamanho, duracao, user_id, arquivo_remoto, sha256, motivo, quarentena_em, expira_em
+             FROM musicas_quarentena ORDER BY quarentena_em DESC"
+        )?;
+        let rows = stmt.query_map([], |row| {
+            Ok(MusicaQuarentena {
+                codigo: row.get(0)?,
+                id: row.get(1)?,
+                artista: row.get(2)?,
+                titulo: row.get(3)?,
+                arquivo_original: row.get(4)?,
+                arquivo_quarentena: row.get(5)?,
+                nome_arquivo: row.get(6)?,
+                tamanho: row.get(7)?,
+                duracao: row.get(8)?,
+                user_id: row.get(9)?,
+                arquivo_remoto: row.get(10)?,
+                sha256: row.get(11)?,
+                motivo: row.get(12)?,
+                quarentena_em: row.get(13)?,
+                expira_em: row.get(14)?,
+            })
+        })?;
+        rows.collect()
+    })
+}
+
+pub fn remover_quarentena(codigo: &str) -> Result<(), String> {
+    with_db(|conn| {
+        conn.execute("DELETE FROM musicas_quarentena WHERE codigo = ?1", params![codigo])?;
+        Ok(())
+    })
+}
+
+pub fn get_download_parcial(destino: &str) -> Result<Option<DownloadParcial>, String> {
+    with_db(|conn| {
+        let mut stmt = conn.prepare(
+            "SELECT destino, url, etag, last_modified, tamanho_total, updated_at
+             FROM downloads_parciais WHERE destino = ?1"
+        )?;
+        let mut rows = stmt.query_map(params![destino], map_download_parcial)?;
+        match rows.next() {
+            Some(r) => Ok(Some(r?)),
+            None => Ok(None),
+        }
+    })
+}
+
+pub fn listar_downloads_parciais() -> Result<Vec<DownloadParcial>, String> {
+    with_db(|conn| {
+        let mut stmt = conn.prepare(
+            "SELECT destino, url, etag, last_modified, tamanho_total, updated_at FROM downloads_parciais"
+        )?;
+        let rows = stmt.query_map([], map_download_parcial)?;
+        rows.collect()
+    })
+}
+
+pub fn salvar_download_parcial(d: &DownloadParcial) -> Result<(), String> {
+    with_db(|conn| {
+        conn.execute(
+            "INSERT OR REPLACE INTO downloads_parciais (destino, url, etag, last_modified, tamanho_total, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![d.destino, d.url, d.etag, d.last_modified, d.tamanho_total, d.updated_at],
+        )?;
+        Ok(())
+    })
+}
+
+pub fn remover_download_parcial(destino: &str) -> Result<(), String> {
+    with_db(|conn| {
+        conn.execute("DELETE FROM downloads_parciais WHERE destino = ?1", params![destino])?;
+        Ok(())
+    })
+}
+
+// -- Fila de downloads --
+
+/// Põe os códigos na fila como pendentes. Os que já estão na fila (inclusive
+/// cancelados) ficam como estão. Retorna quantos entraram.
+pub fn enfileirar_downloads(codigos: &[String]) -> Result<i64, String> {
+    with_db(|conn| {
+        let tx = conn.unchecked_transaction()?;
+        let now = chrono::Utc::now().timestamp_millis();
+        let mut novos = 0;
+        for codigo in codigos {
+            novos += tx.execute(
+                "INSERT OR IGNORE INTO fila_downloads (codigo, estado, created_at, updated_at)
+                 VALUES (?1, 'pendente', ?2, ?2)",
+                params![codigo, now],
+            )? as i64;
+        }
+        tx.commit()?;
+        Ok(novos)
+    })
+}
+
+/// Ordem de download (com `c` = catalogo_remoto e `d` = demanda_musicas):
+/// pedidas, buscadas, mais tocadas no geral, lançamentos mais recentes.
+const ORDEM_PRIORIDADE: &str = "COALESCE(d.pedidos, 0) DESC, COALESCE(d.buscas, 0) DESC,
+    COALESCE(c.popularidade, 0) DESC, c.remote_created_at DESC";
+
+/// Próximo código a baixar: pendentes primeiro, depois erros cuja espera
+/// já passou; dentro de cada grupo, "furar fila" e depois ORDEM_PRIORIDADE.
+/// `ignorar` são os que já estão baixando.
+pub fn proximo_download(ignorar: &[String], max_tentativas: i64) -> Result<Option<String>, String> {
+    let now = chrono::Utc::now().timestamp_millis();
+    with_db(|conn| {
+        let mut stmt = conn.prepare(&format!(
+            "SELECT f.codigo FROM fila_downloads f
+             LEFT JOIN catalogo_remoto c ON c.codigo = f.codigo
+             LEFT JOIN demanda_musicas d ON d.codigo = f.codigo
+             WHERE f.estado = 'pendente'
+                OR (f.estado = 'erro' AND f.tentativas < ?1 AND COALESCE(f.proxima_tentativa, 0) <= ?2)
+             ORDER BY f.estado = 'erro', f.prioridade DESC, {}, f.codigo",
+            ORDEM_PRIORIDADE
+        ))?;
+        let rows = stmt.query_map(params![max_tentativas, now], |row| row.get::<_, String>(0))?;
+        for r in rows {
+            let codigo = r?;
+            if !ignorar.contains(&codigo) {
+                return Ok(Some(codigo));
+            }
+        }
+        Ok(None)
+    })
+}
+
+/// Momento da próxima nova tentativa agendada, se houver.
+pub fn proxima_tentativa_download(max_tentativas: i64) -> Result<Option<i64>, String> {
+    with_db(|conn| {
+        conn.query_row(
+            "SELECT MIN(proxima_tentativa) FROM fila_downloads WHERE estado = 'erro' AND tentativas < ?1",
+            params![max_tentativas],
+            |row| row.get(0),
+        )
+    })
+}
+
+pub fn marcar_download(codigo: &str, estado: &str, erro: Option<&str>, proxima_tentativa: Option<i64>) -> Result<(), String> {
+    with_db(|conn| {
+        let tentativa = if estado == "erro" { 1 } else { 0 };
+        conn.execute(
+            "UPDATE fila_downloads SET estado = ?2, erro = ?3, proxima_tentativa = ?4,
+                tentativas = tentativas + ?5, updated_at = ?6
+             WHERE codigo = ?1",
+            params![codigo, estado, erro, proxima_tentativa, tentativa, chrono::Utc::now().timestamp_millis()],
+        )?;
+        Ok(())
+    })
+}
+
+pub fn tentativas_download(codigo: &str) -> Result<i64, String> {
+    with_db(|conn| {
+        conn.query_row(
+            "SELECT COALESCE(MAX(tentativas), 0) FROM fila_downloads WHERE codigo = ?1",
+            params![codigo],
+            |row| row.get(0),
+        )
+    })
+}
+
+pub fn remover_da_fila(codigo: &str) -> Result<(), String> {
+    with_db(|conn| {
+        conn.execute("DELETE FROM fila_downloads WHERE codigo = ?1", params![codigo])?;
+        Ok(())
+    })
+}
+
+/// Põe o código na frente da fila (mesmo se cancelado ou em erro).
+pub fn priorizar_download(codigo: &str) -> Result<(), String> {
+    with_db(|conn| {
+        let now = chrono::Utc::now().timestamp_millis();
+        conn.execute(
+            "INSERT INTO fila_downloads (codigo, estado, prioridade, created_at, updated_at)
+             VALUES (?1, 'pendente', (SELECT COALESCE(MAX(prioridade), 0) + 1 FROM fila_downloads), ?2, ?2)
+             ON CONFLICT(codigo) DO UPDATE SET
+                estado = CASE WHEN estado = 'baixando' THEN estado ELSE 'pendente' END,
+                prioridade = (SELECT COALESCE(MAX(prioridade), 0) + 1 FROM fila_downloads),
+                tentativas = 0, erro = NULL, proxima_tentativa = NULL, updated_at = ?2",
+            params![codigo, now],
+        )?;
+        conn.execute("DELETE FROM musicas_liberadas WHERE codigo = ?1", params![codigo])?;
+        Ok(())
+    })
+}
+
+/// Registra que um código ausente foi pedido ("pedido") ou apareceu numa
+/// busca ("busca"). Só conta códigos que existem no catálogo.
+pub fn registrar_demanda(codigo: &str, tipo: &str) -> Result<(), String> {
+    let (pedidos, buscas) = if tipo == "pedido" { (1, 0) } else { (0, 1) };
+    with_db(|conn| {
+        conn.execute(
+            "INSERT INTO demanda_musicas (codigo, pedidos, buscas, ultimo_em)
+             SELECT ?1, ?2, ?3, ?4 WHERE EXISTS (SELECT 1 FROM catalogo_remoto WHERE codigo = ?1)
+             ON CONFLICT(codigo) DO UPDATE SET
+                pedidos = pedidos + excluded.pedidos, buscas = buscas + excluded.buscas,
+                ultimo_em = excluded.ultimo_em",
+            params![codigo, pedidos, buscas, chrono::Utc::now().timestamp_millis()],
+        )?;
+        Ok(())
+    })
+}
+
+pub fn remover_demanda(codigo: &str) -> Result<(), String> {
+    with_db(|conn| {
+        conn.execute("DELETE FROM demanda_musicas WHERE codigo = ?1", params![codigo])?;
+        Ok(())
+    })
+}
+
+/// Grava o ranking global (id remoto, execuções); quem não está nele fica com 0.
+pub fn aplicar_popularidade(ranking: &[(String, i64)]) -> Result<(), String> {
+    with_db(|conn| {
+        let tx = conn.unchecked_transaction()?;
+        tx.execute("UPDATE catalogo_remoto SET popularidade = 0 WHERE popularidade != 0", [])?;
+        for (id, total) in ranking {
+            tx.execute("UPDATE catalogo_remoto SET popularidade = ?2 WHERE id = ?1", params![id, total])?;
+        }
+        tx.commit()
+    })
+}
+
+/// Downloads que estavam em andamento quando o app fechou voltam a pendentes.
+pub fn reiniciar_fila_downloads() -> Result<(), String> {
+    with_db(|conn| {
+        conn.execute("UPDATE fila_downloads SET estado = 'pendente' WHERE estado = 'baixando'", [])?;
+        Ok(())
+    })
+}
+
+/// Cancela todos os pendentes/erros (os em andamento são tratados pelo gerenciador).
+pub fn cancelar_fila_downloads() -> Result<Vec<String>, String> {
+    with_db(|conn| {
+        let codigos: Vec<String> = {
+            let mut stmt = conn.prepare("SELECT codigo FROM fila_downloads WHERE estado != 'cancelado'")?;
+            let rows = stmt.query_map([], |row| row.get::<_, String>(0))?;
+            rows.collect::<Result<_, _>>()?
+        };
+        conn.execute(
+            "UPDATE fila_downloads SET estado = 'cancelado', updated_at = ?1 WHERE estado != 'cancelado'",
+            params![chrono::Utc::now().timestamp_millis()],
+        )?;
+        Ok(codigos)
+    })
+}
+
+/// Volta cancelados/erros para pendente (None = todos).
+pub fn reenfileirar_downloads(codigo: Option<&str>) -> Result<i64, String> {
+    with_db(|conn| {
+        let n = conn.execute(
+            "UPDATE fila_downloads SET estado = 'pendente', tentativas = 0, erro = NULL,
+                proxima_tentativa = NULL, updated_at = ?2
+             WHERE estado IN ('cancelado', 'erro') AND (?1 IS NULL OR codigo = ?1)",
+            params![codigo, chrono::Utc::now().timestamp_millis()],
+        )?;
+        Ok(n as i64)
+    })
+}
+
+/// Quantidade por estado ("pendente", "baixando", "erro", "cancelado").
+pub fn contar_fila_downloads() -> Result<Vec<(String, i64)>, String> {
+    with_db(|conn| {
+        let mut stmt = conn.prepare("SELECT estado, COUNT(*) FROM fila_downloads GROUP BY estado")?;
+        let rows = stmt.query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?)))?;
+        rows.collect()
+    })
+}
+
+fn map_download_parcial(row: &rusqlite::Row) -> rusqlite::Result<DownloadParcial> {
+    Ok(DownloadParcial {
+        destino: row.get(0)?,
+        url: row.get(1)?,
+        etag: row.get(2)?,
+        last_modified: row.get(3)?,
+        tamanho_total: row.get(4)?,
+        updated_at: row.get(5)?,
+    })
+}
+
+fn map_musica_catalogo(row: &rusqlite::Row) -> rusqlite::Result<MusicaCatalogo> {
+    Ok(MusicaCatalogo {
+        id: row.get(0)?,
+        codigo: row.get(1)?,
+        artista: row.get(2)?,
+        titulo: row.get(3)?,
+        arquivo: row.get(4)?,
+        nome_arquivo: row.get(5)?,
+        tamanho: row.get(6)?,
+        duracao: row.get(7)?,
+        user_id: row.get(8)?,
+        updated_at: row.get(9)?,
+        sha256: row.get(10)?,
+        created_at: row.get(11)?,
+        removida: false,
+    })
+}
+
+pub fn fixar_musica(codigo: &str, fixada: bool) -> Result<(), String> {
+    with_db(|conn| {
+        if fixada {
+            conn.execute(
+                "INSERT OR IGNORE INTO musicas_fixadas (codigo, fixada_em) VALUES (?1, ?2)",
+                params![codigo, chrono::Utc::now().timestamp_millis()],
+            )?;
+        } else {
+            conn.execute("DELETE FROM musicas_fixadas WHERE codigo = ?1", params![codigo])?;
+        }
+        Ok(())
+    })
+}
+
+pub fn listar_fixadas() -> Result<Vec<String>, String> {
+    with_db(|conn| {
+        let mut stmt = conn.prepare("SELECT codigo FROM musicas_fixadas ORDER BY codigo")?;
+        let rows = stmt.query_map([], |row| row.get::<_, String>(0))?;
+        rows.collect()
+    })
+}
+
+pub fn registrar_liberada(codigo: &str, tamanho: Option<i64>) -> Result<(), String> {
+    with_db(|conn| {
+        conn.execute(
+            "INSERT OR REPLACE INTO musicas_liberadas (codigo, tamanho, liberada_em) VALUES (?1, ?2, ?3)",
+            params![codigo, tamanho, chrono::Utc::now().timestamp_millis()],
+        )?;
+        Ok(())
+    })
+}
+
+pub fn listar_liberadas() -> Result<Vec<String>, String> {
+    with_db(|conn| {
+        let mut stmt = conn.prepare("SELECT codigo FROM musicas_liberadas")?;
+        let rows = stmt.query_map([], |row| row.get::<_, String>(0))?;
+        rows.collect()
+    })
+}
+
+/// Devolve à fila automática tudo que foi apagado por falta de espaço.
+pub fn limpar_liberadas() -> Result<usize, String> {
+    with_db(|conn| conn.execute("DELETE FROM musicas_liberadas", []))
+}
+
+/// Música baixada que pode ser apagada para liberar espaço.
+pub struct CandidataLiberacao {
+    pub codigo: String,
+    pub arquivo: String,
+    pub tamanho: i64,
+}
+
+/// Músicas não fixadas baixadas antes de `baixadas_antes`, na ordem em que
+/// devem sair: "menos_tocadas" (menos execuções, depois a mais tempo sem
+/// tocar) ou "sem_tocar" (a mais tempo sem tocar; nunca tocada conta desde o
+/// download).
+pub fn candidatas_liberacao(politica: &str, baixadas_antes: i64) -> Result<Vec<CandidataLiberacao>, String> {
+    let ordem = match politica {
+        "sem_tocar" => "COALESCE(h.ultima, m.synced_at, 0), m.codigo",
+        _ => "COALESCE(h.execucoes, 0), COALESCE(h.ultima, m.synced_at, 0), m.codigo",
+    };
+    with_db(|conn| {
+        let mut stmt = conn.prepare(&format!(
+            "SELECT m.codigo, m.arquivo, COALESCE(m.tamanho, 0)
+             FROM musicas_local m
+             LEFT JOIN (
+                SELECT codigo, COUNT(*) AS execucoes, MAX(data_execucao) AS ultima
+                FROM historico_local GROUP BY codigo
+             ) h ON h.codigo = m.codigo
+             WHERE m.codigo NOT IN (SELECT codigo FROM musicas_fixadas)
+               AND COALESCE(m.synced_at, 0) < ?1
+             ORDER BY {}",
+            ordem
+        ))?;
+        let rows = stmt.query_map(params![baixadas_antes], |row| {
+            Ok(CandidataLiberacao { codigo: row.get(0)?, arquivo: row.get(1)?, tamanho: row.get(2)? })
+        })?;
+        rows.collect()
+    })
+}
+
 pub fn storage_used() -> Result<i64, String> {
     with_db(|conn| {
         let total: i64 = conn.query_row(
@@ -355,3 +1443,88 @@ pub fn storage_used() -> Result<i64, String> {
         Ok(total)
     })
 }
+
+// -- Sondagem das mídias --
+
+/// (codigo, arquivo) das músicas ainda não sondadas.
+pub fn musicas_sem_sondagem(limite: i64) -> Result<Vec<(String, String)>, String> {
+    with_db(|conn| {
+        let mut stmt = conn.prepare(
+            "SELECT codigo, arquivo FROM musicas_local WHERE sondada_em IS NULL ORDER BY created_at DESC LIMIT ?1"
+        )?;
+        let rows = stmt.query_map(params![limite], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        rows.collect()
+    })
+}
+
+/// Grava a sondagem de `arquivo`; ignorada se a música mudou de arquivo no
+/// meio. A duração medida só preenche a que o catálogo não informou.
+pub fn salvar_sondagem(codigo: &str, arquivo: &str, info: &InfoMidia) -> Result<(), String> {
+    with_db(|conn| {
+        conn.execute(
+            "UPDATE musicas_local SET
+                duracao = COALESCE(duracao, ?3), largura = ?4, altura = ?5, codec_video = ?6,
+                codec_audio = ?7, canais_audio = ?8, loudness_lufs = ?9, pico_dbtp = ?10, sondada_em = ?11
+             WHERE codigo = ?1 AND arquivo = ?2",
+            params![
+                codigo,
+                arquivo,
+                info.duracao.map(|d| d.round() as i64),
+                info.largura,
+                info.altura,
+                info.codec_video,
+                info.codec_audio,
+                info.canais_audio,
+                info.loudness_lufs,
+                info.pico_dbtp,
+                chrono::Utc::now().timestamp_millis(),
+            ],
+        )?;
+        Ok(())
+    })
+}
+
+/// Sondagem gravada da música; None se não existe ou ainda não foi sondada.
+pub fn get_info_midia(codigo: &str) -> Result<Option<InfoMidia>, String> {
+    with_db(|conn| {
+        let mut stmt = conn.prepare(
+            "SELECT duracao, largura, altura, codec_video, codec_audio, canais_audio, loudness_lufs, pico_dbtp
+             FROM musicas_local WHERE codigo = ?1 AND sondada_em IS NOT NULL"
+        )?;
+        let mut rows = stmt.query_map(params![codigo], |row| {
+            Ok(InfoMidia {
+                duracao: row.get::<_, Option<i64>>(0)?.map(|d| d as f64),
+                largura: row.get(1)?,
+                altura: row.get(2)?,
+                codec_video: row.get(3)?,
+                codec_audio: row.get(4)?,
+                canais_audio: row.get(5)?,
+                loudness_lufs: row.get(6)?,
+                pico_dbtp: row.get(7)?,
+            })
+        })?;
+        rows.next().transpose()
+    })
+}
+
+/// Loudness medido do arquivo (LUFS, pico dBTP); None se ainda não medido.
+pub fn loudness_por_arquivo(arquivo: &str) -> Result<Option<(f64, Option<f64>)>, String> {
+    with_db(|conn| {
+        let mut stmt = conn.prepare(
+            "SELECT loudness_lufs, pico_dbtp FROM musicas_local WHERE arquivo = ?1 AND loudness_lufs IS NOT NULL"
+        )?;
+        let mut rows = stmt.query_map(params![arquivo], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        rows.next().transpose()
+    })
+}
+
+/// (com loudness medido, ainda não sondadas)
+pub fn contar_loudness() -> Result<(i64, i64), String> {
+    with_db(|conn| {
+        conn.query_row(
+            "SELECT COUNT(loudness_lufs), COUNT(*) - COUNT(sondada_em) FROM musicas_local",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+    })
+}
@@ -0,0 +1,297 @@
+// Gravação segura dos vídeos baixados.
+//
+// O download vai para `<destino>.part` e só vira o arquivo final depois de
+// fsync + rename atômico: um crash no meio nunca deixa um `.mp4` truncado
+// que pareça completo para a fila de downloads.
+//
+// Um `.part` interrompido é mantido junto com o estado em `downloads_parciais`
+// (URL, ETag/Last-Modified) para ser retomado com `Range` depois, inclusive
+// após reiniciar o app.
+//
+// Antes do rename o parcial é conferido com o tamanho e o SHA-256 do
+// catálogo; uma página de erro HTML ou um arquivo truncado nunca vira `.mp4`.
+// O hash é calculado enquanto os blocos chegam (o trecho de uma sessão
+// anterior é lido uma vez ao retomar), sem reler o vídeo inteiro no fim.
+
+use crate::backend::{ArquivoBaixado, BackendError, Integridade};
+use crate::db;
+use once_cell::sync::Lazy;
+use sha2::{Digest, Sha256};
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+const SUFIXO_PARCIAL: &str = ".part";
+
+/// Parciais iniciados há mais que isso são descartados na inicialização.
+const VALIDADE_PARCIAL_MS: i64 = 7 * 24 * 60 * 60 * 1000;
+
+pub fn caminho_parcial(dest: &Path) -> PathBuf {
+    let mut nome = dest.as_os_str().to_owned();
+    nome.push(SUFIXO_PARCIAL);
+    PathBuf::from(nome)
+}
+
+fn erro_io(path: &Path, e: std::io::Error) -> BackendError {
+    BackendError::Io(format!("{}: {}", path.display(), e))
+}
+
+/// Arquivo `.part` em gravação. Se for descartado sem `concluir`, o parcial
+/// fica no disco para ser retomado.
+pub struct ArquivoParcial {
+    file: Option<File>,
+    parcial: PathBuf,
+    dest: PathBuf,
+    gravados: u64,
+    /// SHA-256 de tudo o que está no parcial
+    hasher: Sha256,
+}
+
+impl ArquivoParcial {
+    /// Começa do zero (trunca um parcial existente).
+    pub fn criar(dest: &Path) -> Result<Self, BackendError> {
+        Self::criar_em(caminho_parcial(dest), dest)
+    }
+
+    /// Começa do zero num parcial só de `origem` (`<destino>.<origem>.part`),
+    /// sem mexer no parcial retomável do download. Não é retomado: sobra de
+    /// uma cópia interrompida sai em `limpar_parciais`.
+    pub fn criar_separado(dest: &Path, origem: &str) -> Result<Self, BackendError> {
+        let mut nome = dest.as_os_str().to_owned();
+        nome.push(format!(".{}{}", origem, SUFIXO_PARCIAL));
+        Self::criar_em(PathBuf::from(nome), dest)
+    }
+
+    fn criar_em(parcial: PathBuf, dest: &Path) -> Result<Self, BackendError> {
+        let file = File::create(&parcial).map_err(|e| erro_io(&parcial, e))?;
+        Ok(Self { file: Some(file), parcial, dest: dest.to_path_buf(), gravados: 0, hasher: Sha256::new() })
+    }
+
+    /// Continua gravando no fim do parcial existente.
+    pub fn retomar(dest: &Path) -> Result<Self, BackendError> {
+        let parcial = caminho_parcial(dest);
+        let file = OpenOptions::new().append(true).open(&parcial).map_err(|e| erro_io(&parcial, e))?;
+        let mut hasher = Sha256::new();
+        let gravados = File::open(&parcial)
+            .and_then(|mut f| alimentar(&mut hasher, &mut f))
+            .map_err(|e| erro_io(&parcial, e))?;
+        Ok(Self { file: Some(file), parcial, dest: dest.to_path_buf(), gravados, hasher })
+    }
+
+    /// Bytes no parcial, incluindo os de uma sessão anterior.
+    pub fn gravados(&self) -> u64 {
+        self.gravados
+    }
+
+    pub fn gravar(&mut self, bytes: &[u8]) -> Result<(), BackendError> {
+        let file = self.file.as_mut().expect("arquivo parcial já concluído");
+        file.write_all(bytes).map_err(|e| erro_io(&self.parcial, e))?;
+        self.hasher.update(bytes);
+        self.gravados += bytes.len() as u64;
+        Ok(())
+    }
+
+    /// Apaga o parcial (cópia que não vale a pena retomar).
+    pub fn descartar(mut self) {
+        drop(self.file.take());
+        std::fs::remove_file(&self.parcial).ok();
+    }
+
+    /// Garante os dados no disco, confere com `integridade` e troca o parcial
+    /// pelo destino final. Se não conferir, o parcial é apagado (não adianta
+    /// retomá-lo).
+    pub fn concluir(mut self, integridade: &Integridade) -> Result<ArquivoBaixado, BackendError> {
+        let file = self.file.take().expect("arquivo parcial já concluído");
+        file.sync_all().map_err(|e| erro_io(&self.parcial, e))?;
+        drop(file);
+
+        let sha256 = hex::encode(self.hasher.finalize());
+        if let Err(e) = conferir(self.gravados, &sha256, integridade) {
+            std::fs::remove_file(&self.parcial).ok();
+            return Err(e);
+        }
+
+        std::fs::rename(&self.parcial, &self.dest).map_err(|e| erro_io(&self.dest, e))?;
+        sincronizar_diretorio(&self.dest);
+        Ok(ArquivoBaixado { tamanho: self.gravados, sha256 })
+    }
+}
+
+fn conferir(tamanho: u64, sha256: &str, integridade: &Integridade) -> Result<(), BackendError> {
+    if let Some(esperado) = integridade.tamanho.filter(|t| *t > 0) {
+        if tamanho != esperado {
+            return Err(BackendError::Integridade(format!("{} bytes, esperado {}", tamanho, esperado)));
+        }
+    }
+    if let Some(esperado) = integridade.sha256.as_deref().filter(|h| !h.is_empty()) {
+        if !sha256.eq_ignore_ascii_case(esperado.trim()) {
+            return Err(BackendError::Integridade(format!("SHA-256 {}, esperado {}", sha256, esperado)));
+        }
+    }
+    Ok(())
+}
+
+/// SHA-256 (hex minúsculo) do conteúdo de um arquivo, lido em blocos.
+pub fn sha256_arquivo(path: &Path) -> std::io::Result<String> {
+    let mut hasher = Sha256::new();
+    alimentar(&mut hasher, &mut File::open(path)?)?;
+    Ok(hex::encode(hasher.finalize()))
+}
+
+/// Passa o conteúdo de `fonte` pelo hash; devolve quantos bytes leu.
+fn alimentar(hasher: &mut Sha256, fonte: &mut impl Read) -> std::io::Result<u64> {
+    let mut buf = vec![0u8; 256 * 1024];
+    let mut total = 0;
+    loop {
+        let n = fonte.read(&mut buf)?;
+        if n == 0 {
+            return Ok(total);
+        }
+        hasher.update(&buf[..n]);
+        total += n as u64;
+    }
+}
+
+// ─── Limite de banda ────────────────────────────────────────────────────────
+
+/// Token bucket compartilhado por todos os downloads. Taxa 0 = sem limite.
+pub struct LimiteBanda {
+    estado: Mutex<Balde>,
+}
+
+struct Balde {
+    bytes_por_seg: u64,
+    /// Pode ficar negativo: quem passou do limite dorme até pagar a dívida
+    tokens: f64,
+    atualizado: Instant,
+}
+
+pub static LIMITE_BANDA: Lazy<LimiteBanda> = Lazy::new(|| LimiteBanda {
+    estado: Mutex::new(Balde { bytes_por_seg: 0, tokens: 0.0, atualizado: Instant::now() }),
+});
+
+impl LimiteBanda {
+    pub fn definir(&self, bytes_por_seg: u64) {
+        let mut b = self.estado.lock().unwrap();
+        b.bytes_por_seg = bytes_por_seg;
+        b.tokens = 0.0;
+        b.atualizado = Instant::now();
+    }
+
+    /// Consome `n` bytes do balde, esperando se a taxa foi excedida.
+    pub async fn aguardar(&self, n: usize) {
+        let espera = {
+            let mut b = self.estado.lock().unwrap();
+            if b.bytes_por_seg == 0 {
+                return;
+            }
+            let taxa = b.bytes_por_seg as f64;
+            let agora = Instant::now();
+            // Rajada de no máximo 1 segundo de banda
+            b.tokens = (b.tokens + agora.duration_since(b.atualizado).as_secs_f64() * taxa).min(taxa);
+            b.atualizado = agora;
+            b.tokens -= n as f64;
+            if b.tokens >= 0.0 {
+                return;
+            }
+            Duration::from_secs_f64(-b.tokens / taxa)
+        };
+        tokio::time::sleep(espera).await;
+    }
+}
+
+/// Persiste a entrada de diretório do rename (no Windows não se aplica).
+fn sincronizar_diretorio(dest: &Path) {
+    #[cfg(unix)]
+    if let Some(dir) = dest.parent() {
+        if let Ok(d) = File::open(dir) {
+            d.sync_all().ok();
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = dest;
+}
+
+/// Apaga `.part` que não podem ser retomados (sem estado salvo ou antigos
+/// demais) e estados cujo parcial sumiu.
+pub fn limpar_parciais(dir: &Path) -> usize {
+    let agora = chrono::Utc::now().timestamp_millis();
+    let mut retomaveis = std::collections::HashSet::new();
+    for d in db::listar_downloads_parciais().unwrap_or_default() {
+        let parcial = caminho_parcial(Path::new(&d.destino));
+        if parcial.exists() && agora - d.updated_at < VALIDADE_PARCIAL_MS {
+            retomaveis.insert(parcial);
+        } else {
+            db::remover_download_parcial(&d.destino).ok();
+        }
+    }
+
+    let entries = match std::fs::read_dir(dir) {
+        Ok(e) => e,
+        Err(_) => return 0,
+    };
+    let mut removidos = 0;
+    for entry in entries.filter_map(|e| e.ok()) {
+        let path = entry.path();
+        let parcial = path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .is_some_and(|n| n.ends_with(SUFIXO_PARCIAL));
+        if parcial && !retomaveis.contains(&path) && std::fs::remove_file(&path).is_ok() {
+            removidos += 1;
+        }
+    }
+    if removidos > 0 {
+        log::info!("[DOWNLOAD] {} downloads incompletos removidos de {}", removidos, dir.display());
+    }
+    if !retomaveis.is_empty() {
+        log::info!("[DOWNLOAD] {} downloads incompletos serão retomados", retomaveis.len());
+    }
+    removidos
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hash_de_parcial_retomado_confere_com_o_arquivo_inteiro() {
+        let dir = std::env::temp_dir().join(format!("bk-download-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let dest = dir.join("01001.mp4");
+
+        let mut parcial = ArquivoParcial::criar(&dest).unwrap();
+        parcial.gravar(b"primeira sessao, ").unwrap();
+        drop(parcial);
+        let mut parcial = ArquivoParcial::retomar(&dest).unwrap();
+        assert_eq!(parcial.gravados(), 17);
+        parcial.gravar(b"segunda sessao").unwrap();
+
+        let esperado = hex::encode(Sha256::digest(b"primeira sessao, segunda sessao"));
+        let integridade = Integridade { tamanho: Some(31), sha256: Some(esperado.clone()) };
+        let baixado = parcial.concluir(&integridade).unwrap();
+        assert_eq!(baixado.sha256, esperado);
+        assert_eq!(sha256_arquivo(&dest).unwrap(), esperado);
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn copia_separada_nao_mexe_no_parcial_retomavel() {
+        let dir = std::env::temp_dir().join(format!("bk-download-separado-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let dest = dir.join("01002.mp4");
+
+        let mut parcial = ArquivoParcial::criar(&dest).unwrap();
+        parcial.gravar(b"metade do download").unwrap();
+        drop(parcial);
+
+        let mut copia = ArquivoParcial::criar_separado(&dest, "lan").unwrap();
+        copia.gravar(b"outro").unwrap();
+        copia.descartar();
+        assert_eq!(std::fs::read(caminho_parcial(&dest)).unwrap(), b"metade do download");
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1);
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -0,0 +1,554 @@
+// Gerenciador de downloads em segundo plano.
+//
+// A fila fica em `fila_downloads` (SQLite) e é processada aqui, no Rust, por
+// até `downloads_paralelos` tarefas ao mesmo tempo, independente da tela
+// aberta no app. O frontend só liga/desliga (retomar/pausar), cancela e
+// acompanha pelos eventos:
+//
+//   "downloads-status"    — mudanças na fila (início/fim de cada música)
+//   "download-progresso"  — bytes, velocidade e ETA por música, no máximo
+//                           a cada INTERVALO_PROGRESSO
+//   "download-erro"       — cada falha, com a próxima tentativa agendada
+//
+// A ordem da fila é: "furar fila" (`priorizar`), músicas pedidas ou buscadas
+// sem estar na máquina, mais tocadas no geral e lançamentos mais recentes.
+//
+// Antes de cada download o espaço é conferido (ver `armazenamento`); sem
+// espaço a fila para, sem gastar tentativas, até o limite ou as músicas
+// fixadas mudarem.
+//
+// Começa pausado: quem libera é o frontend, depois de confirmar a ativação.
+// Pausar ou cancelar aborta a tarefa; o `.part` fica para ser retomado.
+
+use crate::armazenamento;
+use crate::backend::CatalogBackend;
+use crate::catalogo;
+use crate::commands::sync;
+use crate::db;
+use crate::download::LIMITE_BANDA;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+use tauri::async_runtime::JoinHandle;
+use tokio::sync::Notify;
+
+const CHAVE_PARALELOS: &str = "downloads_paralelos";
+const CHAVE_LIMITE_KBPS: &str = "downloads_limite_kbps";
+const PARALELOS_PADRAO: usize = 2;
+const PARALELOS_MAX: usize = 8;
+
+/// Depois disso a música fica em erro até ser reenfileirada.
+const MAX_TENTATIVAS: i64 = 10;
+
+/// Intervalo entre atualizações automáticas da fila a partir do catálogo.
+const INTERVALO_ATUALIZACAO: Duration = Duration::from_secs(30 * 60);
+
+/// Intervalo mínimo entre eventos de progresso (não inundar a WebView).
+const INTERVALO_PROGRESSO: Duration = Duration::from_millis(500);
+
+/// Janela usada para medir a velocidade de cada download.
+const JANELA_VELOCIDADE: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StatusDownloads {
+    pub pausado: bool,
+    pub paralelos: usize,
+    /// 0 = sem limite
+    #[serde(rename = "limiteKbps")]
+    pub limite_kbps: u64,
+    /// Códigos sendo baixados agora
+    pub baixando: Vec<String>,
+    pub pendentes: i64,
+    /// Falharam e aguardam nova tentativa (ou esgotaram as tentativas)
+    pub erros: i64,
+    pub cancelados: i64,
+    /// Concluídos desde que o app abriu
+    pub concluidos: u64,
+    /// Fila parada por falta de espaço (limite da biblioteca ou disco cheio)
+    #[serde(rename = "semEspaco")]
+    pub sem_espaco: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ProgressoMusica {
+    pub codigo: String,
+    /// Bytes no disco, incluindo os de uma sessão anterior (download retomado)
+    pub recebidos: u64,
+    pub total: Option<u64>,
+    /// Bytes por segundo (média móvel)
+    pub velocidade: u64,
+    /// Segundos restantes, se total e velocidade forem conhecidos
+    #[serde(rename = "etaSegundos")]
+    pub eta_segundos: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ProgressoFila {
+    pub musicas: Vec<ProgressoMusica>,
+    pub pendentes: i64,
+    pub erros: i64,
+    pub concluidos: u64,
+    /// Soma das velocidades dos downloads ativos
+    pub velocidade: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ErroDownload {
+    pub codigo: String,
+    pub erro: String,
+    pub tentativa: i64,
+    #[serde(rename = "maxTentativas")]
+    pub max_tentativas: i64,
+    /// Momento (ms) da próxima tentativa; None quando esgotou
+    #[serde(rename = "proximaTentativa")]
+    pub proxima_tentativa: Option<i64>,
+}
+
+/// O que o gerenciador publica; o setup do Tauri emite cada um num evento.
+pub enum EventoDownload {
+    Status(StatusDownloads),
+    Progresso(ProgressoFila),
+    Erro(ErroDownload),
+}
+
+type Listener = Box<dyn Fn(EventoDownload) + Send + Sync>;
+
+struct Medicao {
+    recebidos: u64,
+    total: Option<u64>,
+    inicio_janela: Instant,
+    recebidos_janela: u64,
+    velocidade: f64,
+}
+
+#[derive(Clone)]
+pub struct DownloadManager {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    catalogo: Arc<dyn CatalogBackend>,
+    data_dir: String,
+    pausado: AtomicBool,
+    /// Pede ao despachante que releia o catálogo antes de continuar
+    atualizar: AtomicBool,
+    iniciado: AtomicBool,
+    concluidos: AtomicU64,
+    /// Motivo da fila estar parada por falta de espaço
+    sem_espaco: Mutex<Option<String>>,
+    ativos: Mutex<HashMap<String, JoinHandle<()>>>,
+    progresso: Mutex<HashMap<String, Medicao>>,
+    ultimo_progresso: Mutex<Option<Instant>>,
+    acordar: Notify,
+    listener: OnceLock<Listener>,
+}
+
+impl DownloadManager {
+    pub fn new(catalogo: Arc<dyn CatalogBackend>, data_dir: &str) -> Self {
+        if let Err(e) = db::reiniciar_fila_downloads() {
+            log::error!("[DOWNLOADS] Fila não restaurada: {}", e);
+        }
+        LIMITE_BANDA.definir(limite_kbps() * 1024 / 8);
+        Self {
+            inner: Arc::new(Inner {
+                catalogo,
+                data_dir: data_dir.to_string(),
+                pausado: AtomicBool::new(true),
+                atualizar: AtomicBool::new(true),
+                iniciado: AtomicBool::new(false),
+                concluidos: AtomicU64::new(0),
+                sem_espaco: Mutex::new(None),
+                ativos: Mutex::new(HashMap::new()),
+                progresso: Mutex::new(HashMap::new()),
+                ultimo_progresso: Mutex::new(None),
+                acordar: Notify::new(),
+                listener: OnceLock::new(),
+            }),
+        }
+    }
+
+    /// Registra o callback que recebe status, progresso e erros.
+    pub fn on_evento<F: Fn(EventoDownload) + Send + Sync + 'static>(&self, f: F) {
+        self.inner.listener.set(Box::new(f)).ok();
+    }
+
+    /// Sobe a tarefa que despacha a fila (uma vez só).
+    pub fn iniciar(&self) {
+        if self.inner.iniciado.swap(true, Ordering::SeqCst) {
+            return;
+        }
+        let m = self.clone();
+        tauri::async_runtime::spawn(async move { m.despachar().await });
+    }
+
+    pub fn status(&self) -> StatusDownloads {
+        let mut baixando: Vec<String> = self.inner.ativos.lock().unwrap().keys().cloned().collect();
+        baixando.sort();
+        let contagem = db::contar_fila_downloads().unwrap_or_default();
+        let contar = |estado: &str| contagem.iter().find(|(e, _)| e == estado).map(|(_, n)| *n).unwrap_or(0);
+        StatusDownloads {
+            pausado: self.inner.pausado.load(Ordering::SeqCst),
+            paralelos: paralelos(),
+            limite_kbps: limite_kbps(),
+            baixando,
+            pendentes: contar("pendente"),
+            erros: contar("erro"),
+            cancelados: contar("cancelado"),
+            concluidos: self.inner.concluidos.load(Ordering::SeqCst),
+            sem_espaco: self.inner.sem_espaco.lock().unwrap().clone(),
+        }
+    }
+
+    pub fn retomar(&self) {
+        self.inner.sem_espaco.lock().unwrap().take();
+        if self.inner.pausado.swap(false, Ordering::SeqCst) {
+            log::info!("[DOWNLOADS] Retomados");
+            self.inner.atualizar.store(true, Ordering::SeqCst);
+        }
+        self.inner.acordar.notify_one();
+        self.notificar();
+    }
+
+    /// Para tudo; o que estava baixando volta para pendente (com o parcial).
+    pub fn pausar(&self) {
+        self.inner.pausado.store(true, Ordering::SeqCst);
+        for (codigo, handle) in self.inner.ativos.lock().unwrap().drain() {
+            handle.abort();
+            db::marcar_download(&codigo, "pendente", None, None).ok();
+        }
+        self.inner.progresso.lock().unwrap().clear();
+        log::info!("[DOWNLOADS] Pausados");
+        self.notificar();
+        self.publicar_progresso(true);
+    }
+
+    /// Cancela um código (ou, sem código, a fila inteira).
+    pub fn cancelar(&self, codigo: Option<&str>) -> Result<(), String> {
+        match codigo {
+            Some(codigo) => {
+                if let Some(handle) = self.inner.ativos.lock().unwrap().remove(codigo) {
+                    handle.abort();
+                }
+                self.inner.progresso.lock().unwrap().remove(codigo);
+                db::marcar_download(codigo, "cancelado", None, None)?;
+            }
+            None => {
+                db::cancelar_fila_downloads()?;
+                for (_, handle) in self.inner.ativos.lock().unwrap().drain() {
+                    handle.abort();
+                }
+                self.inner.progresso.lock().unwrap().clear();
+            }
+        }
+        self.inner.acordar.notify_one();
+        self.notificar();
+        self.publicar_progresso(true);
+        Ok(())
+    }
+
+    /// Devolve cancelados/erros à fila (sem código, todos).
+    pub fn reenfileirar(&self, codigo: Option<&str>) -> Result<i64, String> {
+        let n = db::reenfileirar_downloads(codigo)?;
+        self.inner.acordar.notify_one();
+        self.notificar();
+        Ok(n)
+    }
+
+    /// Põe códigos na fila (ou devolve, se estavam cancelados ou em erro).
+    pub fn enfileirar(&self, codigos: &[String]) -> Result<(), String> {
+        db::enfileirar_downloads(codigos)?;
+        for codigo in codigos {
+            db::reenfileirar_downloads(Some(codigo))?;
+        }
+        self.inner.acordar.notify_one();
+        self.notificar();
+        Ok(())
+    }
+
+    /// Passa o código na frente de toda a fila ("furar fila").
+    pub fn priorizar(&self, codigo: &str) -> Result<(), String> {
+        db::priorizar_download(codigo)?;
+        self.espaco_alterado();
+        Ok(())
+    }
+
+    /// Limite, reserva ou músicas fixadas mudaram: tenta de novo a fila que
+    /// parou por falta de espaço.
+    pub fn espaco_alterado(&self) {
+        self.inner.sem_espaco.lock().unwrap().take();
+        self.inner.acordar.notify_one();
+        self.notificar();
+    }
+
+    pub fn configurar(&self, paralelos: Option<usize>, limite_kbps: Option<u64>) -> Result<(), String> {
+        if let Some(p) = paralelos {
+            db::set_config(CHAVE_PARALELOS, &p.clamp(1, PARALELOS_MAX).to_string())?;
+        }
+        if let Some(kbps) = limite_kbps {
+            db::set_config(CHAVE_LIMITE_KBPS, &kbps.to_string())?;
+            LIMITE_BANDA.definir(kbps * 1024 / 8);
+        }
+        self.inner.acordar.notify_one();
+        self.notificar();
+        Ok(())
+    }
+
+    /// Sincroniza o catálogo e põe na fila o que falta baixar. Retorna
+    /// quantos códigos entraram.
+    pub async fn atualizar_fila(&self) -> Result<i64, String> {
+        let musicas_dir = self.musicas_dir();
+        std::fs::create_dir_all(&musicas_dir).map_err(|e| e.to_string())?;
+        if let Err(e) = catalogo::sincronizar(self.inner.catalogo.as_ref(), &self.inner.data_dir, false).await {
+            if db::count_catalogo()? == 0 {
+                return Err(e);
+            }
+            log::warn!("[DOWNLOADS] Usando catálogo salvo: {}", e);
+        }
+        catalogo::atualizar_popularidade(self.inner.catalogo.as_ref()).await?;
+        let codigos: Vec<String> = sync::musicas_pendentes(&musicas_dir)?.into_iter().map(|m| m.codigo).collect();
+        let novos = db::enfileirar_downloads(&codigos)?;
+        if novos > 0 {
+            log::info!("[DOWNLOADS] {} músicas adicionadas à fila", novos);
+        }
+        self.inner.acordar.notify_one();
+        self.notificar();
+        Ok(novos)
+    }
+
+    fn musicas_dir(&self) -> PathBuf {
+        PathBuf::from(&self.inner.data_dir).join("musicas")
+    }
+
+    fn notificar(&self) {
+        self.publicar(|| EventoDownload::Status(self.status()));
+    }
+
+    fn publicar(&self, evento: impl FnOnce() -> EventoDownload) {
+        if let Some(listener) = self.inner.listener.get() {
+            listener(evento());
+        }
+    }
+
+    /// Chamado a cada bloco gravado; atualiza a medição e, respeitando
+    /// INTERVALO_PROGRESSO, publica o progresso da fila.
+    fn registrar_progresso(&self, codigo: &str, recebidos: u64, total: Option<u64>) {
+        {
+            let mut mapa = self.inner.progresso.lock().unwrap();
+            let m = mapa.entry(codigo.to_string()).or_insert_with(|| Medicao {
+                recebidos,
+                total,
+                inicio_janela: Instant::now(),
+                recebidos_janela: recebidos,
+                velocidade: 0.0,
+            });
+            m.recebidos = recebidos;
+            m.total = total;
+            let dt = m.inicio_janela.elapsed();
+            if dt >= JANELA_VELOCIDADE {
+                let instantanea = recebidos.saturating_sub(m.recebidos_janela) as f64 / dt.as_secs_f64();
+                m.velocidade = if m.velocidade == 0.0 { instantanea } else { 0.7 * m.velocidade + 0.3 * instantanea };
+                m.inicio_janela = Instant::now();
+                m.recebidos_janela = recebidos;
+            }
+        }
+        self.publicar_progresso(false);
+    }
+
+    fn publicar_progresso(&self, forcar: bool) {
+        {
+            let mut ultimo = self.inner.ultimo_progresso.lock().unwrap();
+            if !forcar && ultimo.is_some_and(|t| t.elapsed() < INTERVALO_PROGRESSO) {
+                return;
+            }
+            *ultimo = Some(Instant::now());
+        }
+        self.publicar(|| {
+            let mut musicas: Vec<ProgressoMusica> = self
+                .inner
+                .progresso
+                .lock()
+                .unwrap()
+                .iter()
+                .map(|(codigo, m)| {
+                    let velocidade = m.velocidade.round() as u64;
+                    ProgressoMusica {
+                        codigo: codigo.clone(),
+                        recebidos: m.recebidos,
+                        total: m.total,
+                        velocidade,
+                        eta_segundos: m
+                            .total
+                            .filter(|_| velocidade > 0)
+                            .map(|t| t.saturating_sub(m.recebidos) / velocidade),
+                    }
+                })
+                .collect();
+            musicas.sort_by(|a, b| a.codigo.cmp(&b.codigo));
+            let status = self.status();
+            EventoDownload::Progresso(ProgressoFila {
+                velocidade: musicas.iter().map(|m| m.velocidade).sum(),
+                musicas,
+                pendentes: status.pendentes,
+                erros: status.erros,
+                concluidos: status.concluidos,
+            })
+        });
+    }
+
+    async fn despachar(self) {
+        let mut ultima_atualizacao: Option<Instant> = None;
+        loop {
+            if !self.inner.pausado.load(Ordering::SeqCst) {
+                let vencida = ultima_atualizacao.is_none_or(|t| t.elapsed() >= INTERVALO_ATUALIZACAO);
+                if self.inner.atualizar.swap(false, Ordering::SeqCst) || vencida {
+                    ultima_atualizacao = Some(Instant::now());
+                    self.inner.sem_espaco.lock().unwrap().take();
+                    if let Err(e) = self.atualizar_fila().await {
+                        log::warn!("[DOWNLOADS] Fila não atualizada: {}", e);
+                    }
+                }
+                self.iniciar_proximos();
+            }
+
+            // Dorme até alguém mexer na fila ou até a próxima nova tentativa
+            let agora = chrono::Utc::now().timestamp_millis();
+            let espera = db::proxima_tentativa_download(MAX_TENTATIVAS)
+                .ok()
+                .flatten()
+                .map(|t| Duration::from_millis((t - agora).max(0) as u64))
+                .unwrap_or(INTERVALO_ATUALIZACAO)
+                .clamp(Duration::from_secs(1), INTERVALO_ATUALIZACAO);
+            tokio::select! {
+                _ = self.inner.acordar.notified() => {}
+                _ = tokio::time::sleep(espera) => {}
+            }
+        }
+    }
+
+    fn iniciar_proximos(&self) {
+        let limite = paralelos();
+        let mut ativos = self.inner.ativos.lock().unwrap();
+        while ativos.len() < limite && self.inner.sem_espaco.lock().unwrap().is_none() {
+            let ocupados: Vec<String> = ativos.keys().cloned().collect();
+            let codigo = match db::proximo_download(&ocupados, MAX_TENTATIVAS) {
+                Ok(Some(c)) => c,
+                Ok(None) => break,
+                Err(e) => {
+                    log::error!("[DOWNLOADS] {}", e);
+                    break;
+                }
+            };
+            if let Err(e) = db::marcar_download(&codigo, "baixando", None, None) {
+                log::error!("[DOWNLOADS] {}: {}", codigo, e);
+                break;
+            }
+            let m = self.clone();
+            let c = codigo.clone();
+            // A tarefa só sai de `ativos` depois deste lock ser solto
+            let handle = tauri::async_runtime::spawn(async move { m.baixar(c).await });
+            ativos.insert(codigo, handle);
+        }
+        drop(ativos);
+        self.notificar();
+    }
+
+    async fn baixar(self, codigo: String) {
+        let m = self.clone();
+        let c = codigo.clone();
+        let progresso = move |recebidos: u64, total: Option<u64>| m.registrar_progresso(&c, recebidos, total);
+        let resultado = match db::get_catalogo_by_codigo(&codigo) {
+            Ok(Some(musica)) => {
+                let _reserva = match armazenamento::garantir_espaco(&self.musicas_dir(), &codigo, musica.tamanho) {
+                    Ok(r) => r,
+                    Err(e) => {
+                        self.parar_sem_espaco(&codigo, e);
+                        return;
+                    }
+                };
+                sync::baixar_musica(self.inner.catalogo.as_ref(), &self.musicas_dir(), &musica, &progresso).await
+            }
+            Ok(None) => {
+                // Saiu do catálogo enquanto esperava na fila
+                db::remover_da_fila(&codigo).ok();
+                self.finalizar(&codigo);
+                return;
+            }
+            Err(e) => Err(e),
+        };
+
+        match resultado {
+            Ok(_) => {
+                db::remover_da_fila(&codigo).ok();
+                self.inner.concluidos.fetch_add(1, Ordering::SeqCst);
+            }
+            Err(e) => {
+                let tentativas = db::tentativas_download(&codigo).unwrap_or(0) + 1;
+                let proxima = chrono::Utc::now().timestamp_millis() + espera_nova_tentativa(tentativas).as_millis() as i64;
+                log::warn!("[DOWNLOADS] {} falhou (tentativa {}/{}): {}", codigo, tentativas, MAX_TENTATIVAS, e);
+                db::marcar_download(&codigo, "erro", Some(&e), Some(proxima)).ok();
+                self.publicar(|| {
+                    EventoDownload::Erro(ErroDownload {
+                        codigo: codigo.clone(),
+                        erro: e.clone(),
+                        tentativa: tentativas,
+                        max_tentativas: MAX_TENTATIVAS,
+                        proxima_tentativa: (tentativas < MAX_TENTATIVAS).then_some(proxima),
+                    })
+                });
+            }
+        }
+        self.finalizar(&codigo);
+    }
+
+    /// A música volta para pendente sem gastar tentativa e nada mais começa
+    /// até o espaço mudar (ou na próxima atualização da fila).
+    fn parar_sem_espaco(&self, codigo: &str, erro: String) {
+        log::warn!("[DOWNLOADS] Fila parada: {}", erro);
+        db::marcar_download(codigo, "pendente", None, None).ok();
+        *self.inner.sem_espaco.lock().unwrap() = Some(erro.clone());
+        self.publicar(|| {
+            EventoDownload::Erro(ErroDownload {
+                codigo: codigo.to_string(),
+                erro: erro.clone(),
+                tentativa: db::tentativas_download(codigo).unwrap_or(0),
+                max_tentativas: MAX_TENTATIVAS,
+                proxima_tentativa: None,
+            })
+        });
+        self.finalizar(codigo);
+    }
+
+    fn finalizar(&self, codigo: &str) {
+        self.inner.ativos.lock().unwrap().remove(codigo);
+        self.inner.progresso.lock().unwrap().remove(codigo);
+        self.inner.acordar.notify_one();
+        self.notificar();
+        self.publicar_progresso(true);
+    }
+}
+
+/// 30 s, 1 min, 2 min... até 30 min.
+fn espera_nova_tentativa(tentativas: i64) -> Duration {
+    let exp = (tentativas - 1).clamp(0, 6) as u32;
+    Duration::from_secs(30 * 2u64.pow(exp)).min(Duration::from_secs(30 * 60))
+}
+
+fn paralelos() -> usize {
+    db::get_config(CHAVE_PARALELOS)
+        .ok()
+        .flatten()
+        .and_then(|v| v.parse::<usize>().ok())
+        .map(|p| p.clamp(1, PARALELOS_MAX))
+        .unwrap_or(PARALELOS_PADRAO)
+}
+
+fn limite_kbps() -> u64 {
+    db::get_config(CHAVE_LIMITE_KBPS)
+        .ok()
+        .flatten()
+        .and_then(|v| v.parse::<u64>().ok())
+        .unwrap_or(0)
+}
@@ -0,0 +1,109 @@
+// Programas externos que o app usa (mpv, ffprobe, ffmpeg).
+//
+// Procurados na pasta de resources do instalador, ao lado do executável
+// (modo portátil) e por fim no PATH. O player exige o mpv; o ffprobe/ffmpeg
+// são opcionais e, sem eles, o que der é feito com o próprio mpv.
+
+use once_cell::sync::Lazy;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Pasta de resources do app, definida no setup.
+static RECURSOS: Lazy<Mutex<Option<PathBuf>>> = Lazy::new(|| Mutex::new(None));
+
+pub fn definir_recursos(dir: Option<PathBuf>) {
+    *RECURSOS.lock().unwrap() = dir;
+}
+
+fn nome_executavel(nome: &str) -> String {
+    if cfg!(target_os = "windows") { format!("{}.exe", nome) } else { nome.to_string() }
+}
+
+/// Caminho do programa `nome` (sem extensão), se existir.
+pub fn localizar(nome: &str) -> Option<PathBuf> {
+    let exe = nome_executavel(nome);
+
+    if let Some(dir) = RECURSOS.lock().unwrap().clone() {
+        let c = dir.join(&exe);
+        if c.exists() {
+            log::debug!("[ferramentas] {} em resources: {}", nome, c.display());
+            return Some(c);
+        }
+    }
+    if let Ok(atual) = std::env::current_exe() {
+        if let Some(dir) = atual.parent() {
+            let c = dir.join(&exe);
+            if c.exists() {
+                log::debug!("[ferramentas] {} ao lado do exe: {}", nome, c.display());
+                return Some(c);
+            }
+        }
+    }
+    let sep = if cfg!(target_os = "windows") { ";" } else { ":" };
+    let found = std::env::var("PATH")
+        .ok()?
+        .split(sep)
+        .map(|d| PathBuf::from(d).join(&exe))
+        .find(|p| p.exists());
+    if let Some(ref p) = found {
+        log::debug!("[ferramentas] {} no PATH: {}", nome, p.display());
+    }
+    found
+}
+
+/// `Command` do programa, sem abrir janela de console no Windows.
+pub fn comando(programa: &Path) -> Command {
+    #[allow(unused_mut)]
+    let mut cmd = Command::new(programa);
+    #[cfg(target_os = "windows")]
+    {
+        use std::os::windows::process::CommandExt;
+        const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+        cmd.creation_flags(CREATE_NO_WINDOW);
+    }
+    cmd
+}
+
+/// Executa e coleta a saída; mata o processo se passar de `limite`.
+pub fn executar(mut cmd: Command, limite: Duration) -> Result<Output, String> {
+    let mut child = cmd
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| e.to_string())?;
+
+    // Lidas em paralelo para o processo não travar com o pipe cheio
+    let ler = |fonte: Option<Box<dyn Read + Send>>| {
+        std::thread::spawn(move || {
+            let mut buf = Vec::new();
+            if let Some(mut f) = fonte {
+                f.read_to_end(&mut buf).ok();
+            }
+            buf
+        })
+    };
+    let stdout = ler(child.stdout.take().map(|s| Box::new(s) as Box<dyn Read + Send>));
+    let stderr = ler(child.stderr.take().map(|s| Box::new(s) as Box<dyn Read + Send>));
+
+    let inicio = Instant::now();
+    let status = loop {
+        if let Some(status) = child.try_wait().map_err(|e| e.to_string())? {
+            break status;
+        }
+        if inicio.elapsed() > limite {
+            child.kill().ok();
+            child.wait().ok();
+            return Err(format!("sem resposta em {}s", limite.as_secs()));
+        }
+        std::thread::sleep(Duration::from_millis(50));
+    };
+    Ok(Output {
+        status,
+        stdout: stdout.join().unwrap_or_default(),
+        stderr: stderr.join().unwrap_or_default(),
+    })
+}
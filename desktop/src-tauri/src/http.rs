@@ -0,0 +1,258 @@
+// Camada HTTP compartilhada: timeouts, retry com backoff e estado de conectividade.
+//
+// Wi-Fi de casa de show cai e volta o tempo todo. Sem timeout, uma requisição
+// pendurada trava `verificar_ativacao` na inicialização; sem estado de rede,
+// cada chamada espera seu próprio timeout. Aqui:
+//
+//   - connect/read timeouts configuráveis via .env
+//   - retry com backoff exponencial + jitter só para requisições idempotentes
+//   - `Connectivity`: depois de uma falha de rede, novas chamadas falham na
+//     hora (BackendError::Offline) até a próxima sonda, e mudanças de estado
+//     são avisadas ao listener (evento "conectividade-alterada" no lib.rs)
+
+use crate::backend::BackendError;
+use rand::Rng;
+use reqwest::{Client, RequestBuilder, Response};
+use serde::Serialize;
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+// ─── Configuração ───────────────────────────────────────────────────────────
+
+#[derive(Debug, Clone)]
+pub struct HttpConfig {
+    pub connect_timeout: Duration,
+    /// Tempo máximo sem receber bytes (não limita o total de um download grande)
+    pub read_timeout: Duration,
+    /// Tentativas totais para requisições idempotentes (1 = sem retry)
+    pub max_tentativas: u32,
+    pub backoff_base: Duration,
+    pub backoff_max: Duration,
+    /// Depois de cair para offline, intervalo até deixar uma requisição sondar a rede
+    pub sonda_offline: Duration,
+}
+
+impl Default for HttpConfig {
+    fn default() -> Self {
+        Self {
+            connect_timeout: Duration::from_secs(5),
+            read_timeout: Duration::from_secs(20),
+            max_tentativas: 3,
+            backoff_base: Duration::from_millis(500),
+            backoff_max: Duration::from_secs(8),
+            sonda_offline: Duration::from_secs(30),
+        }
+    }
+}
+
+impl HttpConfig {
+    /// Lê `HTTP_CONNECT_TIMEOUT_MS`, `HTTP_READ_TIMEOUT_MS`, `HTTP_MAX_TENTATIVAS`
+    /// e `HTTP_SONDA_OFFLINE_MS`; ausentes ficam com o padrão.
+    pub fn from_env() -> Self {
+        fn ms(nome: &str, padrao: Duration) -> Duration {
+            std::env::var(nome)
+                .ok()
+                .and_then(|v| v.trim().parse::<u64>().ok())
+                .map(Duration::from_millis)
+                .unwrap_or(padrao)
+        }
+        let d = Self::default();
+        Self {
+            connect_timeout: ms("HTTP_CONNECT_TIMEOUT_MS", d.connect_timeout),
+            read_timeout: ms("HTTP_READ_TIMEOUT_MS", d.read_timeout),
+            max_tentativas: std::env::var("HTTP_MAX_TENTATIVAS")
+                .ok()
+                .and_then(|v| v.trim().parse::<u32>().ok())
+                .unwrap_or(d.max_tentativas)
+                .max(1),
+            sonda_offline: ms("HTTP_SONDA_OFFLINE_MS", d.sonda_offline),
+            ..d
+        }
+    }
+
+    /// Backoff exponencial com "full jitter": aleatório em [0, min(max, base * 2^n)].
+    fn backoff(&self, tentativa: u32) -> Duration {
+        let teto = self
+            .backoff_base
+            .saturating_mul(1u32 << tentativa.min(16))
+            .min(self.backoff_max);
+        let ms = rand::thread_rng().gen_range(0..=teto.as_millis() as u64);
+        Duration::from_millis(ms)
+    }
+}
+
+// ─── Conectividade ──────────────────────────────────────────────────────────
+
+const DESCONHECIDO: u8 = 0;
+const ONLINE: u8 = 1;
+const OFFLINE: u8 = 2;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ConectividadeStatus {
+    /// "desconhecido" | "online" | "offline"
+    pub estado: String,
+    pub online: bool,
+    /// Timestamp (ms) da última mudança de estado
+    #[serde(rename = "alteradoEm")]
+    pub alterado_em: Option<i64>,
+}
+
+type Listener = Box<dyn Fn(ConectividadeStatus) + Send + Sync>;
+
+pub struct Connectivity {
+    estado: AtomicU8,
+    alterado_em: Mutex<Option<i64>>,
+    /// Momento da última tentativa real de rede enquanto offline
+    ultima_sonda: Mutex<Option<Instant>>,
+    listener: OnceLock<Listener>,
+}
+
+impl Connectivity {
+    pub fn new() -> Self {
+        Self {
+            estado: AtomicU8::new(DESCONHECIDO),
+            alterado_em: Mutex::new(None),
+            ultima_sonda: Mutex::new(None),
+            listener: OnceLock::new(),
+        }
+    }
+
+    /// Registra o callback chamado a cada mudança online ↔ offline.
+    pub fn on_change<F: Fn(ConectividadeStatus) + Send + Sync + 'static>(&self, f: F) {
+        self.listener.set(Box::new(f)).ok();
+    }
+
+    pub fn status(&self) -> ConectividadeStatus {
+        let estado = self.estado.load(Ordering::SeqCst);
+        ConectividadeStatus {
+            estado: match estado {
+                ONLINE => "online",
+                OFFLINE => "offline",
+                _ => "desconhecido",
+            }
+            .to_string(),
+            online: estado != OFFLINE,
+            alterado_em: *self.alterado_em.lock().unwrap(),
+        }
+    }
+
+    pub fn is_offline(&self) -> bool {
+        self.estado.load(Ordering::SeqCst) == OFFLINE
+    }
+
+    /// Offline e ainda dentro da janela de sonda → falha imediata.
+    /// Fora da janela, a chamada atual vira a sonda e as demais continuam falhando.
+    fn permitir(&self, sonda: Duration) -> bool {
+        if !self.is_offline() {
+            return true;
+        }
+        let mut ultima = self.ultima_sonda.lock().unwrap();
+        match *ultima {
+            Some(t) if t.elapsed() < sonda => false,
+            _ => {
+                *ultima = Some(Instant::now());
+                true
+            }
+        }
+    }
+
+    pub fn marcar_online(&self) {
+        self.alterar(ONLINE);
+    }
+
+    pub fn marcar_offline(&self) {
+        *self.ultima_sonda.lock().unwrap() = Some(Instant::now());
+        self.alterar(OFFLINE);
+    }
+
+    fn alterar(&self, novo: u8) {
+        let anterior = self.estado.swap(novo, Ordering::SeqCst);
+        if anterior == novo {
+            return;
+        }
+        *self.alterado_em.lock().unwrap() = Some(chrono::Utc::now().timestamp_millis());
+        let status = self.status();
+        log::info!("[HTTP] Conectividade: {}", status.estado);
+        if let Some(listener) = self.listener.get() {
+            listener(status);
+        }
+    }
+}
+
+// ─── Cliente ────────────────────────────────────────────────────────────────
+
+pub struct HttpClient {
+    client: Client,
+    config: HttpConfig,
+    conectividade: Arc<Connectivity>,
+}
+
+impl HttpClient {
+    pub fn new(config: HttpConfig, conectividade: Arc<Connectivity>) -> Self {
+        let client = Client::builder()
+            .connect_timeout(config.connect_timeout)
+            .read_timeout(config.read_timeout)
+            .build()
+            .unwrap_or_else(|e| {
+                log::error!("[HTTP] Falha ao configurar cliente ({}), usando padrão", e);
+                Client::new()
+            });
+        Self { client, config, conectividade }
+    }
+
+    pub fn client(&self) -> &Client {
+        &self.client
+    }
+
+    /// Envia a requisição. Idempotentes (GET, PATCH com o mesmo corpo...) são
+    /// repetidas em falha de rede, 429 e 5xx; as demais são enviadas uma vez.
+    /// Respostas fora de 2xx são devolvidas como estão (quem chama trata).
+    pub async fn enviar(&self, req: RequestBuilder, idempotente: bool) -> Result<Response, BackendError> {
+        if !self.conectividade.permitir(self.config.sonda_offline) {
+            return Err(BackendError::Offline);
+        }
+        let tentativas = if idempotente { self.config.max_tentativas } else { 1 };
+        let mut tentativa = 0;
+        loop {
+            let atual = match req.try_clone() {
+                Some(r) if tentativa + 1 < tentativas => r,
+                _ => {
+                    // Última tentativa (ou corpo não clonável): consome o builder original
+                    return self.enviar_uma(req).await;
+                }
+            };
+            match self.enviar_uma(atual).await {
+                Ok(resp) if !deve_repetir(resp.status()) => return Ok(resp),
+                Ok(resp) => log::warn!("[HTTP] {} — tentativa {}/{}", resp.status(), tentativa + 1, tentativas),
+                Err(e) => log::warn!("[HTTP] {} — tentativa {}/{}", e, tentativa + 1, tentativas),
+            }
+            let espera = self.config.backoff(tentativa);
+            tentativa += 1;
+            tokio::time::sleep(espera).await;
+        }
+    }
+
+    async fn enviar_uma(&self, req: RequestBuilder) -> Result<Response, BackendError> {
+        match req.send().await {
+            Ok(resp) => {
+                self.conectividade.marcar_online();
+                Ok(resp)
+            }
+            Err(e) => Err(self.erro_rede(e)),
+        }
+    }
+
+    /// Converte erro do reqwest; conexão recusada/timeout marcam a rede como offline.
+    pub fn erro_rede(&self, e: reqwest::Error) -> BackendError {
+        log::error!("[HTTP] Request error: {}", e);
+        if e.is_connect() || e.is_timeout() {
+            self.conectividade.marcar_offline();
+        }
+        BackendError::Rede(e.to_string())
+    }
+}
+
+fn deve_repetir(status: reqwest::StatusCode) -> bool {
+    status.is_server_error() || status == reqwest::StatusCode::TOO_MANY_REQUESTS
+}
@@ -0,0 +1,595 @@
+// Letras sincronizadas (LRC e ASS) das faixas só de áudio.
+//
+// A letra é um arquivo companheiro do áudio, com o mesmo nome (`01001.lrc`
+// ou `01001.ass` ao lado de `01001.mp3`). Os dois formatos viram a mesma
+// `Letra`: linhas com início/fim e, quando o arquivo traz, o tempo de cada
+// palavra (LRC "enhanced" com `<mm:ss.xx>`, ASS com `\k`).
+//
+// No mpv a letra entra com `--sub-file`: o ASS vai direto; o LRC é
+// convertido para ASS (com `\kf` para o preenchimento palavra a palavra) em
+// `letras/` dentro da pasta de dados. Sem o mpv o frontend recebe a `Letra`
+// e desenha por cima do fundo.
+//
+// Arquivos de karaokê vêm de todo lugar: linhas que não dá para entender são
+// puladas, e só é erro quando não sobra nenhuma linha com tempo.
+
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+/// Extensões de letra, na ordem de preferência.
+pub const EXTENSOES: &[&str] = &["ass", "lrc"];
+/// Quanto a última linha fica na tela quando o arquivo não diz.
+const DURACAO_ULTIMA_MS: i64 = 5000;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Palavra {
+    #[serde(rename = "inicioMs")]
+    pub inicio_ms: i64,
+    #[serde(rename = "fimMs")]
+    pub fim_ms: i64,
+    pub texto: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Linha {
+    #[serde(rename = "inicioMs")]
+    pub inicio_ms: i64,
+    #[serde(rename = "fimMs")]
+    pub fim_ms: i64,
+    pub texto: String,
+    /// Vazio quando o arquivo só tem tempo por linha
+    pub palavras: Vec<Palavra>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct Letra {
+    pub titulo: Option<String>,
+    pub artista: Option<String>,
+    pub linhas: Vec<Linha>,
+}
+
+/// Arquivo de letra que acompanha o áudio `arquivo`.
+pub fn arquivo_letra(arquivo: &Path) -> Option<PathBuf> {
+    EXTENSOES.iter().find_map(|ext| crate::midia::companheiro(arquivo, ext))
+}
+
+/// Lê e interpreta um `.lrc` ou `.ass`.
+pub fn ler(path: &Path) -> Result<Letra, String> {
+    let bytes = std::fs::read(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    // Muitos .lrc antigos vêm em Latin-1; o que não for UTF-8 vira Latin-1
+    let texto = match String::from_utf8(bytes) {
+        Ok(s) => s,
+        Err(e) => e.into_bytes().iter().map(|b| *b as char).collect(),
+    };
+    let extensao = path.extension().and_then(|e| e.to_str()).unwrap_or_default().to_ascii_lowercase();
+    match extensao.as_str() {
+        "lrc" => parse_lrc(&texto),
+        "ass" | "ssa" => parse_ass(&texto),
+        _ => Err(format!("Formato de letra não suportado: {}", path.display())),
+    }
+}
+
+/// Legenda para o mpv: o próprio `.ass`, ou o `.lrc` convertido em
+/// `<data_dir>/letras/<codigo>.ass`.
+pub fn legenda_mpv(data_dir: &str, codigo: &str, letra: &Path) -> Result<PathBuf, String> {
+    let extensao = letra.extension().and_then(|e| e.to_str()).unwrap_or_default().to_ascii_lowercase();
+    if extensao == "ass" || extensao == "ssa" {
+        return Ok(letra.to_path_buf());
+    }
+    let convertida = para_ass(&ler(letra)?);
+    let dir = Path::new(data_dir).join("letras");
+    std::fs::create_dir_all(&dir).map_err(|e| format!("{}: {}", dir.display(), e))?;
+    let destino = dir.join(format!("{}.ass", codigo));
+    std::fs::write(&destino, convertida).map_err(|e| format!("{}: {}", destino.display(), e))?;
+    Ok(destino)
+}
+
+// ─── LRC ────────────────────────────────────────────────────────────────────
+
+/// `mm:ss`, `mm:ss.x`, `mm:ss.xx`, `mm:ss.xxx` ou `mm:ss:xx` em ms.
+fn tempo_lrc(s: &str) -> Option<i64> {
+    let s = s.trim();
+    let (min, resto) = s.split_once(':')?;
+    let (seg, fracao) = match resto.split_once(['.', ':']) {
+        Some((seg, fracao)) => (seg, fracao),
+        None => (resto, ""),
+    };
+    let min: i64 = min.parse().ok().filter(|m| *m >= 0)?;
+    let seg: i64 = seg.parse().ok().filter(|s| (0..60).contains(s))?;
+    if fracao.len() > 3 || !fracao.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let fracao_ms = match fracao.len() {
+        0 => 0,
+        n => fracao.parse::<i64>().ok()? * 10i64.pow(3 - n as u32),
+    };
+    Some(min * 60_000 + seg * 1000 + fracao_ms)
+}
+
+pub fn parse_lrc(texto: &str) -> Result<Letra, String> {
+    let mut letra = Letra::default();
+    let mut offset = 0i64;
+    // (início, texto com as marcas <mm:ss.xx> de palavra)
+    let mut brutas: Vec<(i64, String)> = Vec::new();
+
+    for linha in texto.trim_start_matches('\u{feff}').lines() {
+        let mut resto = linha.trim();
+        let mut tempos = Vec::new();
+        while let Some(tag) = resto.strip_prefix('[') {
+            let Some((conteudo, depois)) = tag.split_once(']') else { break };
+            if let Some(t) = tempo_lrc(conteudo) {
+                tempos.push(t);
+            } else if let Some((chave, valor)) = conteudo.split_once(':') {
+                let valor = valor.trim().to_string();
+                match chave.trim().to_ascii_lowercase().as_str() {
+                    "ti" if !valor.is_empty() => letra.titulo = Some(valor),
+                    "ar" if !valor.is_empty() => letra.artista = Some(valor),
+                    "offset" => offset = valor.trim_start_matches('+').parse().unwrap_or(0),
+                    _ => {}
+                }
+            }
+            resto = depois;
+        }
+        for t in tempos {
+            brutas.push((t, resto.to_string()));
+        }
+    }
+
+    // offset positivo adianta a letra
+    let mut brutas: Vec<(i64, String)> = brutas.into_iter().map(|(t, s)| ((t - offset).max(0), s)).collect();
+    brutas.sort_by_key(|(t, _)| *t);
+
+    for i in 0..brutas.len() {
+        let (inicio, bruto) = &brutas[i];
+        let proxima = brutas.get(i + 1).map(|(t, _)| *t);
+        let palavras = palavras_lrc(bruto, *inicio, offset);
+        let fim = proxima
+            .or_else(|| palavras.last().filter(|p| p.fim_ms > p.inicio_ms).map(|p| p.fim_ms))
+            .unwrap_or(palavras.last().map_or(*inicio, |p| p.inicio_ms) + DURACAO_ULTIMA_MS)
+            .max(*inicio);
+        let mut palavras = palavras;
+        fechar_palavras(&mut palavras, fim);
+        let texto = if palavras.is_empty() {
+            bruto.trim().to_string()
+        } else {
+            palavras.iter().map(|p| p.texto.as_str()).collect::<String>().trim().to_string()
+        };
+        // Linha só com tempo marca um intervalo (tela limpa): não vira linha
+        if texto.is_empty() {
+            continue;
+        }
+        letra.linhas.push(Linha { inicio_ms: *inicio, fim_ms: fim, texto, palavras });
+    }
+
+    if letra.linhas.is_empty() {
+        return Err("LRC sem nenhuma linha com tempo".to_string());
+    }
+    Ok(letra)
+}
+
+/// Palavras de uma linha LRC "enhanced". O fim de cada uma é o início da
+/// próxima; a última termina numa marca de tempo final, se houver
+/// (`...que<00:12.00>`), ou fica em aberto (fim = início) até `fechar_palavras`.
+fn palavras_lrc(bruto: &str, inicio_linha: i64, offset: i64) -> Vec<Palavra> {
+    let mut palavras: Vec<Palavra> = Vec::new();
+    let mut marcas = 0;
+    let mut resto = bruto;
+    let mut atual = inicio_linha;
+    let mut texto = String::new();
+    while !resto.is_empty() {
+        if let Some(tag) = resto.strip_prefix('<') {
+            if let Some((conteudo, depois)) = tag.split_once('>') {
+                if let Some(t) = tempo_lrc(conteudo) {
+                    marcas += 1;
+                    let t = (t - offset).max(inicio_linha);
+                    if !texto.is_empty() {
+                        palavras.push(Palavra { inicio_ms: atual, fim_ms: atual, texto: std::mem::take(&mut texto) });
+                    }
+                    atual = t;
+                    resto = depois;
+                    continue;
+                }
+            }
+        }
+        let mut chars = resto.chars();
+        texto.push(chars.next().expect("resto não vazio"));
+        resto = chars.as_str();
+    }
+    if marcas == 0 {
+        return Vec::new();
+    }
+    if texto.is_empty() {
+        // Marca final: fim explícito da última palavra
+        if let Some(ultima) = palavras.last_mut() {
+            ultima.fim_ms = atual;
+        }
+    } else {
+        palavras.push(Palavra { inicio_ms: atual, fim_ms: atual, texto });
+    }
+    palavras
+}
+
+/// Fim de cada palavra = início da seguinte; a última, se ainda em aberto,
+/// vai até `fim_linha`.
+fn fechar_palavras(palavras: &mut [Palavra], fim_linha: i64) {
+    let n = palavras.len();
+    for i in 0..n {
+        let fim = match palavras.get(i + 1) {
+            Some(p) => p.inicio_ms,
+            None if palavras[i].fim_ms > palavras[i].inicio_ms => palavras[i].fim_ms.min(fim_linha),
+            None => fim_linha,
+        };
+        palavras[i].fim_ms = fim.max(palavras[i].inicio_ms);
+    }
+}
+
+// ─── ASS ────────────────────────────────────────────────────────────────────
+
+/// `h:mm:ss.cc` em ms.
+fn tempo_ass(s: &str) -> Option<i64> {
+    let mut partes = s.trim().split(':');
+    let h: i64 = partes.next()?.parse().ok()?;
+    let m: i64 = partes.next()?.parse().ok()?;
+    let seg = partes.next()?;
+    if partes.next().is_some() || h < 0 || !(0..60).contains(&m) {
+        return None;
+    }
+    let (s, cs) = seg.split_once('.').unwrap_or((seg, "0"));
+    let s: i64 = s.parse().ok().filter(|s| (0..60).contains(s))?;
+    if cs.is_empty() || cs.len() > 3 || !cs.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let fracao_ms = cs.parse::<i64>().ok()? * 10i64.pow(3 - cs.len() as u32);
+    Some(h * 3_600_000 + m * 60_000 + s * 1000 + fracao_ms)
+}
+
+const FORMATO_PADRAO: &[&str] = &["layer", "start", "end", "style", "name", "marginl", "marginr", "marginv", "effect", "text"];
+
+pub fn parse_ass(texto: &str) -> Result<Letra, String> {
+    let mut letra = Letra::default();
+    let mut secao = String::new();
+    let mut formato: Vec<String> = FORMATO_PADRAO.iter().map(|s| s.to_string()).collect();
+    let mut viu_eventos = false;
+
+    for linha in texto.trim_start_matches('\u{feff}').lines() {
+        let linha = linha.trim();
+        if linha.starts_with('[') && linha.ends_with(']') {
+            secao = linha[1..linha.len() - 1].trim().to_ascii_lowercase();
+            viu_eventos |= secao == "events";
+            continue;
+        }
+        let Some((tipo, valor)) = linha.split_once(':') else { continue };
+        let tipo = tipo.trim().to_ascii_lowercase();
+        match secao.as_str() {
+            "script info" => match tipo.as_str() {
+                "title" if !valor.trim().is_empty() => letra.titulo = Some(valor.trim().to_string()),
+                "original script" | "artist" if !valor.trim().is_empty() && letra.artista.is_none() => {
+                    letra.artista = Some(valor.trim().to_string())
+                }
+                _ => {}
+            },
+            "events" if tipo == "format" => {
+                formato = valor.split(',').map(|c| c.trim().to_ascii_lowercase()).collect();
+            }
+            "events" if tipo == "dialogue" => {
+                if let Some(l) = dialogo_ass(valor, &formato) {
+                    letra.linhas.push(l);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    if !viu_eventos {
+        return Err("ASS sem seção [Events]".to_string());
+    }
+    if letra.linhas.is_empty() {
+        return Err("ASS sem nenhuma fala com tempo".to_string());
+    }
+    letra.linhas.sort_by_key(|l| l.inicio_ms);
+    Ok(letra)
+}
+
+fn dialogo_ass(valor: &str, formato: &[String]) -> Option<Linha> {
+    // O texto é o último campo e pode ter vírgulas
+    let campos: Vec<&str> = valor.trim_start().splitn(formato.len(), ',').collect();
+    if campos.len() != formato.len() {
+        return None;
+    }
+    let campo = |nome: &str| formato.iter().position(|f| f == nome).map(|i| campos[i]);
+    let inicio = tempo_ass(campo("start")?)?;
+    let fim = tempo_ass(campo("end")?)?;
+    if fim < inicio {
+        return None;
+    }
+    let (texto, palavras) = texto_ass(campo("text")?, inicio, fim);
+    if texto.trim().is_empty() {
+        return None;
+    }
+    Some(Linha { inicio_ms: inicio, fim_ms: fim, texto: texto.trim().to_string(), palavras })
+}
+
+/// Tira os blocos `{...}` do texto, lendo os `\k`/`\kf`/`\ko`/`\K`
+/// (centésimos) para montar as palavras.
+fn texto_ass(bruto: &str, inicio: i64, fim: i64) -> (String, Vec<Palavra>) {
+    let mut texto = String::new();
+    let mut palavras: Vec<Palavra> = Vec::new();
+    let mut karaoke = false;
+    let mut cursor = inicio;
+    let mut resto = bruto;
+
+    while !resto.is_empty() {
+        if let Some(bloco) = resto.strip_prefix('{') {
+            // Bloco sem fechamento: o resto não é texto
+            let Some((tags, depois)) = bloco.split_once('}') else { break };
+            for tag in tags.split('\\').skip(1) {
+                let lower = tag.to_ascii_lowercase();
+                let num = lower.strip_prefix("kf").or_else(|| lower.strip_prefix("ko")).or_else(|| lower.strip_prefix('k'));
+                if let Some(cs) = num.and_then(|n| n.trim().parse::<i64>().ok()).filter(|cs| *cs >= 0) {
+                    karaoke = true;
+                    let fim_palavra = (cursor + cs * 10).min(fim);
+                    palavras.push(Palavra { inicio_ms: cursor, fim_ms: fim_palavra, texto: String::new() });
+                    cursor = fim_palavra;
+                }
+            }
+            resto = depois;
+            continue;
+        }
+        let (trecho, depois) = match resto.strip_prefix('\\') {
+            Some(r) if r.starts_with('N') || r.starts_with('n') => ("\n", &r[1..]),
+            Some(r) if r.starts_with('h') => (" ", &r[1..]),
+            _ => {
+                let fim_char = resto.chars().next().map(char::len_utf8).unwrap_or(1);
+                (&resto[..fim_char], &resto[fim_char..])
+            }
+        };
+        texto.push_str(trecho);
+        if let Some(p) = palavras.last_mut() {
+            p.texto.push_str(trecho);
+        }
+        resto = depois;
+    }
+
+    if !karaoke {
+        palavras.clear();
+    }
+    // Sílabas vazias ({\k50} de pausa) só contam tempo
+    palavras.retain(|p| !p.texto.is_empty());
+    (texto, palavras)
+}
+
+// ─── Conversão para o mpv ───────────────────────────────────────────────────
+
+fn tempo_ass_fmt(ms: i64) -> String {
+    let cs = ms.max(0) / 10;
+    format!("{}:{:02}:{:02}.{:02}", cs / 360_000, cs / 6000 % 60, cs / 100 % 60, cs % 100)
+}
+
+/// Texto sem nada que o ASS interprete como tag.
+fn escapar_ass(s: &str) -> String {
+    s.replace('{', "(").replace('}', ")").replace('\\', "/").replace('\n', "\\N")
+}
+
+/// Letra como legenda ASS: texto grande no centro da tela, preenchendo
+/// palavra a palavra (`\kf`) quando há tempo por palavra.
+pub fn para_ass(letra: &Letra) -> String {
+    let mut out = String::from(
+        "[Script Info]\n\
+         ScriptType: v4.00+\n\
+         PlayResX: 1280\n\
+         PlayResY: 720\n\
+         WrapStyle: 0\n\n\
+         [V4+ Styles]\n\
+         Format: Name, Fontname, Fontsize, PrimaryColour, SecondaryColour, OutlineColour, BackColour, Bold, Italic, Underline, StrikeOut, ScaleX, ScaleY, Spacing, Angle, BorderStyle, Outline, Shadow, Alignment, MarginL, MarginR, MarginV, Encoding\n\
+         Style: Default,Arial,64,&H0000FFFF,&H00FFFFFF,&H00000000,&H80000000,-1,0,0,0,100,100,0,0,1,4,2,5,40,40,40,1\n\n\
+         [Events]\n\
+         Format: Layer, Start, End, Style, Name, MarginL, MarginR, MarginV, Effect, Text\n",
+    );
+    for l in &letra.linhas {
+        let texto = if l.palavras.is_empty() {
+            escapar_ass(&l.texto)
+        } else {
+            let mut s = String::new();
+            let mut cursor = l.inicio_ms;
+            for p in &l.palavras {
+                // Pausa antes da palavra
+                if p.inicio_ms > cursor {
+                    s.push_str(&format!("{{\\k{}}}", (p.inicio_ms - cursor) / 10));
+                }
+                s.push_str(&format!("{{\\kf{}}}{}", (p.fim_ms - p.inicio_ms).max(0) / 10, escapar_ass(&p.texto)));
+                cursor = p.fim_ms.max(p.inicio_ms);
+            }
+            s
+        };
+        out.push_str(&format!(
+            "Dialogue: 0,{},{},Default,,0,0,0,,{}\n",
+            tempo_ass_fmt(l.inicio_ms),
+            tempo_ass_fmt(l.fim_ms),
+            texto
+        ));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // ─── LRC ───
+
+    #[test]
+    fn lrc_simples_com_metadados() {
+        let l = parse_lrc("[ti:Evidências]\n[ar:Chitãozinho & Xororó]\n[00:12.00]Quando eu digo\n[00:15.50]que deixei de te amar\n").unwrap();
+        assert_eq!(l.titulo.as_deref(), Some("Evidências"));
+        assert_eq!(l.artista.as_deref(), Some("Chitãozinho & Xororó"));
+        assert_eq!(l.linhas.len(), 2);
+        assert_eq!((l.linhas[0].inicio_ms, l.linhas[0].fim_ms), (12_000, 15_500));
+        assert_eq!(l.linhas[0].texto, "Quando eu digo");
+        assert_eq!(l.linhas[1].fim_ms, 15_500 + DURACAO_ULTIMA_MS);
+        assert!(l.linhas[0].palavras.is_empty());
+    }
+
+    #[test]
+    fn lrc_formatos_de_tempo() {
+        assert_eq!(tempo_lrc("01:02"), Some(62_000));
+        assert_eq!(tempo_lrc("01:02.5"), Some(62_500));
+        assert_eq!(tempo_lrc("01:02.05"), Some(62_050));
+        assert_eq!(tempo_lrc("01:02.005"), Some(62_005));
+        assert_eq!(tempo_lrc("01:02:05"), Some(62_050));
+        assert_eq!(tempo_lrc("100:00.00"), Some(6_000_000));
+        assert_eq!(tempo_lrc("01:60.00"), None);
+        assert_eq!(tempo_lrc("-1:00.00"), None);
+        assert_eq!(tempo_lrc("ab:cd"), None);
+        assert_eq!(tempo_lrc("01:02.1234"), None);
+        assert_eq!(tempo_lrc("01:02.x"), None);
+        assert_eq!(tempo_lrc("ar"), None);
+    }
+
+    #[test]
+    fn lrc_varios_tempos_na_mesma_linha_e_fora_de_ordem() {
+        let l = parse_lrc("[00:30.00]B\n[00:10.00][00:50.00]Refrão\n").unwrap();
+        let inicios: Vec<i64> = l.linhas.iter().map(|l| l.inicio_ms).collect();
+        assert_eq!(inicios, vec![10_000, 30_000, 50_000]);
+        assert_eq!(l.linhas[0].texto, "Refrão");
+        assert_eq!(l.linhas[2].texto, "Refrão");
+    }
+
+    #[test]
+    fn lrc_enhanced_com_tempo_por_palavra() {
+        let l = parse_lrc("[00:10.00]<00:10.00>Eu <00:10.50>sei <00:11.20>que<00:12.00>\n[00:13.00]fim\n").unwrap();
+        let linha = &l.linhas[0];
+        assert_eq!(linha.texto, "Eu sei que");
+        let p: Vec<(i64, i64, &str)> = linha.palavras.iter().map(|p| (p.inicio_ms, p.fim_ms, p.texto.as_str())).collect();
+        assert_eq!(p, vec![(10_000, 10_500, "Eu "), (10_500, 11_200, "sei "), (11_200, 12_000, "que")]);
+        assert_eq!(linha.fim_ms, 13_000);
+    }
+
+    #[test]
+    fn lrc_offset_adianta_e_nao_fica_negativo() {
+        let l = parse_lrc("[offset:+500]\n[00:00.20]a\n[00:10.00]<00:10.00>b <00:11.00>c\n").unwrap();
+        assert_eq!(l.linhas[0].inicio_ms, 0);
+        assert_eq!(l.linhas[1].inicio_ms, 9_500);
+        assert_eq!(l.linhas[1].palavras[1].inicio_ms, 10_500);
+        let l = parse_lrc("[offset:-250]\n[00:01.00]a\n").unwrap();
+        assert_eq!(l.linhas[0].inicio_ms, 1_250);
+    }
+
+    #[test]
+    fn lrc_malformado_pula_o_que_nao_entende() {
+        let texto = "\u{feff}lixo sem tag\r\n[00:xx.00]tempo ruim\r\n[00:05.00 sem fechar\r\n[00:06.00]boa\r\n[]\r\n[:]\r\n[00:07.00]\r\n[00:08.00]<00:08.5 palavra sem fechar\r\n";
+        let l = parse_lrc(texto).unwrap();
+        let textos: Vec<&str> = l.linhas.iter().map(|l| l.texto.as_str()).collect();
+        assert_eq!(textos, vec!["boa", "<00:08.5 palavra sem fechar"]);
+        // A linha vazia de 00:07 fecha a anterior
+        assert_eq!(l.linhas[0].fim_ms, 7_000);
+    }
+
+    #[test]
+    fn lrc_sem_linhas_e_erro() {
+        assert!(parse_lrc("").is_err());
+        assert!(parse_lrc("[ti:Só título]\n[ar:Ninguém]\n").is_err());
+        assert!(parse_lrc("texto sem tempo nenhum\n").is_err());
+        assert!(parse_lrc("[00:01.00]\n[00:02.00]\n").is_err());
+    }
+
+    #[test]
+    fn lrc_offset_invalido_e_ignorado() {
+        let l = parse_lrc("[offset:abc]\n[00:01.00]a\n").unwrap();
+        assert_eq!(l.linhas[0].inicio_ms, 1_000);
+    }
+
+    // ─── ASS ───
+
+    const ASS: &str = "[Script Info]\nTitle: Canção\nScriptType: v4.00+\n\n[V4+ Styles]\nFormat: Name, Fontname\nStyle: Default,Arial\n\n[Events]\nFormat: Layer, Start, End, Style, Name, MarginL, MarginR, MarginV, Effect, Text\n";
+
+    #[test]
+    fn ass_dialogos_com_virgula_no_texto_e_tags() {
+        let texto = format!(
+            "{}Dialogue: 0,0:00:05.00,0:00:08.50,Default,,0,0,0,,{{\\an8\\c&HFFFFFF&}}Olá, mundo\\Nsegunda\nComment: 0,0:00:01.00,0:00:02.00,Default,,0,0,0,,ignorado\nDialogue: 0,0:00:01.00,0:00:02.00,Default,,0,0,0,,Primeiro\n",
+            ASS
+        );
+        let l = parse_ass(&texto).unwrap();
+        assert_eq!(l.titulo.as_deref(), Some("Canção"));
+        assert_eq!(l.linhas.len(), 2);
+        assert_eq!(l.linhas[0].texto, "Primeiro");
+        assert_eq!((l.linhas[1].inicio_ms, l.linhas[1].fim_ms), (5_000, 8_500));
+        assert_eq!(l.linhas[1].texto, "Olá, mundo\nsegunda");
+        assert!(l.linhas[1].palavras.is_empty());
+    }
+
+    #[test]
+    fn ass_karaoke_vira_palavras() {
+        let texto = format!("{}Dialogue: 0,0:00:10.00,0:00:14.00,Default,,0,0,0,,{{\\k50}}Eu {{\\kf100}}sei{{\\k30}} {{\\K200}}que\n", ASS);
+        let l = parse_ass(&texto).unwrap();
+        let p: Vec<(i64, i64, &str)> = l.linhas[0].palavras.iter().map(|p| (p.inicio_ms, p.fim_ms, p.texto.as_str())).collect();
+        assert_eq!(p, vec![(10_000, 10_500, "Eu "), (10_500, 11_500, "sei"), (11_500, 11_800, " "), (11_800, 13_800, "que")]);
+        assert_eq!(l.linhas[0].texto, "Eu sei que");
+    }
+
+    #[test]
+    fn ass_karaoke_nao_passa_do_fim_e_pausa_vazia_some() {
+        let texto = format!("{}Dialogue: 0,0:00:01.00,0:00:02.00,Default,,0,0,0,,{{\\k20}}{{\\k500}}longa\n", ASS);
+        let l = parse_ass(&texto).unwrap();
+        assert_eq!(l.linhas[0].palavras.len(), 1);
+        assert_eq!((l.linhas[0].palavras[0].inicio_ms, l.linhas[0].palavras[0].fim_ms), (1_200, 2_000));
+    }
+
+    #[test]
+    fn ass_formato_em_outra_ordem() {
+        let texto = "[Events]\nFormat: Start, End, Text\nDialogue: 0:00:03.00,0:00:04.00,Texto, com vírgula\n";
+        let l = parse_ass(texto).unwrap();
+        assert_eq!(l.linhas[0].texto, "Texto, com vírgula");
+        assert_eq!(l.linhas[0].inicio_ms, 3_000);
+    }
+
+    #[test]
+    fn ass_malformado_pula_falas_ruins() {
+        let texto = format!(
+            "{}Dialogue: 0,xx,0:00:02.00,Default,,0,0,0,,tempo ruim\nDialogue: 0,0:00:05.00,0:00:04.00,Default,,0,0,0,,fim antes do início\nDialogue: 0,0:00:01.00\nDialogue: 0,0:00:01.00,0:00:02.00,Default,,0,0,0,,{{\\an8}}\nDialogue: 0,0:00:01.00,0:00:02.00,Default,,0,0,0,,texto {{sem fechar\nDialogue: 0,0:00:03.00,0:00:04.00,Default,,0,0,0,,boa\n",
+            ASS
+        );
+        let l = parse_ass(&texto).unwrap();
+        let textos: Vec<&str> = l.linhas.iter().map(|l| l.texto.as_str()).collect();
+        assert_eq!(textos, vec!["texto", "boa"]);
+    }
+
+    #[test]
+    fn ass_tempos() {
+        assert_eq!(tempo_ass("1:02:03.45"), Some(3_723_450));
+        assert_eq!(tempo_ass("0:00:01"), Some(1_000));
+        assert_eq!(tempo_ass("0:00:01.5"), Some(1_500));
+        assert_eq!(tempo_ass("0:61:00.00"), None);
+        assert_eq!(tempo_ass("0:00:60.00"), None);
+        assert_eq!(tempo_ass("0:00:01."), None);
+        assert_eq!(tempo_ass("00:01.00"), None);
+        assert_eq!(tempo_ass("0:0:0:0"), None);
+    }
+
+    #[test]
+    fn ass_sem_eventos_e_erro() {
+        assert!(parse_ass("").is_err());
+        assert!(parse_ass("[Script Info]\nTitle: x\n").is_err());
+        assert!(parse_ass(ASS).is_err());
+        assert!(parse_ass("Dialogue: 0,0:00:01.00,0:00:02.00,Default,,0,0,0,,fora de seção\n").is_err());
+    }
+
+    // ─── Conversão ───
+
+    #[test]
+    fn lrc_convertido_para_ass_volta_igual() {
+        let lrc = parse_lrc("[00:10.00]<00:10.00>Eu <00:10.50>sei\n[00:12.00]{chaves} e \\barra\n").unwrap();
+        let ass = para_ass(&lrc);
+        assert!(ass.contains("Dialogue: 0,0:00:10.00,0:00:12.00,Default,,0,0,0,,{\\kf50}Eu {\\kf150}sei"));
+        assert!(ass.contains("(chaves) e /barra"));
+        let volta = parse_ass(&ass).unwrap();
+        assert_eq!(volta.linhas.len(), 2);
+        assert_eq!(volta.linhas[0].texto, "Eu sei");
+        assert_eq!(volta.linhas[0].palavras[1].fim_ms, 12_000);
+    }
+
+    #[test]
+    fn formata_tempo_ass() {
+        assert_eq!(tempo_ass_fmt(3_723_450), "1:02:03.45");
+        assert_eq!(tempo_ass_fmt(-5), "0:00:00.00");
+    }
+}
@@ -1,8 +1,29 @@
+mod armazenamento;
+mod backend;
+mod biblioteca;
+mod catalogo;
+mod cdg;
 mod commands;
 mod db;
+mod download;
+mod download_manager;
+mod ferramentas;
+mod http;
+mod letra;
+mod midia;
+mod miniaturas;
+mod mpv_ipc;
+mod normalizacao;
+mod pacote_offline;
+mod quarentena;
+mod rede_local;
+mod segundo_plano;
+mod sondagem;
 mod supabase;
+#[cfg(test)]
+mod teste;
 
-use tauri::Manager;
+use tauri::{Emitter, Manager};
 use commands::player::NativePlayerState;
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
@@ -55,6 +76,37 @@ pub fn run() {
             }
             
             db::init_db(&data_dir).expect("Failed to initialize database");
+            ferramentas::definir_recursos(app.path().resource_dir().ok());
+            download::limpar_parciais(&std::path::Path::new(&data_dir).join("musicas"));
+
+            // Backend remoto (Supabase ou fake), lido após carregar o .env
+            let backend = backend::Backend::from_env();
+            let handle = app.handle().clone();
+            backend.conectividade.on_change(move |status| {
+                handle.emit("conectividade-alterada", status).ok();
+            });
+
+            // Downloads em segundo plano; começa pausado até o frontend liberar
+            let manager = download_manager::DownloadManager::new(backend.catalogo.clone(), &data_dir);
+            let handle = app.handle().clone();
+            manager.on_evento(move |evento| {
+                match evento {
+                    download_manager::EventoDownload::Status(s) => handle.emit("downloads-status", s),
+                    download_manager::EventoDownload::Progresso(p) => handle.emit("download-progresso", p),
+                    download_manager::EventoDownload::Erro(e) => handle.emit("download-erro", e),
+                }
+                .ok();
+            });
+            manager.iniciar();
+            app.manage(manager);
+
+            // Troca de vídeos com as outras máquinas da mesma conta na rede local
+            rede_local::iniciar();
+            // Duração, resolução e loudness das músicas ainda não sondadas
+            sondagem::iniciar();
+            // Miniaturas das buscas, paradas enquanto há música tocando
+            miniaturas::iniciar(&data_dir);
+            app.manage(backend);
             
             // Store data dir in app state
             app.manage(AppState {
@@ -69,18 +121,61 @@ pub fn run() {
             commands::musicas::get_musica_by_codigo,
             commands::musicas::musica_aleatoria,
             commands::musicas::get_all_musicas_count,
+            commands::musicas::buscar_catalogo,
+            commands::musicas::listar_nao_baixadas,
+            commands::musicas::get_info_midia,
             commands::historico::salvar_historico,
             commands::ativacao::verificar_ativacao,
             commands::ativacao::validar_chave,
             commands::ativacao::remover_ativacao,
+            commands::ativacao::listar_eventos_ativacao,
+            commands::ativacao::exportar_pacote_suporte,
             commands::sync::get_offline_status,
-            commands::sync::download_batch,
             commands::sync::reindex_musicas,
+            commands::sync::reverificar_musicas,
+            commands::sync::verificar_biblioteca,
+            commands::pacote_offline::exportar_pacote_offline,
+            commands::pacote_offline::importar_pacote_offline,
+            commands::pacote_offline::chaves_pacote_offline,
+            commands::pacote_offline::confiar_chave_pacote,
+            commands::downloads::status_downloads,
+            commands::downloads::retomar_downloads,
+            commands::downloads::pausar_downloads,
+            commands::downloads::cancelar_download,
+            commands::downloads::reenfileirar_download,
+            commands::downloads::priorizar_download,
+            commands::downloads::configurar_downloads,
+            commands::downloads::atualizar_fila_downloads,
+            commands::armazenamento::status_armazenamento,
+            commands::armazenamento::configurar_armazenamento,
+            commands::armazenamento::fixar_musica,
+            commands::rede_local::status_rede_local,
+            commands::rede_local::configurar_rede_local,
+            commands::normalizacao::status_normalizacao,
+            commands::normalizacao::configurar_normalizacao,
+            commands::sync::sincronizar_catalogo,
+            commands::sync::listar_quarentena,
+            commands::sync::configurar_quarentena,
             commands::video::get_video_path,
+            commands::video::cdg_quadro,
+            commands::video::get_letra,
+            commands::video::get_miniatura,
+            commands::conectividade::get_conectividade,
             commands::player::native_player_available,
             commands::player::play_native,
             commands::player::stop_native,
             commands::player::native_player_ended,
+            commands::player::reproducao_ativa,
+            commands::player::pausar_native,
+            commands::player::retomar_native,
+            commands::player::alternar_pausa_native,
+            commands::player::buscar_native,
+            commands::player::reiniciar_native,
+            commands::player::volume_native,
+            commands::player::mudo_native,
+            commands::player::estado_native,
+            commands::player::definir_tom_native,
+            commands::player::resetar_tom_native,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
@@ -0,0 +1,122 @@
+// Tipos de mídia da biblioteca.
+//
+// A música é identificada pelo código (nome do arquivo sem extensão); o
+// contêiner pode ser qualquer um que o mpv toque. `musicas_local` guarda a
+// extensão real e o tipo (vídeo com letra embutida, só áudio, cuja letra
+// vem de outro arquivo, ou MP3+G: áudio com os gráficos num `.cdg` de mesmo
+// nome), e tudo que procura o arquivo de um código passa por `localizar`.
+// O arquivo principal é sempre o que tem som; os que vêm junto (`.cdg` e as
+// letras `.ass`/`.lrc`) são "companheiros" e acompanham o principal em cópias
+// e remoções.
+
+use std::path::{Path, PathBuf};
+
+/// Extensões de vídeo, na ordem de preferência quando há mais de uma.
+pub const EXTENSOES_VIDEO: &[&str] = &["mp4", "m4v", "mkv", "webm", "mov", "avi", "mpg", "mpeg"];
+/// Extensões de áudio (pacotes áudio + letra).
+pub const EXTENSOES_AUDIO: &[&str] = &["mp3", "m4a", "ogg", "opus", "flac", "wav"];
+/// Arquivos que acompanham o principal, com o mesmo nome.
+pub const EXTENSOES_COMPANHEIRAS: &[&str] = &["cdg", "ass", "lrc"];
+/// Extensão assumida quando a URL remota não diz.
+pub const EXTENSAO_PADRAO: &str = "mp4";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TipoMidia {
+    Video,
+    Audio,
+    /// MP3+G: áudio + gráficos CD+G no `.cdg` ao lado
+    Cdg,
+}
+
+impl TipoMidia {
+    pub fn como_str(self) -> &'static str {
+        match self {
+            TipoMidia::Video => "video",
+            TipoMidia::Audio => "audio",
+            TipoMidia::Cdg => "cdg",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Midia {
+    /// Minúscula, sem ponto
+    pub extensao: String,
+    pub tipo: TipoMidia,
+}
+
+pub fn tipo_da_extensao(extensao: &str) -> Option<TipoMidia> {
+    let ext = extensao.to_ascii_lowercase();
+    if EXTENSOES_VIDEO.contains(&ext.as_str()) {
+        Some(TipoMidia::Video)
+    } else if EXTENSOES_AUDIO.contains(&ext.as_str()) {
+        Some(TipoMidia::Audio)
+    } else {
+        None
+    }
+}
+
+/// Tipo do arquivo pela extensão (áudio com `.cdg` ao lado é MP3+G); None
+/// se não for mídia da biblioteca.
+pub fn identificar(path: &Path) -> Option<Midia> {
+    let extensao = path.extension()?.to_str()?.to_ascii_lowercase();
+    let tipo = match tipo_da_extensao(&extensao)? {
+        TipoMidia::Audio if companheiro(path, "cdg").is_some() => TipoMidia::Cdg,
+        tipo => tipo,
+    };
+    Some(Midia { extensao, tipo })
+}
+
+/// Arquivo `extensao` com o mesmo nome de `path` (em minúsculas ou
+/// maiúsculas, como vêm dos discos de karaokê).
+pub fn companheiro(path: &Path, extensao: &str) -> Option<PathBuf> {
+    [extensao.to_ascii_lowercase(), extensao.to_ascii_uppercase()]
+        .into_iter()
+        .map(|ext| path.with_extension(ext))
+        .find(|p| p.is_file())
+}
+
+/// Todos os companheiros de `path` que existem no disco.
+pub fn companheiros(path: &Path) -> Vec<PathBuf> {
+    EXTENSOES_COMPANHEIRAS.iter().filter_map(|ext| companheiro(path, ext)).collect()
+}
+
+/// Extensão do arquivo remoto (URL ou caminho no storage), ignorando query
+/// string; `EXTENSAO_PADRAO` se não for uma extensão de mídia conhecida.
+pub fn extensao_remota(arquivo: &str) -> String {
+    let caminho = arquivo.split(['?', '#']).next().unwrap_or("");
+    let nome = caminho.rsplit('/').next().unwrap_or("");
+    nome.rsplit_once('.')
+        .map(|(_, ext)| ext.to_ascii_lowercase())
+        .filter(|ext| tipo_da_extensao(ext).is_some())
+        .unwrap_or_else(|| EXTENSAO_PADRAO.to_string())
+}
+
+/// Onde gravar o download de `codigo` cujo arquivo remoto é `arquivo`.
+pub fn caminho_destino(musicas_dir: &Path, codigo: &str, arquivo: &str) -> PathBuf {
+    musicas_dir.join(format!("{}.{}", codigo, extensao_remota(arquivo)))
+}
+
+/// Arquivo de mídia de `codigo` em `musicas_dir`, qualquer que seja o
+/// contêiner (vídeo antes de áudio).
+pub fn localizar(musicas_dir: &Path, codigo: &str) -> Option<PathBuf> {
+    EXTENSOES_VIDEO
+        .iter()
+        .chain(EXTENSOES_AUDIO)
+        .map(|ext| musicas_dir.join(format!("{}.{}", codigo, ext)))
+        .find(|p| p.is_file())
+}
+
+/// Arquivos de mídia no primeiro nível de `musicas_dir`.
+pub fn listar(musicas_dir: &Path) -> std::io::Result<Vec<PathBuf>> {
+    Ok(std::fs::read_dir(musicas_dir)?
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| p.is_file() && identificar(p).is_some())
+        .collect())
+}
+
+/// Código da música a partir do nome do arquivo.
+pub fn codigo_do_arquivo(path: &Path) -> Option<String> {
+    path.file_stem()?.to_str().filter(|s| !s.is_empty()).map(str::to_string)
+}
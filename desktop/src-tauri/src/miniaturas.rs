@@ -0,0 +1,197 @@
+// Miniaturas das músicas para os resultados de busca.
+//
+// Um quadro em PERCENTUAL_QUADRO da duração (passada a abertura, com a letra
+// já na tela), reduzido para LARGURA px e gravado em
+// `<data_dir>/miniaturas/<codigo>.jpg`. Sai do ffmpeg quando ele acompanha o
+// app, senão do mpv. MP3+G usa o `.cdg`; só áudio não tem miniatura.
+//
+// Só a thread gera: `caminho` (busca) devolve a que já existe e, se falta,
+// avisa a thread. Ela completa as que faltam (bibliotecas antigas e, avisada
+// pela sondagem, as músicas novas), parada enquanto há música tocando, e
+// apaga as de músicas que saíram da biblioteca. Miniatura mais velha que o arquivo da música (baixada de novo)
+// é refeita.
+
+use crate::cdg;
+use crate::db;
+use crate::ferramentas;
+use crate::midia::{self, TipoMidia};
+use crate::segundo_plano::{self, Tarefa};
+use once_cell::sync::Lazy;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+
+const PASTA: &str = "miniaturas";
+const PERCENTUAL_QUADRO: f64 = 30.0;
+const LARGURA: u32 = 320;
+/// Sem duração conhecida (ffmpeg), o quadro vem deste instante.
+const SEGUNDO_PADRAO: f64 = 20.0;
+const INTERVALO_VARREDURA: Duration = Duration::from_secs(30 * 60);
+const LIMITE_GERACAO: Duration = Duration::from_secs(30);
+
+static TAREFA: Tarefa = Tarefa::new("MINIATURAS");
+/// Códigos que falharam nesta execução; não são tentados de novo pela thread.
+static FALHAS: Lazy<Mutex<HashSet<String>>> = Lazy::new(|| Mutex::new(HashSet::new()));
+
+pub fn pasta(data_dir: &str) -> PathBuf {
+    Path::new(data_dir).join(PASTA)
+}
+
+fn arquivo(data_dir: &str, codigo: &str) -> PathBuf {
+    pasta(data_dir).join(format!("{}.jpg", codigo))
+}
+
+/// Existe e é mais nova que a mídia.
+fn atual(miniatura: &Path, midia: &Path) -> bool {
+    let modificado = |p: &Path| std::fs::metadata(p).and_then(|m| m.modified()).ok();
+    match (modificado(miniatura), modificado(midia)) {
+        (Some(m), Some(v)) => m >= v,
+        (Some(_), None) => true,
+        _ => false,
+    }
+}
+
+/// Miniatura da música `codigo`. None se a música não está baixada, é só
+/// áudio, o quadro não pôde ser extraído ou ainda não foi gerada (a thread é
+/// avisada e a próxima busca já a encontra).
+pub fn caminho(data_dir: &str, codigo: &str) -> Result<Option<PathBuf>, String> {
+    let Some(m) = db::get_musica_by_codigo_db(codigo)? else {
+        return Ok(None);
+    };
+    let destino = arquivo(data_dir, &m.codigo);
+    if atual(&destino, Path::new(&m.arquivo)) {
+        return Ok(Some(destino));
+    }
+    if !FALHAS.lock().unwrap().contains(&m.codigo) {
+        avisar();
+    }
+    Ok(None)
+}
+
+/// Acorda a thread (músicas novas, já sondadas).
+pub fn avisar() {
+    TAREFA.avisar();
+}
+
+pub fn iniciar(data_dir: &str) {
+    let data_dir = data_dir.to_string();
+    TAREFA.iniciar(INTERVALO_VARREDURA, move || {
+        if let Err(e) = completar(&data_dir) {
+            log::warn!("[MINIATURAS] {}", e);
+        }
+    });
+}
+
+/// Gera as que faltam e remove as de músicas que não estão mais na biblioteca.
+fn completar(data_dir: &str) -> Result<(), String> {
+    if ferramentas::localizar("ffmpeg").is_none() && ferramentas::localizar("mpv").is_none() {
+        return Ok(());
+    }
+    let dir = pasta(data_dir);
+    std::fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+    let musicas = db::listar_musicas_local()?;
+
+    let codigos: HashSet<&str> = musicas.iter().map(|m| m.codigo.as_str()).collect();
+    for entrada in std::fs::read_dir(&dir).map_err(|e| e.to_string())?.filter_map(|e| e.ok()) {
+        let path = entrada.path();
+        // Sobras de uma geração interrompida (só esta thread gera)
+        if path.is_dir() {
+            std::fs::remove_dir_all(&path).ok();
+            continue;
+        }
+        if path.to_string_lossy().contains(".tmp") || midia::codigo_do_arquivo(&path).is_none_or(|c| !codigos.contains(c.as_str())) {
+            std::fs::remove_file(&path).ok();
+        }
+    }
+
+    let mut geradas = 0;
+    for m in &musicas {
+        let destino = arquivo(data_dir, &m.codigo);
+        if atual(&destino, Path::new(&m.arquivo)) || FALHAS.lock().unwrap().contains(&m.codigo) {
+            continue;
+        }
+        // Prioridade baixa: nada de extrair quadros com música tocando
+        segundo_plano::esperar_reproducao();
+        match gerar(Path::new(&m.arquivo), &destino, m.duracao) {
+            Ok(true) => geradas += 1,
+            Ok(false) => {
+                FALHAS.lock().unwrap().insert(m.codigo.clone());
+            }
+            Err(e) => {
+                log::warn!("[MINIATURAS] {}: {}", m.codigo, e);
+                FALHAS.lock().unwrap().insert(m.codigo.clone());
+            }
+        }
+    }
+    if geradas > 0 {
+        log::info!("[MINIATURAS] {} geradas", geradas);
+    }
+    Ok(())
+}
+
+/// Extrai o quadro de `arquivo` para `destino`. Ok(false) se a mídia não tem
+/// imagem (só áudio).
+fn gerar(arquivo: &Path, destino: &Path, duracao: Option<i64>) -> Result<bool, String> {
+    let fonte = match midia::identificar(arquivo).map(|m| m.tipo) {
+        Some(TipoMidia::Video) => arquivo.to_path_buf(),
+        Some(TipoMidia::Cdg) => cdg::arquivo_cdg(arquivo).ok_or("CDG não encontrado")?,
+        _ => return Ok(false),
+    };
+    if !fonte.is_file() {
+        return Err(format!("arquivo não encontrado: {}", fonte.display()));
+    }
+    let dir = destino.parent().ok_or("destino inválido")?;
+    std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    let codigo = midia::codigo_do_arquivo(destino).ok_or("destino inválido")?;
+    // Gravada ao lado e renomeada: a busca nunca vê um JPEG pela metade
+    let temporario = dir.join(format!("{}.tmp.jpg", codigo));
+
+    let resultado = match ferramentas::localizar("ffmpeg") {
+        Some(ffmpeg) => {
+            let segundo = duracao
+                .filter(|d| *d > 0)
+                .map(|d| d as f64 * PERCENTUAL_QUADRO / 100.0)
+                .unwrap_or(SEGUNDO_PADRAO);
+            let mut cmd = ferramentas::comando(&ffmpeg);
+            cmd.args(["-hide_banner", "-loglevel", "error", "-y", "-ss", &format!("{:.2}", segundo), "-i"])
+                .arg(&fonte)
+                .args(["-frames:v", "1", "-vf", &format!("scale={}:-2", LARGURA), "-q:v", "4"])
+                .arg(&temporario);
+            ferramentas::executar(cmd, LIMITE_GERACAO)
+        }
+        None => {
+            let mpv = ferramentas::localizar("mpv").ok_or("nem ffmpeg nem mpv disponíveis")?;
+            // O mpv grava 00000001.jpg numa pasta; vai para uma só dele
+            let saida = dir.join(format!("{}.tmp", codigo));
+            std::fs::create_dir_all(&saida).map_err(|e| e.to_string())?;
+            let mut cmd = ferramentas::comando(&mpv);
+            cmd.args([
+                "--no-config",
+                "--no-audio",
+                "--no-sub",
+                &format!("--start={}%", PERCENTUAL_QUADRO),
+                "--frames=1",
+                "--vo=image",
+                "--vo-image-format=jpg",
+                &format!("--vf=scale={}:-2", LARGURA),
+            ])
+            .arg(format!("--vo-image-outdir={}", saida.display()))
+            .arg(&fonte);
+            let r = ferramentas::executar(cmd, LIMITE_GERACAO);
+            if let Some(quadro) = std::fs::read_dir(&saida).ok().and_then(|mut d| d.find_map(|e| e.ok())) {
+                std::fs::rename(quadro.path(), &temporario).ok();
+            }
+            std::fs::remove_dir_all(&saida).ok();
+            r
+        }
+    }?;
+
+    if !temporario.is_file() {
+        std::fs::remove_file(&temporario).ok();
+        let erro = String::from_utf8_lossy(&resultado.stderr).trim().to_string();
+        return Err(if erro.is_empty() { "nenhum quadro extraído".to_string() } else { erro });
+    }
+    std::fs::rename(&temporario, destino).map_err(|e| e.to_string())?;
+    Ok(true)
+}
@@ -0,0 +1,98 @@
+// Cliente do IPC JSON do mpv (`--input-ipc-server`).
+//
+// O mpv do player escuta num socket Unix (pasta temporária) ou num named pipe
+// no Windows, com nome próprio deste processo. Cada comando abre uma conexão,
+// manda `{"command": [...], "request_id": n}` e lê linhas até a resposta com
+// o mesmo id (os eventos que o mpv manda no meio são ignorados).
+// Referência: https://mpv.io/manual/stable/#json-ipc
+
+use serde_json::{json, Value};
+use std::io::{BufRead, BufReader, Write};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+const LIMITE_RESPOSTA: Duration = Duration::from_secs(2);
+
+static PROXIMO_ID: AtomicU64 = AtomicU64::new(1);
+
+/// Endereço passado ao mpv em `--input-ipc-server`.
+pub fn endereco() -> String {
+    let nome = format!("blue-karaoke-mpv-{}", std::process::id());
+    if cfg!(target_os = "windows") {
+        format!(r"\\.\pipe\{}", nome)
+    } else {
+        std::env::temp_dir().join(format!("{}.sock", nome)).to_string_lossy().to_string()
+    }
+}
+
+/// Apaga o socket de uma execução anterior do mpv (no Windows o pipe some
+/// sozinho com o processo).
+pub fn limpar() {
+    #[cfg(unix)]
+    std::fs::remove_file(endereco()).ok();
+}
+
+#[cfg(unix)]
+fn conectar() -> std::io::Result<(Box<dyn BufRead>, Box<dyn Write>)> {
+    let stream = std::os::unix::net::UnixStream::connect(endereco())?;
+    stream.set_read_timeout(Some(LIMITE_RESPOSTA))?;
+    stream.set_write_timeout(Some(LIMITE_RESPOSTA))?;
+    Ok((Box::new(BufReader::new(stream.try_clone()?)), Box::new(stream)))
+}
+
+#[cfg(windows)]
+fn conectar() -> std::io::Result<(Box<dyn BufRead>, Box<dyn Write>)> {
+    // O named pipe abre como arquivo comum, sem timeout de leitura (ver `comando`)
+    let pipe = std::fs::OpenOptions::new().read(true).write(true).open(endereco())?;
+    Ok((Box::new(BufReader::new(pipe.try_clone()?)), Box::new(pipe)))
+}
+
+/// Executa `comando` (ex.: `["set_property", "pause", true]`) e devolve o
+/// campo `data` da resposta.
+pub fn comando(comando: Value) -> Result<Value, String> {
+    if cfg!(windows) {
+        // Sem timeout no pipe, a troca vai para uma thread e a resposta é
+        // esperada até LIMITE_RESPOSTA; se o mpv travar, a thread fica presa
+        // na leitura até ele fechar o pipe
+        let (tx, rx) = std::sync::mpsc::channel();
+        std::thread::spawn(move || tx.send(trocar(comando)).ok());
+        return rx
+            .recv_timeout(LIMITE_RESPOSTA)
+            .map_err(|_| format!("mpv não respondeu em {}s", LIMITE_RESPOSTA.as_secs()))?;
+    }
+    trocar(comando)
+}
+
+fn trocar(comando: Value) -> Result<Value, String> {
+    let (mut leitor, mut escritor) = conectar().map_err(|e| format!("mpv não está tocando ({})", e))?;
+    let id = PROXIMO_ID.fetch_add(1, Ordering::Relaxed);
+    let mut linha = json!({ "command": comando, "request_id": id }).to_string();
+    linha.push('\n');
+    escritor.write_all(linha.as_bytes()).map_err(|e| e.to_string())?;
+    escritor.flush().map_err(|e| e.to_string())?;
+
+    let mut resposta = String::new();
+    loop {
+        resposta.clear();
+        if leitor.read_line(&mut resposta).map_err(|e| e.to_string())? == 0 {
+            return Err("mpv fechou a conexão".into());
+        }
+        let Ok(valor) = serde_json::from_str::<Value>(&resposta) else { continue };
+        if valor["request_id"].as_u64() != Some(id) {
+            continue;
+        }
+        return match valor["error"].as_str() {
+            Some("success") => Ok(valor["data"].clone()),
+            Some(erro) => Err(format!("mpv: {}", erro)),
+            None => Err("mpv: resposta sem status".into()),
+        };
+    }
+}
+
+pub fn definir(propriedade: &str, valor: Value) -> Result<(), String> {
+    comando(json!(["set_property", propriedade, valor])).map(|_| ())
+}
+
+pub fn obter(propriedade: &str) -> Result<Value, String> {
+    comando(json!(["get_property", propriedade]))
+}
@@ -0,0 +1,93 @@
+// Normalização de volume entre as músicas do catálogo.
+//
+// O loudness integrado (EBU R128) e o pico verdadeiro de cada arquivo são
+// medidos pela sondagem (sondagem.rs) e ficam em `musicas_local`. Ao tocar,
+// a diferença para o alvo global (`loudness_alvo`, em LUFS) vira um ganho
+// aplicado por um filtro de áudio do mpv, separado do volume do operador:
+// limitado a GANHO_MIN_DB..GANHO_MAX_DB e sem passar o pico de TETO_PICO_DBTP.
+// Música ainda não medida toca sem ganho.
+
+use crate::db;
+use serde::Serialize;
+
+const CHAVE_ATIVA: &str = "normalizacao_ativa";
+const CHAVE_ALVO: &str = "loudness_alvo";
+
+const ALVO_PADRAO_LUFS: f64 = -16.0;
+const ALVO_MIN_LUFS: f64 = -30.0;
+const ALVO_MAX_LUFS: f64 = -8.0;
+const GANHO_MIN_DB: f64 = -20.0;
+const GANHO_MAX_DB: f64 = 12.0;
+const TETO_PICO_DBTP: f64 = -1.0;
+/// Abaixo disso a medida é de silêncio (intro vazia, arquivo mudo).
+const LOUDNESS_MINIMO_LUFS: f64 = -60.0;
+
+#[derive(Debug, Serialize)]
+pub struct StatusNormalizacao {
+    pub ativa: bool,
+    #[serde(rename = "alvoLufs")]
+    pub alvo_lufs: f64,
+    /// Músicas com loudness medido
+    pub analisadas: i64,
+    /// Ainda não sondadas (a medida sai em segundo plano)
+    pub pendentes: i64,
+}
+
+pub fn ativa() -> bool {
+    db::get_config(CHAVE_ATIVA).ok().flatten().is_none_or(|v| v != "0")
+}
+
+pub fn alvo_lufs() -> f64 {
+    db::get_config(CHAVE_ALVO)
+        .ok()
+        .flatten()
+        .and_then(|v| v.parse::<f64>().ok())
+        .filter(|v| (ALVO_MIN_LUFS..=ALVO_MAX_LUFS).contains(v))
+        .unwrap_or(ALVO_PADRAO_LUFS)
+}
+
+/// Altera só o que vier preenchido.
+pub fn configurar(ativa: Option<bool>, alvo_lufs: Option<f64>) -> Result<(), String> {
+    if let Some(alvo) = alvo_lufs {
+        if !(ALVO_MIN_LUFS..=ALVO_MAX_LUFS).contains(&alvo) {
+            return Err(format!("Alvo fora da faixa ({} a {} LUFS)", ALVO_MIN_LUFS, ALVO_MAX_LUFS));
+        }
+        db::set_config(CHAVE_ALVO, &alvo.to_string())?;
+    }
+    if let Some(ativa) = ativa {
+        db::set_config(CHAVE_ATIVA, if ativa { "1" } else { "0" })?;
+    }
+    Ok(())
+}
+
+pub fn status() -> Result<StatusNormalizacao, String> {
+    let (analisadas, pendentes) = db::contar_loudness()?;
+    Ok(StatusNormalizacao { ativa: ativa(), alvo_lufs: alvo_lufs(), analisadas, pendentes })
+}
+
+/// Ganho (dB) que leva a música de `loudness_lufs` ao alvo.
+pub fn ganho_db(loudness_lufs: f64, pico_dbtp: Option<f64>, alvo_lufs: f64) -> Option<f64> {
+    if !loudness_lufs.is_finite() || loudness_lufs < LOUDNESS_MINIMO_LUFS {
+        return None;
+    }
+    let mut ganho = (alvo_lufs - loudness_lufs).clamp(GANHO_MIN_DB, GANHO_MAX_DB);
+    if let Some(pico) = pico_dbtp.filter(|p| p.is_finite()) {
+        ganho = ganho.min(TETO_PICO_DBTP - pico);
+    }
+    Some(ganho)
+}
+
+/// Argumento do mpv com o ganho da música em `arquivo`; None se desligada,
+/// ainda não medida ou sem ajuste a fazer.
+pub fn arg_mpv(arquivo: &str) -> Option<String> {
+    if !ativa() {
+        return None;
+    }
+    let (lufs, pico) = db::loudness_por_arquivo(arquivo).ok().flatten()?;
+    let ganho = ganho_db(lufs, pico, alvo_lufs())?;
+    if ganho.abs() < 0.1 {
+        return None;
+    }
+    log::info!("[NORMALIZACAO] {:.1} LUFS → ganho {:+.1} dB", lufs, ganho);
+    Some(format!("--af-add=@normalizacao:lavfi=[volume={:.2}dB]", ganho))
+}
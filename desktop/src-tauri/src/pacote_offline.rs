@@ -0,0 +1,620 @@
+// Pacote offline: músicas levadas num pendrive para casas sem internet.
+//
+// Formato (uma pasta, em qualquer caminho montado):
+//
+//   manifesto.json  — versão do catálogo, origem e, por música, metadados,
+//                     tamanho e SHA-256 do vídeo
+//   manifesto.sig   — assinatura Ed25519 (hex) dos bytes de manifesto.json
+//   videos/<codigo>.<ext>  — no contêiner original (mp4, mkv, mp3...), com
+//                            os companheiros (.cdg, .lrc, .ass) ao lado e
+//                            no manifesto
+//
+// Cada máquina tem seu par de chaves (`pacote_chave_privada` em config_local)
+// e assina o que exporta. Na importação a assinatura é sempre conferida; uma
+// música só entra se o assinante for confiável (a própria máquina, as chaves
+// de PACOTE_CHAVES_CONFIAVEIS no .env ou as cadastradas com
+// `confiar_chave`) ou se id e hash baterem com o catálogo local. O catálogo
+// não tem hash dos companheiros: de assinante não confiável só o vídeo
+// entra. Todo arquivo é copiado para `.part`, conferido com o manifesto e
+// só então ganha o nome final, como num download.
+
+use crate::armazenamento;
+use crate::backend::{ArquivoBaixado, BackendError, Integridade};
+use crate::catalogo;
+use crate::db;
+use crate::download::{self, ArquivoParcial};
+use crate::midia;
+use crate::sondagem;
+use ed25519_dalek::{Signature, Signer, SigningKey, VerifyingKey};
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::Read;
+use std::path::{Component, Path, PathBuf};
+
+const FORMATO: u32 = 1;
+const ARQUIVO_MANIFESTO: &str = "manifesto.json";
+const ARQUIVO_ASSINATURA: &str = "manifesto.sig";
+const PASTA_VIDEOS: &str = "videos";
+
+const CHAVE_PRIVADA: &str = "pacote_chave_privada";
+const CHAVE_CONFIAVEIS: &str = "pacote_chaves_confiaveis";
+const ENV_CONFIAVEIS: &str = "PACOTE_CHAVES_CONFIAVEIS";
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Manifesto {
+    pub formato: u32,
+    /// `catalogo_hwm` da máquina que exportou
+    pub versao_catalogo: Option<String>,
+    pub criado_em: i64,
+    /// machine_id de quem exportou
+    pub origem: Option<String>,
+    /// Chave pública Ed25519 (hex) que assinou o manifesto
+    pub chave_publica: String,
+    pub musicas: Vec<MusicaPacote>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MusicaPacote {
+    pub id: String,
+    pub codigo: String,
+    pub artista: String,
+    pub titulo: String,
+    pub arquivo_remoto: Option<String>,
+    pub nome_arquivo: Option<String>,
+    pub tamanho: u64,
+    pub duracao: Option<i64>,
+    pub user_id: Option<String>,
+    pub updated_at: Option<String>,
+    pub created_at: Option<String>,
+    pub sha256: String,
+    /// Caminho do vídeo relativo à pasta do pacote
+    pub video: String,
+    /// Arquivos que acompanham o vídeo (`.cdg` do MP3+G, letras)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub companheiros: Vec<ArquivoCompanheiro>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArquivoCompanheiro {
+    /// Relativo à pasta do pacote
+    pub caminho: String,
+    pub sha256: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ProgressoPacote {
+    /// "exportando", "verificando" ou "copiando"
+    pub etapa: &'static str,
+    pub atual: usize,
+    pub total: usize,
+    pub codigo: Option<String>,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct ResultadoExportacao {
+    pub pasta: String,
+    pub musicas: usize,
+    pub bytes: u64,
+    #[serde(rename = "chavePublica")]
+    pub chave_publica: String,
+    pub erros: Vec<String>,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct ResultadoImportacao {
+    #[serde(rename = "versaoCatalogo")]
+    pub versao_catalogo: Option<String>,
+    #[serde(rename = "chavePublica")]
+    pub chave_publica: String,
+    /// Assinante está entre as chaves confiáveis
+    pub confiavel: bool,
+    pub importadas: Vec<String>,
+    /// Já estavam na biblioteca com o mesmo vídeo
+    pub duplicadas: Vec<String>,
+    /// O catálogo local tem outra versão do vídeo
+    pub desatualizadas: Vec<String>,
+    /// Assinante não confiável e música fora do catálogo local
+    pub rejeitadas: Vec<String>,
+    pub erros: Vec<String>,
+}
+
+type OnProgresso<'a> = &'a (dyn Fn(ProgressoPacote) + Send + Sync);
+
+// ─── Chaves ─────────────────────────────────────────────────────────────────
+
+fn chave_privada() -> Result<SigningKey, String> {
+    if let Some(hex) = db::get_config(CHAVE_PRIVADA)? {
+        let bytes: [u8; 32] = hex::decode(hex.trim())
+            .ok()
+            .and_then(|b| b.try_into().ok())
+            .ok_or("Chave de assinatura local inválida")?;
+        return Ok(SigningKey::from_bytes(&bytes));
+    }
+    let chave = SigningKey::from_bytes(&rand::random::<[u8; 32]>());
+    db::set_config(CHAVE_PRIVADA, &hex::encode(chave.to_bytes()))?;
+    Ok(chave)
+}
+
+/// Chave pública desta máquina, para ser cadastrada como confiável em outras.
+pub fn chave_publica() -> Result<String, String> {
+    Ok(hex::encode(chave_privada()?.verifying_key().to_bytes()))
+}
+
+/// Chaves cadastradas nesta máquina (sem as do .env e a própria).
+pub fn chaves_confiaveis() -> Result<Vec<String>, String> {
+    Ok(db::get_config(CHAVE_CONFIAVEIS)?
+        .unwrap_or_default()
+        .split(',')
+        .map(|c| c.trim().to_ascii_lowercase())
+        .filter(|c| !c.is_empty())
+        .collect())
+}
+
+pub fn confiar_chave(chave: &str, confiar: bool) -> Result<Vec<String>, String> {
+    let chave = chave.trim().to_ascii_lowercase();
+    let valida = hex::decode(&chave).ok().and_then(|b| <[u8; 32]>::try_from(b).ok());
+    if valida.is_none_or(|b| VerifyingKey::from_bytes(&b).is_err()) {
+        return Err("Chave pública inválida (esperado Ed25519 em hex, 64 caracteres)".to_string());
+    }
+    let mut chaves = chaves_confiaveis()?;
+    chaves.retain(|c| *c != chave);
+    if confiar {
+        chaves.push(chave);
+    }
+    db::set_config(CHAVE_CONFIAVEIS, &chaves.join(","))?;
+    Ok(chaves)
+}
+
+fn eh_confiavel(chave: &str) -> Result<bool, String> {
+    let chave = chave.to_ascii_lowercase();
+    if chave == chave_publica()? || chaves_confiaveis()?.contains(&chave) {
+        return Ok(true);
+    }
+    Ok(std::env::var(ENV_CONFIAVEIS)
+        .unwrap_or_default()
+        .split(',')
+        .any(|c| c.trim().eq_ignore_ascii_case(&chave)))
+}
+
+fn conferir_assinatura(manifesto: &[u8], assinatura_hex: &str, chave_hex: &str) -> Result<(), String> {
+    let chave: [u8; 32] = hex::decode(chave_hex.trim())
+        .ok()
+        .and_then(|b| b.try_into().ok())
+        .ok_or("Chave pública do pacote inválida")?;
+    let assinatura: [u8; 64] = hex::decode(assinatura_hex.trim())
+        .ok()
+        .and_then(|b| b.try_into().ok())
+        .ok_or("Assinatura do pacote inválida")?;
+    VerifyingKey::from_bytes(&chave)
+        .map_err(|e| format!("Chave pública do pacote inválida: {}", e))?
+        .verify_strict(manifesto, &Signature::from_bytes(&assinatura))
+        .map_err(|_| "Assinatura não confere: manifesto alterado ou corrompido".to_string())
+}
+
+// ─── Exportação ─────────────────────────────────────────────────────────────
+
+/// Grava em `destino` um pacote com as músicas baixadas (todas, ou só
+/// `codigos`). Cada vídeo tem o hash recalculado antes de entrar.
+pub fn exportar(destino: &Path, codigos: Option<Vec<String>>, progresso: OnProgresso) -> Result<ResultadoExportacao, String> {
+    let chave = chave_privada()?;
+    let videos_dir = destino.join(PASTA_VIDEOS);
+    std::fs::create_dir_all(&videos_dir).map_err(|e| format!("{}: {}", videos_dir.display(), e))?;
+
+    let musicas = match codigos {
+        Some(codigos) => codigos
+            .iter()
+            .filter_map(|c| db::get_musica_local(c).transpose())
+            .collect::<Result<Vec<_>, _>>()?,
+        None => db::listar_musicas_local()?,
+    };
+    let mut out = ResultadoExportacao {
+        pasta: destino.to_string_lossy().to_string(),
+        chave_publica: hex::encode(chave.verifying_key().to_bytes()),
+        ..Default::default()
+    };
+    let mut entradas = Vec::new();
+    for (i, m) in musicas.iter().enumerate() {
+        progresso(ProgressoPacote { etapa: "exportando", atual: i, total: musicas.len(), codigo: Some(m.codigo.clone()) });
+        let origem = Path::new(&m.arquivo);
+        let sha256 = match download::sha256_arquivo(origem) {
+            Ok(h) => h,
+            Err(e) => {
+                out.erros.push(format!("{}: {}", m.codigo, e));
+                continue;
+            }
+        };
+        if m.sha256.as_deref().is_some_and(|h| !h.eq_ignore_ascii_case(&sha256)) {
+            out.erros.push(format!("{}: vídeo não confere com o download, não exportado", m.codigo));
+            continue;
+        }
+        let extensao = midia::identificar(origem).map(|t| t.extensao).unwrap_or_else(|| midia::EXTENSAO_PADRAO.to_string());
+        let video = format!("{}/{}.{}", PASTA_VIDEOS, m.codigo, extensao);
+        let alvo = destino.join(&video);
+        let temporario = download::caminho_parcial(&alvo);
+        let tamanho = match std::fs::copy(origem, &temporario).and_then(|t| std::fs::rename(&temporario, &alvo).map(|_| t)) {
+            Ok(t) => t,
+            Err(e) => {
+                std::fs::remove_file(&temporario).ok();
+                out.erros.push(format!("{}: {}", m.codigo, e));
+                continue;
+            }
+        };
+        let companheiros = match exportar_companheiros(destino, origem, &m.codigo) {
+            Ok(c) => c,
+            Err(e) => {
+                std::fs::remove_file(&alvo).ok();
+                out.erros.push(format!("{}: {}", m.codigo, e));
+                continue;
+            }
+        };
+        let remota = db::get_catalogo_by_codigo(&m.codigo)?.filter(|c| c.id == m.id);
+        entradas.push(MusicaPacote {
+            id: m.id.clone(),
+            codigo: m.codigo.clone(),
+            artista: m.artista.clone(),
+            titulo: m.titulo.clone(),
+            arquivo_remoto: m.arquivo_remoto.clone(),
+            nome_arquivo: m.nome_arquivo.clone(),
+            tamanho,
+            duracao: m.duracao,
+            user_id: m.user_id.clone(),
+            updated_at: remota.as_ref().and_then(|c| c.updated_at.clone()),
+            created_at: remota.as_ref().and_then(|c| c.created_at.clone()),
+            sha256,
+            video,
+            companheiros,
+        });
+        out.bytes += tamanho;
+    }
+    out.musicas = entradas.len();
+
+    let manifesto = Manifesto {
+        formato: FORMATO,
+        versao_catalogo: catalogo::versao(),
+        criado_em: chrono::Utc::now().timestamp_millis(),
+        origem: db::get_or_create_machine_id().ok(),
+        chave_publica: out.chave_publica.clone(),
+        musicas: entradas,
+    };
+    let bytes = serde_json::to_vec_pretty(&manifesto).map_err(|e| e.to_string())?;
+    let assinatura = hex::encode(chave.sign(&bytes).to_bytes());
+    std::fs::write(destino.join(ARQUIVO_MANIFESTO), &bytes).map_err(|e| e.to_string())?;
+    std::fs::write(destino.join(ARQUIVO_ASSINATURA), assinatura).map_err(|e| e.to_string())?;
+    progresso(ProgressoPacote { etapa: "exportando", atual: musicas.len(), total: musicas.len(), codigo: None });
+
+    log::info!("[PACOTE] {} músicas exportadas para {} ({} bytes)", out.musicas, out.pasta, out.bytes);
+    Ok(out)
+}
+
+// ─── Importação ─────────────────────────────────────────────────────────────
+
+/// Lê e confere a assinatura do pacote em `origem`.
+pub fn ler_manifesto(origem: &Path) -> Result<Manifesto, String> {
+    let bytes = std::fs::read(origem.join(ARQUIVO_MANIFESTO))
+        .map_err(|e| format!("{} não encontrado em {}: {}", ARQUIVO_MANIFESTO, origem.display(), e))?;
+    let assinatura = std::fs::read_to_string(origem.join(ARQUIVO_ASSINATURA))
+        .map_err(|_| format!("Pacote sem assinatura ({})", ARQUIVO_ASSINATURA))?;
+    let manifesto: Manifesto = serde_json::from_slice(&bytes).map_err(|e| format!("Manifesto inválido: {}", e))?;
+    if manifesto.formato > FORMATO {
+        return Err(format!("Pacote no formato {}, esta versão lê até o {}", manifesto.formato, FORMATO));
+    }
+    conferir_assinatura(&bytes, &assinatura, &manifesto.chave_publica)?;
+    Ok(manifesto)
+}
+
+/// Copia para o pacote os companheiros do vídeo `origem`, com o hash de cada.
+fn exportar_companheiros(destino: &Path, origem: &Path, codigo: &str) -> Result<Vec<ArquivoCompanheiro>, String> {
+    let mut out = Vec::new();
+    for fonte in midia::companheiros(origem) {
+        let extensao = fonte.extension().and_then(|e| e.to_str()).unwrap_or_default().to_ascii_lowercase();
+        let caminho = format!("{}/{}.{}", PASTA_VIDEOS, codigo, extensao);
+        let sha256 = download::sha256_arquivo(&fonte).map_err(|e| format!("{}: {}", fonte.display(), e))?;
+        std::fs::copy(&fonte, destino.join(&caminho)).map_err(|e| format!("{}: {}", fonte.display(), e))?;
+        out.push(ArquivoCompanheiro { caminho, sha256 });
+    }
+    Ok(out)
+}
+
+/// Caminho do vídeo dentro do pacote; recusa caminhos que saiam da pasta.
+fn caminho_video(origem: &Path, video: &str) -> Option<PathBuf> {
+    let relativo = Path::new(video);
+    relativo
+        .components()
+        .all(|c| matches!(c, Component::Normal(_)))
+        .then(|| origem.join(relativo))
+}
+
+fn codigo_valido(codigo: &str) -> bool {
+    !codigo.is_empty() && codigo.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+/// Importa o pacote em `origem` para a biblioteca de `data_dir`.
+pub fn importar(data_dir: &str, origem: &Path, progresso: OnProgresso) -> Result<ResultadoImportacao, String> {
+    let manifesto = ler_manifesto(origem)?;
+    let musicas_dir = Path::new(data_dir).join("musicas");
+    std::fs::create_dir_all(&musicas_dir).map_err(|e| e.to_string())?;
+
+    let mut out = ResultadoImportacao {
+        versao_catalogo: manifesto.versao_catalogo.clone(),
+        chave_publica: manifesto.chave_publica.to_ascii_lowercase(),
+        confiavel: eh_confiavel(&manifesto.chave_publica)?,
+        ..Default::default()
+    };
+    let total = manifesto.musicas.len();
+    for (i, m) in manifesto.musicas.iter().enumerate() {
+        progresso(ProgressoPacote { etapa: "verificando", atual: i, total, codigo: Some(m.codigo.clone()) });
+        let Some(fonte) = caminho_video(origem, &m.video).filter(|_| codigo_valido(&m.codigo)) else {
+            out.erros.push(format!("{}: caminho inválido no manifesto", m.codigo));
+            continue;
+        };
+        let Some(tipo) = midia::identificar(&fonte) else {
+            out.erros.push(format!("{}: formato não suportado ({})", m.codigo, m.video));
+            continue;
+        };
+
+        let remota = db::get_catalogo_by_codigo(&m.codigo)?;
+        if let Some(r) = &remota {
+            if r.id != m.id || r.sha256.as_deref().is_some_and(|h| !h.eq_ignore_ascii_case(&m.sha256)) {
+                out.desatualizadas.push(m.codigo.clone());
+                continue;
+            }
+        }
+        let no_catalogo = remota.as_ref().is_some_and(|r| r.sha256.is_some());
+        if !out.confiavel && !no_catalogo {
+            out.rejeitadas.push(m.codigo.clone());
+            continue;
+        }
+
+        if let Some(local) = db::get_musica_local(&m.codigo)? {
+            let mesmo_video = match &local.sha256 {
+                Some(h) => h.eq_ignore_ascii_case(&m.sha256),
+                None => local.id == m.id && local.tamanho == Some(m.tamanho as i64),
+            };
+            if mesmo_video && Path::new(&local.arquivo).exists() {
+                out.duplicadas.push(m.codigo.clone());
+                continue;
+            }
+        }
+
+        progresso(ProgressoPacote { etapa: "copiando", atual: i, total, codigo: Some(m.codigo.clone()) });
+        let _reserva = match armazenamento::garantir_espaco(&musicas_dir, &m.codigo, Some(m.tamanho as i64)) {
+            Ok(r) => r,
+            Err(e) => {
+                out.erros.push(e);
+                break;
+            }
+        };
+        let companheiros: &[ArquivoCompanheiro] = if out.confiavel {
+            &m.companheiros
+        } else {
+            if !m.companheiros.is_empty() {
+                out.erros.push(format!("{}: companheiros ignorados (assinante não confiável)", m.codigo));
+            }
+            &[]
+        };
+        match copiar(origem, &fonte, &musicas_dir.join(format!("{}.{}", m.codigo, tipo.extensao)), m, companheiros) {
+            Ok(_) => out.importadas.push(m.codigo.clone()),
+            Err(e) => out.erros.push(format!("{}: {}", m.codigo, e)),
+        }
+    }
+    progresso(ProgressoPacote { etapa: "copiando", atual: total, total, codigo: None });
+
+    log::info!(
+        "[PACOTE] Importação de {}: {} importadas, {} duplicadas, {} desatualizadas, {} rejeitadas, {} erros",
+        origem.display(), out.importadas.len(), out.duplicadas.len(), out.desatualizadas.len(),
+        out.rejeitadas.len(), out.erros.len()
+    );
+    Ok(out)
+}
+
+/// Copia um arquivo do pacote como se fosse um download.
+fn copiar_conferido(fonte: &Path, dest: &Path, integridade: &Integridade) -> Result<ArquivoBaixado, String> {
+    let mut origem = File::open(fonte).map_err(|e| format!("{}: {}", fonte.display(), e))?;
+    let mut parcial = ArquivoParcial::criar(dest).map_err(|e| e.to_string())?;
+    let mut buf = vec![0u8; 256 * 1024];
+    loop {
+        let n = origem.read(&mut buf).map_err(|e| format!("{}: {}", fonte.display(), e))?;
+        if n == 0 {
+            break;
+        }
+        parcial.gravar(&buf[..n]).map_err(|e| e.to_string())?;
+    }
+    parcial.concluir(integridade).map_err(|e| match e {
+        BackendError::Integridade(e) => format!("arquivo do pacote não confere com o manifesto ({})", e),
+        e => e.to_string(),
+    })
+}
+
+/// Origem e destino de um companheiro; só as extensões de
+/// `midia::EXTENSOES_COMPANHEIRAS`, dentro da pasta do pacote.
+fn destino_companheiro(origem: &Path, dest: &Path, c: &ArquivoCompanheiro) -> Option<(PathBuf, PathBuf)> {
+    let extensao = Path::new(&c.caminho)
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(str::to_ascii_lowercase)
+        .filter(|e| midia::EXTENSOES_COMPANHEIRAS.contains(&e.as_str()))?;
+    Some((caminho_video(origem, &c.caminho)?, dest.with_extension(extensao)))
+}
+
+/// Copia um vídeo (e os `companheiros` já aceitos) do pacote e indexa.
+fn copiar(origem: &Path, fonte: &Path, dest: &Path, m: &MusicaPacote, companheiros: &[ArquivoCompanheiro]) -> Result<(), String> {
+    let copias = companheiros
+        .iter()
+        .map(|c| {
+            destino_companheiro(origem, dest, c)
+                .map(|(fonte, alvo)| (fonte, alvo, c))
+                .ok_or_else(|| format!("companheiro inválido no manifesto ({})", c.caminho))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let integridade = Integridade { tamanho: Some(m.tamanho), sha256: Some(m.sha256.clone()) };
+    let copiado = copiar_conferido(fonte, dest, &integridade)?;
+    // Companheiros antes de indexar: o tipo da mídia depende deles
+    let mut copiados = Vec::new();
+    for (fonte, alvo, c) in copias {
+        let integridade = Integridade { tamanho: None, sha256: Some(c.sha256.clone()) };
+        if let Err(e) = copiar_conferido(&fonte, &alvo, &integridade) {
+            for arquivo in copiados.iter().chain([&dest.to_path_buf()]) {
+                std::fs::remove_file(arquivo).ok();
+            }
+            return Err(e);
+        }
+        copiados.push(alvo);
+    }
+
+    let arquivo = dest.to_string_lossy().to_string();
+    let anterior = db::get_musica_local(&m.codigo)?.map(|l| l.arquivo).filter(|a| *a != arquivo);
+    db::insert_musica(&db::Musica {
+        id: m.id.clone(),
+        codigo: m.codigo.clone(),
+        artista: m.artista.clone(),
+        titulo: m.titulo.clone(),
+        arquivo,
+        nome_arquivo: m.nome_arquivo.clone(),
+        tamanho: Some(copiado.tamanho as i64),
+        duracao: m.duracao,
+        user_id: m.user_id.clone(),
+        arquivo_remoto: m.arquivo_remoto.clone(),
+        sha256: Some(copiado.sha256),
+    })?;
+    // Versão anterior em outro contêiner não fica órfã
+    if let Some(anterior) = anterior {
+        std::fs::remove_file(anterior).ok();
+    }
+    sondagem::avisar();
+    // Sem internet o catálogo local pode nem ter a música: o manifesto supre
+    if db::get_catalogo_by_codigo(&m.codigo)?.is_none() {
+        db::aplicar_catalogo(
+            &[db::MusicaCatalogo {
+                id: m.id.clone(),
+                codigo: m.codigo.clone(),
+                artista: m.artista.clone(),
+                titulo: m.titulo.clone(),
+                arquivo: m.arquivo_remoto.clone().unwrap_or_default(),
+                nome_arquivo: m.nome_arquivo.clone(),
+                tamanho: Some(m.tamanho as i64),
+                duracao: m.duracao,
+                user_id: m.user_id.clone(),
+                updated_at: m.updated_at.clone(),
+                created_at: m.created_at.clone(),
+                sha256: Some(m.sha256.to_ascii_lowercase()),
+                removida: false,
+            }],
+            None,
+        )?;
+    }
+    db::remover_da_fila(&m.codigo).ok();
+    db::remover_demanda(&m.codigo).ok();
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::teste;
+
+    /// Pacote em `dir` com `<codigo>.mp3` e o `.cdg` ao lado, assinado por
+    /// `chave`; `editar` mexe na entrada antes da assinatura.
+    fn montar(dir: &Path, codigo: &str, chave: &SigningKey, editar: impl FnOnce(&mut MusicaPacote)) -> MusicaPacote {
+        std::fs::create_dir_all(dir.join(PASTA_VIDEOS)).unwrap();
+        let video = format!("{}/{}.mp3", PASTA_VIDEOS, codigo);
+        std::fs::write(dir.join(&video), format!("audio {}", codigo)).unwrap();
+        let cdg = format!("{}/{}.cdg", PASTA_VIDEOS, codigo);
+        std::fs::write(dir.join(&cdg), format!("cdg {}", codigo)).unwrap();
+        let mut m = MusicaPacote {
+            id: format!("id-{}", codigo),
+            codigo: codigo.to_string(),
+            artista: "Artista".to_string(),
+            titulo: format!("Música {}", codigo),
+            arquivo_remoto: None,
+            nome_arquivo: None,
+            tamanho: std::fs::metadata(dir.join(&video)).unwrap().len(),
+            duracao: None,
+            user_id: None,
+            updated_at: None,
+            created_at: None,
+            sha256: download::sha256_arquivo(&dir.join(&video)).unwrap(),
+            companheiros: vec![ArquivoCompanheiro { sha256: download::sha256_arquivo(&dir.join(&cdg)).unwrap(), caminho: cdg }],
+            video,
+        };
+        editar(&mut m);
+        let manifesto = Manifesto {
+            formato: FORMATO,
+            versao_catalogo: None,
+            criado_em: 0,
+            origem: None,
+            chave_publica: hex::encode(chave.verifying_key().to_bytes()),
+            musicas: vec![m.clone()],
+        };
+        let bytes = serde_json::to_vec_pretty(&manifesto).unwrap();
+        std::fs::write(dir.join(ARQUIVO_MANIFESTO), &bytes).unwrap();
+        std::fs::write(dir.join(ARQUIVO_ASSINATURA), hex::encode(chave.sign(&bytes).to_bytes())).unwrap();
+        m
+    }
+
+    #[test]
+    fn companheiro_fora_das_extensoes_nao_entra() {
+        let dados = teste::dados();
+        let pacote = dados.join("pacote-91201");
+        montar(&pacote, "91201", &chave_privada().unwrap(), |m| {
+            let caminho = format!("{}/91201.exe", PASTA_VIDEOS);
+            std::fs::write(pacote.join(&caminho), b"MZ").unwrap();
+            m.companheiros[0] = ArquivoCompanheiro { sha256: download::sha256_arquivo(&pacote.join(&caminho)).unwrap(), caminho };
+        });
+
+        let r = importar(dados.to_str().unwrap(), &pacote, &|_| {}).unwrap();
+        assert!(r.confiavel);
+        assert!(r.importadas.is_empty());
+        assert!(r.erros.iter().any(|e| e.starts_with("91201") && e.contains("companheiro inválido")), "{:?}", r.erros);
+        let musicas = dados.join("musicas");
+        assert!(!musicas.join("91201.mp3").exists() && !musicas.join("91201.exe").exists());
+        assert!(db::get_musica_local("91201").unwrap().is_none());
+    }
+
+    #[test]
+    fn manifesto_alterado_depois_de_assinado_nao_importa() {
+        let dados = teste::dados();
+        let pacote = dados.join("pacote-91202");
+        montar(&pacote, "91202", &chave_privada().unwrap(), |_| {});
+        let manifesto = std::fs::read_to_string(pacote.join(ARQUIVO_MANIFESTO)).unwrap();
+        std::fs::write(pacote.join(ARQUIVO_MANIFESTO), manifesto.replace("91202.cdg", "91202.dll")).unwrap();
+
+        let erro = importar(dados.to_str().unwrap(), &pacote, &|_| {}).unwrap_err();
+        assert!(erro.contains("Assinatura não confere"), "{}", erro);
+        assert!(db::get_musica_local("91202").unwrap().is_none());
+    }
+
+    #[test]
+    fn assinante_nao_confiavel_so_traz_o_video_do_catalogo() {
+        let dados = teste::dados();
+        let pacote = dados.join("pacote-91203");
+        let estranho = SigningKey::from_bytes(&rand::random::<[u8; 32]>());
+        let m = montar(&pacote, "91203", &estranho, |_| {});
+        db::aplicar_catalogo(
+            &[db::MusicaCatalogo {
+                id: m.id.clone(),
+                codigo: m.codigo.clone(),
+                artista: m.artista.clone(),
+                titulo: m.titulo.clone(),
+                arquivo: "musicas/91203.mp3".to_string(),
+                nome_arquivo: None,
+                tamanho: Some(m.tamanho as i64),
+                duracao: None,
+                user_id: None,
+                updated_at: None,
+                created_at: None,
+                sha256: Some(m.sha256.clone()),
+                removida: false,
+            }],
+            None,
+        )
+        .unwrap();
+
+        let r = importar(dados.to_str().unwrap(), &pacote, &|_| {}).unwrap();
+        assert!(!r.confiavel);
+        assert_eq!(r.importadas, vec!["91203".to_string()]);
+        assert!(r.erros.iter().any(|e| e.starts_with("91203") && e.contains("companheiros ignorados")), "{:?}", r.erros);
+        let musicas = dados.join("musicas");
+        assert!(musicas.join("91203.mp3").is_file());
+        assert!(!musicas.join("91203.cdg").exists());
+    }
+}
@@ -0,0 +1,212 @@
+// Quarentena de músicas removidas do catálogo remoto.
+//
+// Quando uma música baixada some do catálogo (tombstone ou ausência numa
+// sincronização completa), o vídeo vai para `musicas/.quarentena/`, junto
+// com os companheiros (.cdg, letras), e sai de `musicas_local`. Se a música
+// reaparecer dentro do prazo, volta para a biblioteca sem novo download;
+// vencido o prazo (`quarentena_dias` em config_local), os arquivos são
+// apagados. Prazo 0 apaga na hora.
+//
+// Vídeos corrompidos encontrados pela conferência da biblioteca também vêm
+// para cá, mas só esperam o prazo: nunca são restaurados.
+
+use crate::db;
+use crate::midia;
+use crate::sondagem;
+use std::path::Path;
+
+const CHAVE_DIAS: &str = "quarentena_dias";
+const DIAS_PADRAO: i64 = 7;
+const DIA_MS: i64 = 24 * 60 * 60 * 1000;
+
+/// Motivo dos vídeos isolados pela conferência da biblioteca.
+pub const MOTIVO_CORROMPIDA: &str = "corrompida";
+
+pub fn dias() -> i64 {
+    db::get_config(CHAVE_DIAS)
+        .ok()
+        .flatten()
+        .and_then(|v| v.parse::<i64>().ok())
+        .filter(|d| *d >= 0)
+        .unwrap_or(DIAS_PADRAO)
+}
+
+pub fn set_dias(dias: i64) -> Result<(), String> {
+    if dias < 0 {
+        return Err("Prazo de quarentena não pode ser negativo".to_string());
+    }
+    db::set_config(CHAVE_DIAS, &dias.to_string())
+}
+
+/// Tira da biblioteca as músicas `codigos` que estão baixadas e não existem
+/// mais no catálogo. Retorna os códigos efetivamente removidos.
+pub fn isolar(data_dir: &str, codigos: &[String], motivo: &str) -> Vec<String> {
+    let mut isoladas = Vec::new();
+    for codigo in codigos {
+        // Código reaproveitado por uma música viva: não mexe
+        if matches!(db::get_catalogo_by_codigo(codigo), Ok(Some(_))) {
+            continue;
+        }
+        let musica = match db::get_musica_local(codigo) {
+            Ok(Some(m)) => m,
+            _ => continue,
+        };
+        if isolar_musica(data_dir, &musica, motivo) {
+            isoladas.push(codigo.clone());
+        }
+    }
+    isoladas
+}
+
+/// Tira da biblioteca um vídeo baixado que não confere (vazio, truncado).
+/// Fica na quarentena para diagnóstico, mas nunca é restaurado.
+pub fn isolar_corrompida(data_dir: &str, musica: &db::Musica) -> bool {
+    isolar_musica(data_dir, musica, MOTIVO_CORROMPIDA)
+}
+
+fn isolar_musica(data_dir: &str, musica: &db::Musica, motivo: &str) -> bool {
+    let dir = Path::new(data_dir).join("musicas").join(".quarentena");
+    let dias = dias();
+    let now = chrono::Utc::now().timestamp_millis();
+    let codigo = &musica.codigo;
+
+    let original = Path::new(&musica.arquivo);
+    if dias == 0 {
+        std::fs::remove_file(original).ok();
+        for companheiro in midia::companheiros(original) {
+            std::fs::remove_file(companheiro).ok();
+        }
+    } else {
+        if let Err(e) = std::fs::create_dir_all(&dir) {
+            log::error!("[QUARENTENA] {}: {}", dir.display(), e);
+            return false;
+        }
+        let nome = original.file_name().map(|n| n.to_owned()).unwrap_or_else(|| format!("{}.mp4", codigo).into());
+        let destino = dir.join(nome);
+        if original.exists() {
+            if let Err(e) = mover(original, &destino) {
+                log::error!("[QUARENTENA] {} não movida: {}", codigo, e);
+                return false;
+            }
+        }
+        let registro = db::MusicaQuarentena {
+            codigo: musica.codigo.clone(),
+            id: musica.id.clone(),
+            artista: musica.artista.clone(),
+            titulo: musica.titulo.clone(),
+            arquivo_original: musica.arquivo.clone(),
+            arquivo_quarentena: destino.to_string_lossy().to_string(),
+            nome_arquivo: musica.nome_arquivo.clone(),
+            tamanho: musica.tamanho,
+            duracao: musica.duracao,
+            user_id: musica.user_id.clone(),
+            arquivo_remoto: musica.arquivo_remoto.clone(),
+            sha256: musica.sha256.clone(),
+            motivo: motivo.to_string(),
+            quarentena_em: now,
+            expira_em: now + dias * DIA_MS,
+        };
+        if let Err(e) = db::inserir_quarentena(&registro) {
+            // Sem o registro o arquivo ficaria órfão: desfaz a movimentação
+            mover(&destino, original).ok();
+            log::error!("[QUARENTENA] {}: {}", codigo, e);
+            return false;
+        }
+    }
+
+    if let Err(e) = db::remover_musica_local(codigo) {
+        log::error!("[QUARENTENA] {}: {}", codigo, e);
+        return false;
+    }
+    log::info!("[QUARENTENA] {} removida da biblioteca ({})", codigo, motivo);
+    true
+}
+
+/// Move o arquivo e os companheiros (.cdg, letras), que seguem o nome dele.
+fn mover(origem: &Path, destino: &Path) -> std::io::Result<()> {
+    std::fs::rename(origem, destino)?;
+    for companheiro in midia::companheiros(origem) {
+        if let Some(ext) = companheiro.extension() {
+            std::fs::rename(&companheiro, destino.with_extension(ext))?;
+        }
+    }
+    Ok(())
+}
+
+/// Devolve à biblioteca as músicas em quarentena que voltaram ao catálogo
+/// com o mesmo id. Se o vídeo mudou nesse meio tempo, fica marcada para
+/// novo download. Retorna os códigos restaurados.
+pub fn restaurar_reaparecidas() -> Result<Vec<String>, String> {
+    let mut restauradas = Vec::new();
+    for q in db::listar_quarentena()? {
+        if q.motivo == MOTIVO_CORROMPIDA {
+            continue;
+        }
+        let remota = match db::get_catalogo_by_codigo(&q.codigo)? {
+            Some(m) if m.id == q.id => m,
+            _ => continue,
+        };
+        if db::musica_existe(&q.codigo)? {
+            continue;
+        }
+        if Path::new(&q.arquivo_quarentena).exists() {
+            if let Err(e) = mover(Path::new(&q.arquivo_quarentena), Path::new(&q.arquivo_original)) {
+                log::error!("[QUARENTENA] {} não restaurada: {}", q.codigo, e);
+                continue;
+            }
+        } else {
+            // Arquivo sumiu da quarentena: o download normal traz de volta
+            db::remover_quarentena(&q.codigo)?;
+            continue;
+        }
+        db::insert_musica(&db::Musica {
+            id: remota.id.clone(),
+            codigo: remota.codigo.clone(),
+            artista: remota.artista.clone(),
+            titulo: remota.titulo.clone(),
+            arquivo: q.arquivo_original.clone(),
+            nome_arquivo: remota.nome_arquivo.clone(),
+            tamanho: q.tamanho,
+            duracao: remota.duracao.or(q.duracao),
+            user_id: remota.user_id.clone(),
+            arquivo_remoto: q.arquivo_remoto.clone(),
+            sha256: q.sha256.clone(),
+        })?;
+        let mudou = q.arquivo_remoto.as_deref().is_some_and(|a| a != remota.arquivo)
+            || matches!((&q.sha256, &remota.sha256), (Some(a), Some(b)) if a != b);
+        if mudou {
+            db::marcar_precisa_atualizar(&q.codigo)?;
+        }
+        db::remover_quarentena(&q.codigo)?;
+        log::info!("[QUARENTENA] {} voltou ao catálogo e foi restaurada", q.codigo);
+        sondagem::avisar();
+        restauradas.push(q.codigo);
+    }
+    Ok(restauradas)
+}
+
+/// Apaga os arquivos com prazo vencido. Retorna quantos foram expurgados.
+pub fn expurgar_vencidas() -> Result<i64, String> {
+    let now = chrono::Utc::now().timestamp_millis();
+    let mut expurgadas = 0;
+    for q in db::listar_quarentena()? {
+        if q.expira_em > now {
+            continue;
+        }
+        if let Err(e) = std::fs::remove_file(&q.arquivo_quarentena) {
+            if e.kind() != std::io::ErrorKind::NotFound {
+                log::warn!("[QUARENTENA] {} não apagada: {}", q.arquivo_quarentena, e);
+                continue;
+            }
+        }
+        for companheiro in midia::companheiros(Path::new(&q.arquivo_quarentena)) {
+            std::fs::remove_file(companheiro).ok();
+        }
+        db::remover_quarentena(&q.codigo)?;
+        expurgadas += 1;
+    }
+    if expurgadas > 0 {
+        log::info!("[QUARENTENA] {} músicas expurgadas", expurgadas);
+    }
+    Ok(expurgadas)
+}
@@ -0,0 +1,666 @@
+// Sincronização entre máquinas da mesma casa pela rede local.
+//
+// Casas com várias salas baixavam os mesmos gigabytes uma vez por máquina.
+// Aqui cada máquina:
+//
+//   - anuncia-se por broadcast UDP (PORTA_DESCOBERTA) a cada INTERVALO_ANUNCIO
+//     e escuta os anúncios das outras
+//   - serve a própria biblioteca num servidor HTTP mínimo (porta livre,
+//     informada no anúncio): GET /biblioteca (código + SHA-256 do vídeo e
+//     dos companheiros), GET /video/<codigo> e GET /companheiro/<codigo>.<ext>
+//   - antes de baixar do Supabase, procura um par que tenha o vídeo com o
+//     mesmo hash do catálogo e copia dele o vídeo e os companheiros (.cdg,
+//     letras), com a mesma conferência de um download (parcial, tamanho,
+//     SHA-256)
+//
+// Só conversam máquinas ativadas na mesma conta: a validação online da chave
+// traz o segredo da rede local da conta (entregue só a chaves vinculadas à
+// própria máquina, guardado em `rede_local_segredo`). Dele saem, por HMAC, o
+// identificador de grupo do anúncio e a chave que assina toda requisição
+// HTTP, com carimbo de tempo e nonce para não ser reaproveitada.
+
+use crate::backend::{ArquivoBaixado, BackendError, Integridade, LicenseBackend, OnProgresso};
+use crate::db;
+use crate::download::{self, ArquivoParcial};
+use crate::midia;
+use hmac::{Hmac, Mac};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::collections::HashMap;
+use std::net::{Ipv4Addr, SocketAddr};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream, UdpSocket};
+
+const PORTA_DESCOBERTA: u16 = 47811;
+const INTERVALO_ANUNCIO: Duration = Duration::from_secs(30);
+/// Par que não se anuncia há mais que isso é esquecido.
+const VALIDADE_PAR: Duration = Duration::from_secs(95);
+/// Idade máxima da lista de músicas de um par antes de pedir de novo.
+const VALIDADE_BIBLIOTECA: Duration = Duration::from_secs(120);
+/// Tolerância do carimbo de tempo das requisições (relógios desacertados).
+const JANELA_ASSINATURA_MS: i64 = 5 * 60 * 1000;
+/// Tempo para o cliente mandar a linha de requisição e os cabeçalhos.
+const LIMITE_CABECALHOS: Duration = Duration::from_secs(10);
+/// Maior linha aceita na requisição (bytes).
+const LIMITE_LINHA: u64 = 4 * 1024;
+
+const CHAVE_ATIVA: &str = "rede_local_ativa";
+const CHAVE_SEGREDO: &str = "rede_local_segredo";
+/// Onde versões anteriores guardavam o user_id da chave.
+const CHAVE_CONTA_ANTIGA: &str = "conta_id";
+const CABECALHO_ASSINATURA: &str = "x-blue-karaoke-auth";
+const APP: &str = "blue-karaoke";
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Anuncio {
+    app: String,
+    versao: u32,
+    grupo: String,
+    machine_id: String,
+    nome: String,
+    porta: u16,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct ItemBiblioteca {
+    codigo: String,
+    sha256: String,
+    /// `.cdg` e letras ao lado do vídeo (versões antigas não mandam)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    companheiros: Vec<CompanheiroBiblioteca>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CompanheiroBiblioteca {
+    extensao: String,
+    sha256: String,
+}
+
+struct Par {
+    nome: String,
+    endereco: SocketAddr,
+    visto_em: Instant,
+    /// codigo → item, com os hashes em minúsculas
+    biblioteca: HashMap<String, ItemBiblioteca>,
+    biblioteca_em: Option<Instant>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ParStatus {
+    #[serde(rename = "machineId")]
+    pub machine_id: String,
+    pub nome: String,
+    pub endereco: String,
+    pub musicas: usize,
+    /// Segundos desde o último anúncio
+    #[serde(rename = "vistoHa")]
+    pub visto_ha: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct StatusRedeLocal {
+    pub ativa: bool,
+    /// false enquanto a máquina não foi ativada online com uma chave de conta
+    #[serde(rename = "contaVinculada")]
+    pub conta_vinculada: bool,
+    /// Porta do servidor HTTP local; None se não subiu
+    pub porta: Option<u16>,
+    pub pares: Vec<ParStatus>,
+}
+
+static PARES: Lazy<Mutex<HashMap<String, Par>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+static PORTA_HTTP: OnceLock<u16> = OnceLock::new();
+/// Nonces já aceitos → carimbo; saem quando o carimbo passa da janela.
+static NONCES: Lazy<Mutex<HashMap<String, i64>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+static CLIENTE: Lazy<reqwest::Client> = Lazy::new(|| {
+    reqwest::Client::builder()
+        .connect_timeout(Duration::from_secs(2))
+        .read_timeout(Duration::from_secs(15))
+        .build()
+        .unwrap_or_default()
+});
+
+// ─── Configuração e conta ───────────────────────────────────────────────────
+
+pub fn ativa() -> bool {
+    db::get_config(CHAVE_ATIVA).ok().flatten().is_none_or(|v| v != "0")
+}
+
+pub fn set_ativa(ativa: bool) -> Result<(), String> {
+    db::set_config(CHAVE_ATIVA, if ativa { "1" } else { "0" })?;
+    if !ativa {
+        PARES.lock().unwrap().clear();
+    }
+    Ok(())
+}
+
+/// Busca o segredo da conta depois de validar `chave` online. Sem resposta do
+/// servidor fica o que já estava guardado; chave recusada esquece o segredo.
+pub async fn atualizar_segredo(licenca: &dyn LicenseBackend, chave: &str, machine_id: Option<&str>) {
+    let Some(machine_id) = machine_id else { return };
+    match licenca.segredo_rede_local(chave, machine_id).await {
+        Ok(segredo) => definir_segredo(segredo.as_deref()),
+        Err(e) => log::warn!("[REDE LOCAL] Segredo da conta não obtido: {}", e),
+    }
+}
+
+/// Guarda o segredo da conta (ou esquece, com None).
+pub fn definir_segredo(segredo: Option<&str>) {
+    let resultado = match segredo.map(str::trim).filter(|s| !s.is_empty()) {
+        Some(s) => db::set_config(CHAVE_SEGREDO, s),
+        None => db::remover_config(CHAVE_SEGREDO),
+    };
+    if let Err(e) = resultado {
+        log::error!("[REDE LOCAL] Segredo não gravado: {}", e);
+    }
+    db::remover_config(CHAVE_CONTA_ANTIGA).ok();
+    PARES.lock().unwrap().clear();
+}
+
+fn segredo() -> Option<String> {
+    db::get_config(CHAVE_SEGREDO).ok().flatten()
+}
+
+fn derivar(rotulo: &str, segredo: &str) -> [u8; 32] {
+    let mut mac = Hmac::<Sha256>::new_from_slice(segredo.as_bytes()).expect("HMAC aceita qualquer chave");
+    mac.update(format!("{}/lan/{}", APP, rotulo).as_bytes());
+    mac.finalize().into_bytes().into()
+}
+
+fn grupo(segredo: &str) -> String {
+    hex::encode(&derivar("grupo", segredo)[..8])
+}
+
+fn mac_requisicao(segredo: &str, carimbo: i64, nonce: &str, caminho: &str) -> Hmac<Sha256> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(&derivar("assinatura", segredo)).expect("HMAC aceita qualquer chave");
+    mac.update(format!("{}\n{}\n{}", carimbo, nonce, caminho).as_bytes());
+    mac
+}
+
+/// `carimbo.nonce.assinatura`
+fn cabecalho_assinatura(segredo: &str, caminho: &str) -> String {
+    let carimbo = chrono::Utc::now().timestamp_millis();
+    let nonce = uuid::Uuid::new_v4().simple().to_string();
+    let assinatura = hex::encode(mac_requisicao(segredo, carimbo, &nonce, caminho).finalize().into_bytes());
+    format!("{}.{}.{}", carimbo, nonce, assinatura)
+}
+
+/// Confere a assinatura, o carimbo e se o nonce ainda não foi usado dentro
+/// da janela (uma requisição capturada não é aceita de novo).
+fn assinatura_valida(segredo: &str, cabecalho: &str, caminho: &str) -> bool {
+    let mut partes = cabecalho.splitn(3, '.');
+    let (Some(carimbo), Some(nonce), Some(recebida)) = (partes.next(), partes.next(), partes.next()) else {
+        return false;
+    };
+    let Ok(carimbo) = carimbo.parse::<i64>() else {
+        return false;
+    };
+    let agora = chrono::Utc::now().timestamp_millis();
+    if (agora - carimbo).abs() > JANELA_ASSINATURA_MS || nonce.is_empty() || nonce.len() > 64 {
+        return false;
+    }
+    let Ok(recebida) = hex::decode(recebida) else {
+        return false;
+    };
+    if mac_requisicao(segredo, carimbo, nonce, caminho).verify_slice(&recebida).is_err() {
+        return false;
+    }
+    let mut nonces = NONCES.lock().unwrap();
+    nonces.retain(|_, c| (agora - *c).abs() <= JANELA_ASSINATURA_MS);
+    nonces.insert(nonce.to_string(), carimbo).is_none()
+}
+
+pub fn status() -> StatusRedeLocal {
+    let mut pares: Vec<ParStatus> = PARES
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|(id, p)| ParStatus {
+            machine_id: id.clone(),
+            nome: p.nome.clone(),
+            endereco: p.endereco.to_string(),
+            musicas: p.biblioteca.len(),
+            visto_ha: p.visto_em.elapsed().as_secs(),
+        })
+        .collect();
+    pares.sort_by(|a, b| a.nome.cmp(&b.nome));
+    StatusRedeLocal {
+        ativa: ativa(),
+        conta_vinculada: segredo().is_some(),
+        porta: PORTA_HTTP.get().copied(),
+        pares,
+    }
+}
+
+// ─── Inicialização ──────────────────────────────────────────────────────────
+
+/// Sobe o servidor HTTP e a descoberta. Com o modo desligado ou sem conta,
+/// as tarefas ficam rodando mas não anunciam nem atendem.
+pub fn iniciar() {
+    tauri::async_runtime::spawn(async {
+        let listener = match TcpListener::bind((Ipv4Addr::UNSPECIFIED, 0)).await {
+            Ok(l) => l,
+            Err(e) => {
+                log::warn!("[REDE LOCAL] Servidor não iniciado: {}", e);
+                return;
+            }
+        };
+        let porta = listener.local_addr().map(|a| a.port()).unwrap_or(0);
+        PORTA_HTTP.set(porta).ok();
+        log::info!("[REDE LOCAL] Servindo a biblioteca na porta {}", porta);
+        tauri::async_runtime::spawn(descoberta(porta));
+        loop {
+            match listener.accept().await {
+                Ok((stream, origem)) => {
+                    tauri::async_runtime::spawn(async move {
+                        if let Err(e) = atender(stream).await {
+                            log::debug!("[REDE LOCAL] {}: {}", origem, e);
+                        }
+                    });
+                }
+                Err(e) => log::warn!("[REDE LOCAL] accept: {}", e),
+            }
+        }
+    });
+}
+
+// ─── Descoberta ─────────────────────────────────────────────────────────────
+
+async fn descoberta(porta_http: u16) {
+    let socket = match UdpSocket::bind((Ipv4Addr::UNSPECIFIED, PORTA_DESCOBERTA)).await {
+        Ok(s) => s,
+        Err(e) => {
+            log::warn!("[REDE LOCAL] Descoberta indisponível (porta {}): {}", PORTA_DESCOBERTA, e);
+            return;
+        }
+    };
+    socket.set_broadcast(true).ok();
+    let machine_id = db::get_or_create_machine_id().unwrap_or_default();
+    let nome = hostname::get().map(|h| h.to_string_lossy().to_string()).unwrap_or_else(|_| machine_id.clone());
+    let mut anunciar = tokio::time::interval(INTERVALO_ANUNCIO);
+    let mut buf = [0u8; 2048];
+
+    loop {
+        tokio::select! {
+            _ = anunciar.tick() => {
+                PARES.lock().unwrap().retain(|_, p| p.visto_em.elapsed() < VALIDADE_PAR);
+                let Some(segredo) = segredo().filter(|_| ativa()) else { continue };
+                let anuncio = Anuncio {
+                    app: APP.to_string(),
+                    versao: 1,
+                    grupo: grupo(&segredo),
+                    machine_id: machine_id.clone(),
+                    nome: nome.clone(),
+                    porta: porta_http,
+                };
+                if let Ok(bytes) = serde_json::to_vec(&anuncio) {
+                    if let Err(e) = socket.send_to(&bytes, (Ipv4Addr::BROADCAST, PORTA_DESCOBERTA)).await {
+                        log::debug!("[REDE LOCAL] Anúncio não enviado: {}", e);
+                    }
+                }
+            }
+            recebido = socket.recv_from(&mut buf) => {
+                let Ok((n, origem)) = recebido else { continue };
+                let Ok(anuncio) = serde_json::from_slice::<Anuncio>(&buf[..n]) else { continue };
+                let Some(segredo) = segredo().filter(|_| ativa()) else { continue };
+                if anuncio.app != APP || anuncio.machine_id == machine_id || anuncio.grupo != grupo(&segredo) {
+                    continue;
+                }
+                registrar_par(anuncio, SocketAddr::new(origem.ip(), 0));
+            }
+        }
+    }
+}
+
+fn registrar_par(anuncio: Anuncio, origem: SocketAddr) {
+    let endereco = SocketAddr::new(origem.ip(), anuncio.porta);
+    let desatualizada = {
+        let mut pares = PARES.lock().unwrap();
+        let par = pares.entry(anuncio.machine_id.clone()).or_insert_with(|| {
+            log::info!("[REDE LOCAL] Par encontrado: {} ({})", anuncio.nome, endereco);
+            Par { nome: anuncio.nome.clone(), endereco, visto_em: Instant::now(), biblioteca: HashMap::new(), biblioteca_em: None }
+        });
+        par.nome = anuncio.nome;
+        par.endereco = endereco;
+        par.visto_em = Instant::now();
+        par.biblioteca_em.is_none_or(|t| t.elapsed() >= VALIDADE_BIBLIOTECA)
+    };
+    if desatualizada {
+        tauri::async_runtime::spawn(atualizar_biblioteca(anuncio.machine_id, endereco));
+    }
+}
+
+async fn atualizar_biblioteca(machine_id: String, endereco: SocketAddr) {
+    // Marca antes de pedir para não disparar pedidos repetidos
+    if let Some(p) = PARES.lock().unwrap().get_mut(&machine_id) {
+        p.biblioteca_em = Some(Instant::now());
+    }
+    let Some(segredo) = segredo() else { return };
+    let caminho = "/biblioteca";
+    let resposta = CLIENTE
+        .get(format!("http://{}{}", endereco, caminho))
+        .header(CABECALHO_ASSINATURA, cabecalho_assinatura(&segredo, caminho))
+        .send()
+        .await
+        .and_then(|r| r.error_for_status());
+    let itens: Vec<ItemBiblioteca> = match resposta {
+        Ok(r) => match r.json().await {
+            Ok(itens) => itens,
+            Err(e) => {
+                log::warn!("[REDE LOCAL] Biblioteca de {} inválida: {}", endereco, e);
+                return;
+            }
+        },
+        Err(e) => {
+            log::warn!("[REDE LOCAL] Biblioteca de {} indisponível: {}", endereco, e);
+            return;
+        }
+    };
+    if let Some(p) = PARES.lock().unwrap().get_mut(&machine_id) {
+        p.biblioteca = itens.into_iter().map(|i| (i.codigo.clone(), normalizar(i))).collect();
+        log::info!("[REDE LOCAL] {} tem {} músicas", p.nome, p.biblioteca.len());
+    }
+}
+
+/// Hashes em minúsculas; companheiros de extensão desconhecida ficam de fora.
+fn normalizar(mut item: ItemBiblioteca) -> ItemBiblioteca {
+    item.sha256.make_ascii_lowercase();
+    item.companheiros.retain_mut(|c| {
+        c.extensao.make_ascii_lowercase();
+        c.sha256.make_ascii_lowercase();
+        midia::EXTENSOES_COMPANHEIRAS.contains(&c.extensao.as_str())
+    });
+    item
+}
+
+// ─── Servidor ───────────────────────────────────────────────────────────────
+
+async fn atender(stream: TcpStream) -> std::io::Result<()> {
+    let mut leitor = BufReader::new(stream);
+    let (metodo, caminho, assinatura_recebida) = tokio::time::timeout(LIMITE_CABECALHOS, ler_cabecalhos(&mut leitor))
+        .await
+        .map_err(|_| std::io::Error::new(std::io::ErrorKind::TimedOut, "cabeçalhos não recebidos a tempo"))??;
+    let mut stream = leitor.into_inner();
+
+    let autorizado = match (segredo().filter(|_| ativa()), &assinatura_recebida) {
+        (Some(segredo), Some(a)) => assinatura_valida(&segredo, a, &caminho),
+        _ => false,
+    };
+    if !autorizado {
+        return responder(&mut stream, "403 Forbidden", "text/plain", b"forbidden").await;
+    }
+    if metodo != "GET" {
+        return responder(&mut stream, "405 Method Not Allowed", "text/plain", b"").await;
+    }
+
+    if caminho == "/biblioteca" {
+        let itens: Vec<ItemBiblioteca> = tauri::async_runtime::spawn_blocking(|| {
+            db::listar_musicas_local()
+                .unwrap_or_default()
+                .into_iter()
+                .filter(|m| Path::new(&m.arquivo).exists())
+                .filter_map(|m| {
+                    let companheiros = midia::companheiros(Path::new(&m.arquivo))
+                        .iter()
+                        .filter_map(|c| {
+                            Some(CompanheiroBiblioteca {
+                                extensao: c.extension()?.to_str()?.to_ascii_lowercase(),
+                                sha256: download::sha256_arquivo(c).ok()?,
+                            })
+                        })
+                        .collect();
+                    Some(ItemBiblioteca { sha256: m.sha256?, codigo: m.codigo, companheiros })
+                })
+                .collect()
+        })
+        .await
+        .unwrap_or_default();
+        let corpo = serde_json::to_vec(&itens).unwrap_or_default();
+        return responder(&mut stream, "200 OK", "application/json", &corpo).await;
+    }
+
+    let arquivo = if let Some(codigo) = caminho.strip_prefix("/video/") {
+        arquivo_local(codigo).map(PathBuf::from)
+    } else if let Some((codigo, extensao)) = caminho.strip_prefix("/companheiro/").and_then(|c| c.rsplit_once('.')) {
+        arquivo_local(codigo)
+            .filter(|_| midia::EXTENSOES_COMPANHEIRAS.contains(&extensao))
+            .and_then(|a| midia::companheiro(Path::new(&a), extensao))
+    } else {
+        None
+    };
+    let Some(arquivo) = arquivo else {
+        return responder(&mut stream, "404 Not Found", "text/plain", b"").await;
+    };
+    let mut file = match tokio::fs::File::open(&arquivo).await {
+        Ok(f) => f,
+        Err(_) => return responder(&mut stream, "404 Not Found", "text/plain", b"").await,
+    };
+    let tamanho = file.metadata().await?.len();
+    let cabecalho = format!(
+        "HTTP/1.1 200 OK\r\nContent-Type: application/octet-stream\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        tamanho
+    );
+    stream.write_all(cabecalho.as_bytes()).await?;
+    tokio::io::copy(&mut file, &mut stream).await?;
+    stream.shutdown().await
+}
+
+/// Arquivo da música `codigo` na biblioteca (só códigos bem formados).
+fn arquivo_local(codigo: &str) -> Option<String> {
+    codigo
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        .then(|| db::get_musica_local(codigo).ok().flatten())
+        .flatten()
+        .map(|m| m.arquivo)
+}
+
+/// Linha de requisição e cabeçalhos: (método, caminho, assinatura).
+async fn ler_cabecalhos(leitor: &mut BufReader<TcpStream>) -> std::io::Result<(String, String, Option<String>)> {
+    let linha = ler_linha(leitor).await?;
+    let mut partes = linha.split_whitespace();
+    let (metodo, caminho) = (partes.next().unwrap_or("").to_string(), partes.next().unwrap_or("").to_string());
+
+    let mut assinatura_recebida = None;
+    for _ in 0..64 {
+        let cabecalho = ler_linha(leitor).await?;
+        if cabecalho.trim().is_empty() {
+            break;
+        }
+        if let Some((nome, valor)) = cabecalho.split_once(':') {
+            if nome.trim().eq_ignore_ascii_case(CABECALHO_ASSINATURA) {
+                assinatura_recebida = Some(valor.trim().to_string());
+            }
+        }
+    }
+    Ok((metodo, caminho, assinatura_recebida))
+}
+
+/// Uma linha de até LIMITE_LINHA bytes; mais que isso encerra a conexão.
+async fn ler_linha(leitor: &mut BufReader<TcpStream>) -> std::io::Result<String> {
+    let mut linha = String::new();
+    let lidos = (&mut *leitor).take(LIMITE_LINHA).read_line(&mut linha).await?;
+    if lidos as u64 == LIMITE_LINHA && !linha.ends_with('\n') {
+        return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, "linha longa demais"));
+    }
+    Ok(linha)
+}
+
+async fn responder(stream: &mut TcpStream, status: &str, tipo: &str, corpo: &[u8]) -> std::io::Result<()> {
+    let cabecalho = format!(
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        status,
+        tipo,
+        corpo.len()
+    );
+    stream.write_all(cabecalho.as_bytes()).await?;
+    stream.write_all(corpo).await?;
+    stream.shutdown().await
+}
+
+// ─── Cliente ────────────────────────────────────────────────────────────────
+
+/// Tenta copiar `codigo` (com os companheiros) de um par que tenha o vídeo
+/// com hash `sha256`. None se nenhum par tiver ou todos falharem (aí vale o
+/// Supabase).
+pub async fn baixar_de_par(
+    codigo: &str,
+    dest: &Path,
+    integridade: &Integridade,
+    progresso: OnProgresso<'_>,
+) -> Option<ArquivoBaixado> {
+    let sha256 = integridade.sha256.as_deref()?.to_ascii_lowercase();
+    let segredo = segredo().filter(|_| ativa())?;
+    let candidatos: Vec<(String, SocketAddr, Vec<CompanheiroBiblioteca>)> = PARES
+        .lock()
+        .unwrap()
+        .values()
+        .filter_map(|p| {
+            let item = p.biblioteca.get(codigo).filter(|i| i.sha256 == sha256)?;
+            Some((p.nome.clone(), p.endereco, item.companheiros.clone()))
+        })
+        .collect();
+
+    for (nome, endereco, companheiros) in candidatos {
+        match baixar_de(&segredo, endereco, codigo, dest, integridade, &companheiros, progresso).await {
+            Ok(baixado) => {
+                log::info!("[REDE LOCAL] {} copiada de {} ({} bytes)", codigo, nome, baixado.tamanho);
+                return Some(baixado);
+            }
+            Err(e) => log::warn!("[REDE LOCAL] {} não copiada de {}: {}", codigo, nome, e),
+        }
+    }
+    None
+}
+
+/// Recebe tudo antes de concluir: o vídeo só ganha o nome final com os
+/// companheiros conferidos ao lado.
+async fn baixar_de(
+    segredo: &str,
+    endereco: SocketAddr,
+    codigo: &str,
+    dest: &Path,
+    integridade: &Integridade,
+    companheiros: &[CompanheiroBiblioteca],
+    progresso: OnProgresso<'_>,
+) -> Result<ArquivoBaixado, BackendError> {
+    let video = receber(segredo, endereco, &format!("/video/{}", codigo), dest, progresso).await?;
+    let mut recebidos = Vec::new();
+    for c in companheiros {
+        let caminho = format!("/companheiro/{}.{}", codigo, c.extensao);
+        match receber(segredo, endereco, &caminho, &dest.with_extension(&c.extensao), &|_, _| {}).await {
+            Ok(parcial) => recebidos.push((parcial, c)),
+            Err(e) => {
+                video.descartar();
+                for (parcial, _) in recebidos {
+                    parcial.descartar();
+                }
+                return Err(e);
+            }
+        }
+    }
+
+    let mut concluidos = Vec::new();
+    let mut falha = None;
+    for (parcial, c) in recebidos {
+        if falha.is_some() {
+            parcial.descartar();
+            continue;
+        }
+        match parcial.concluir(&Integridade { tamanho: None, sha256: Some(c.sha256.clone()) }) {
+            Ok(_) => concluidos.push(dest.with_extension(&c.extensao)),
+            Err(e) => falha = Some(e),
+        }
+    }
+    let resultado = match falha {
+        None => video.concluir(integridade),
+        Some(e) => {
+            video.descartar();
+            Err(e)
+        }
+    };
+    if resultado.is_err() {
+        for c in &concluidos {
+            std::fs::remove_file(c).ok();
+        }
+    }
+    resultado
+}
+
+/// Recebe `caminho` do par num parcial só da rede local (o `.part` do
+/// download do Supabase pode estar esperando para ser retomado).
+async fn receber(
+    segredo: &str,
+    endereco: SocketAddr,
+    caminho: &str,
+    alvo: &Path,
+    progresso: OnProgresso<'_>,
+) -> Result<ArquivoParcial, BackendError> {
+    let mut resposta = CLIENTE
+        .get(format!("http://{}{}", endereco, caminho))
+        .header(CABECALHO_ASSINATURA, cabecalho_assinatura(segredo, caminho))
+        .send()
+        .await
+        .and_then(|r| r.error_for_status())
+        .map_err(|e| BackendError::Rede(e.to_string()))?;
+    let total = resposta.content_length();
+    let mut parcial = ArquivoParcial::criar_separado(alvo, "lan")?;
+    loop {
+        let chunk = match resposta.chunk().await {
+            Ok(Some(chunk)) => chunk,
+            Ok(None) => break,
+            Err(e) => {
+                parcial.descartar();
+                return Err(BackendError::Rede(e.to_string()));
+            }
+        };
+        if let Err(e) = parcial.gravar(&chunk) {
+            parcial.descartar();
+            return Err(e);
+        }
+        progresso(parcial.gravados(), total);
+    }
+    Ok(parcial)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn assinatura_vale_uma_vez_e_so_com_o_mesmo_segredo() {
+        let cabecalho = cabecalho_assinatura("segredo-a", "/biblioteca");
+        assert!(!assinatura_valida("segredo-b", &cabecalho, "/biblioteca"));
+        assert!(!assinatura_valida("segredo-a", &cabecalho, "/video/123"));
+        assert!(assinatura_valida("segredo-a", &cabecalho, "/biblioteca"));
+        // Repetida dentro da janela: recusada
+        assert!(!assinatura_valida("segredo-a", &cabecalho, "/biblioteca"));
+    }
+
+    #[test]
+    fn grupo_depende_do_segredo() {
+        assert_eq!(grupo("segredo-a"), grupo("segredo-a"));
+        assert_ne!(grupo("segredo-a"), grupo("segredo-b"));
+        assert_eq!(grupo("segredo-a").len(), 16);
+    }
+
+    #[test]
+    fn biblioteca_do_par_so_traz_companheiros_conhecidos() {
+        // Versão antiga: sem companheiros
+        let antigo: ItemBiblioteca = serde_json::from_str(r#"{"codigo":"123","sha256":"AB"}"#).unwrap();
+        assert!(normalizar(antigo).companheiros.is_empty());
+
+        let item: ItemBiblioteca = serde_json::from_str(
+            r#"{"codigo":"123","sha256":"AB","companheiros":[{"extensao":"CDG","sha256":"CD"},{"extensao":"exe","sha256":"EF"}]}"#,
+        )
+        .unwrap();
+        let item = normalizar(item);
+        assert_eq!(item.sha256, "ab");
+        assert_eq!(item.companheiros.len(), 1);
+        assert_eq!((item.companheiros[0].extensao.as_str(), item.companheiros[0].sha256.as_str()), ("cdg", "cd"));
+    }
+}
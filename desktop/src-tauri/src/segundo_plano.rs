@@ -0,0 +1,53 @@
+// Threads de segundo plano de prioridade baixa (sondagem, miniaturas).
+//
+// Cada tarefa roda na partida, de `intervalo` em `intervalo` e sempre que
+// alguém chama `avisar`. O trabalho pesado espera a música acabar
+// (`esperar_reproducao`): nada de disputar CPU e disco com o player.
+
+use crate::commands::player;
+use std::sync::{Condvar, Mutex};
+use std::time::Duration;
+
+/// De quanto em quanto tempo se confere se a música acabou.
+const ESPERA_REPRODUCAO: Duration = Duration::from_secs(5);
+
+pub struct Tarefa {
+    nome: &'static str,
+    pendente: Mutex<bool>,
+    aviso: Condvar,
+}
+
+impl Tarefa {
+    pub const fn new(nome: &'static str) -> Self {
+        Self {
+            nome,
+            pendente: Mutex::new(false),
+            aviso: Condvar::new(),
+        }
+    }
+
+    /// Acorda a thread antes do próximo intervalo.
+    pub fn avisar(&self) {
+        *self.pendente.lock().unwrap() = true;
+        self.aviso.notify_one();
+    }
+
+    pub fn iniciar<F: FnMut() + Send + 'static>(&'static self, intervalo: Duration, mut trabalho: F) {
+        std::thread::spawn(move || {
+            log::info!("[{}] Thread iniciada", self.nome);
+            loop {
+                trabalho();
+                let guard = self.pendente.lock().unwrap();
+                let (mut guard, _) = self.aviso.wait_timeout_while(guard, intervalo, |p| !*p).unwrap();
+                *guard = false;
+            }
+        });
+    }
+}
+
+/// Bloqueia enquanto há música tocando.
+pub fn esperar_reproducao() {
+    while player::tocando() {
+        std::thread::sleep(ESPERA_REPRODUCAO);
+    }
+}
@@ -0,0 +1,326 @@
+// Sondagem das mídias da biblioteca: duração, resolução, codecs, canais de
+// áudio e loudness (EBU R128) de cada arquivo, gravados em `musicas_local`.
+//
+// Usa o ffprobe/ffmpeg quando acompanham o app; sem eles, o mpv (que o
+// player já exige) faz as duas coisas com `--term-playing-msg` e o filtro
+// ebur128 do lavfi. Roda numa thread própria, uma música por vez e parada
+// enquanto há música tocando: downloads, reindexação e importação só avisam
+// (`avisar`), e na partida ela pega o que ainda não foi sondado (bibliotecas
+// antigas). Medir o loudness decodifica o áudio inteiro, por isso não fica
+// no caminho do download.
+
+use crate::cdg;
+use crate::db;
+use crate::ferramentas;
+use crate::midia::{self, TipoMidia};
+use crate::miniaturas;
+use crate::segundo_plano::{self, Tarefa};
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Sem aviso, procura pendentes de tempos em tempos mesmo assim.
+const INTERVALO_VARREDURA: Duration = Duration::from_secs(10 * 60);
+/// Limite dos programas: cabeçalhos são rápidos, o loudness lê tudo.
+const LIMITE_INFO: Duration = Duration::from_secs(30);
+const LIMITE_LOUDNESS: Duration = Duration::from_secs(5 * 60);
+const LOTE: i64 = 20;
+/// Prefixo da linha que o mpv imprime com as propriedades.
+const MARCA_MPV: &str = "BK_SONDA|";
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct InfoMidia {
+    /// Segundos
+    pub duracao: Option<f64>,
+    pub largura: Option<i64>,
+    pub altura: Option<i64>,
+    #[serde(rename = "codecVideo")]
+    pub codec_video: Option<String>,
+    #[serde(rename = "codecAudio")]
+    pub codec_audio: Option<String>,
+    #[serde(rename = "canaisAudio")]
+    pub canais_audio: Option<i64>,
+    /// Loudness integrado (LUFS)
+    #[serde(rename = "loudnessLufs")]
+    pub loudness_lufs: Option<f64>,
+    /// Pico verdadeiro (dBTP)
+    #[serde(rename = "picoDbtp")]
+    pub pico_dbtp: Option<f64>,
+}
+
+/// Por que uma música ficou sem sondagem.
+#[derive(Debug)]
+pub enum Falha {
+    /// Programa ausente, tempo esgotado ou arquivo sumido: tenta de novo depois
+    Transitoria(String),
+    /// O arquivo não abre; fica marcado como sondado, sem dados
+    Arquivo(String),
+}
+
+static TAREFA: Tarefa = Tarefa::new("SONDAGEM");
+/// Códigos com falha transitória nesta execução; ficam para a próxima.
+static ADIADAS: Lazy<Mutex<HashSet<String>>> = Lazy::new(|| Mutex::new(HashSet::new()));
+
+/// Acorda a thread de sondagem (depois de indexar uma música).
+pub fn avisar() {
+    TAREFA.avisar();
+}
+
+pub fn iniciar() {
+    TAREFA.iniciar(INTERVALO_VARREDURA, sondar_pendentes);
+}
+
+fn sondar_pendentes() {
+    // Sem nenhum programa não há o que fazer; tenta de novo na próxima volta
+    if ferramentas::localizar("ffprobe").is_none() && ferramentas::localizar("mpv").is_none() {
+        return;
+    }
+    let mut sondadas = 0;
+    loop {
+        // As adiadas continuam sem sondada_em: o lote cresce para passar delas
+        let adiadas = ADIADAS.lock().unwrap().len() as i64;
+        let pendentes: Vec<(String, String)> = match db::musicas_sem_sondagem(LOTE + adiadas) {
+            Ok(p) => p.into_iter().filter(|(c, _)| !ADIADAS.lock().unwrap().contains(c)).collect(),
+            Err(e) => {
+                log::warn!("[SONDAGEM] {}", e);
+                return;
+            }
+        };
+        if pendentes.is_empty() {
+            break;
+        }
+        for (codigo, arquivo) in pendentes {
+            // Prioridade baixa: nada de decodificar áudio com música tocando
+            segundo_plano::esperar_reproducao();
+            let info = match sondar(Path::new(&arquivo)) {
+                Ok(info) => info,
+                Err(Falha::Transitoria(e)) => {
+                    log::warn!("[SONDAGEM] {} (tenta de novo depois): {}", codigo, e);
+                    ADIADAS.lock().unwrap().insert(codigo);
+                    continue;
+                }
+                // Gravada mesmo assim (tudo nulo), para não insistir no mesmo arquivo
+                Err(Falha::Arquivo(e)) => {
+                    log::warn!("[SONDAGEM] {}: {}", codigo, e);
+                    InfoMidia::default()
+                }
+            };
+            if let Err(e) = db::salvar_sondagem(&codigo, &arquivo, &info) {
+                log::warn!("[SONDAGEM] {}: {}", codigo, e);
+                return;
+            }
+            sondadas += 1;
+        }
+    }
+    // Com a duração medida, a miniatura sai do ponto certo do vídeo
+    if sondadas > 0 {
+        miniaturas::avisar();
+    }
+}
+
+/// Sonda o arquivo: cabeçalhos e loudness.
+pub fn sondar(path: &Path) -> Result<InfoMidia, Falha> {
+    if !path.is_file() {
+        return Err(Falha::Transitoria(format!("arquivo não encontrado: {}", path.display())));
+    }
+    let mut info = match ferramentas::localizar("ffprobe") {
+        Some(ffprobe) => info_ffprobe(&ffprobe, path)?,
+        None => {
+            let mpv = ferramentas::localizar("mpv").ok_or(Falha::Transitoria("nem ffprobe nem mpv disponíveis".into()))?;
+            info_mpv(&mpv, path)?
+        }
+    };
+    // MP3+G: a imagem é o .cdg, de tamanho fixo
+    if midia::identificar(path).map(|m| m.tipo) == Some(TipoMidia::Cdg) {
+        info.largura = Some(cdg::LARGURA as i64);
+        info.altura = Some(cdg::ALTURA as i64);
+        info.codec_video = Some("cdg".into());
+    }
+    if info.codec_audio.is_some() {
+        // Sem a medida o loudness nunca mais seria tentado: a falha é transitória
+        let (lufs, pico) = loudness(path).map_err(|e| Falha::Transitoria(format!("loudness: {}", e)))?;
+        info.loudness_lufs = lufs;
+        info.pico_dbtp = pico;
+    }
+    Ok(info)
+}
+
+fn info_ffprobe(ffprobe: &Path, path: &Path) -> Result<InfoMidia, Falha> {
+    let mut cmd = ferramentas::comando(ffprobe);
+    cmd.args(["-v", "error", "-print_format", "json", "-show_format", "-show_streams"]).arg(path);
+    let saida = ferramentas::executar(cmd, LIMITE_INFO).map_err(|e| Falha::Transitoria(format!("ffprobe: {}", e)))?;
+    if !saida.status.success() {
+        return Err(Falha::Arquivo(format!("ffprobe: {}", String::from_utf8_lossy(&saida.stderr).trim())));
+    }
+    let json: serde_json::Value =
+        serde_json::from_slice(&saida.stdout).map_err(|e| Falha::Arquivo(format!("ffprobe: {}", e)))?;
+    Ok(parse_ffprobe(&json))
+}
+
+fn parse_ffprobe(json: &serde_json::Value) -> InfoMidia {
+    let num = |v: &serde_json::Value| v.as_f64().or_else(|| v.as_str()?.parse().ok());
+    let streams = json["streams"].as_array().map(Vec::as_slice).unwrap_or_default();
+    // Capa embutida no MP3/M4A aparece como vídeo; não conta
+    let video = streams
+        .iter()
+        .find(|s| s["codec_type"] == "video" && s["disposition"]["attached_pic"].as_i64() != Some(1));
+    let audio = streams.iter().find(|s| s["codec_type"] == "audio");
+    InfoMidia {
+        duracao: num(&json["format"]["duration"]).filter(|d| *d > 0.0),
+        largura: video.and_then(|v| v["width"].as_i64()),
+        altura: video.and_then(|v| v["height"].as_i64()),
+        codec_video: video.and_then(|v| v["codec_name"].as_str()).map(str::to_string),
+        codec_audio: audio.and_then(|a| a["codec_name"].as_str()).map(str::to_string),
+        canais_audio: audio.and_then(|a| a["channels"].as_i64()),
+        ..Default::default()
+    }
+}
+
+fn info_mpv(mpv: &Path, path: &Path) -> Result<InfoMidia, Falha> {
+    let mut cmd = ferramentas::comando(mpv);
+    // Sem relógio e com um segundo só: sai logo depois de abrir (só áudio
+    // tocaria a música inteira em tempo real)
+    cmd.args([
+        "--no-config",
+        "--vo=null",
+        "--ao=null",
+        "--ao-null-untimed",
+        "--frames=1",
+        "--length=1",
+        "--msg-level=all=no,cplayer=info",
+        &format!(
+            "--term-playing-msg={}${{=duration:}}|${{current-tracks/video/demux-w:}}|${{current-tracks/video/demux-h:}}|${{video-format:}}|${{audio-codec-name:}}|${{audio-params/channel-count:}}",
+            MARCA_MPV
+        ),
+    ])
+    .arg(path);
+    let saida = ferramentas::executar(cmd, LIMITE_INFO).map_err(|e| Falha::Transitoria(format!("mpv: {}", e)))?;
+    let mut texto = String::from_utf8_lossy(&saida.stdout).to_string();
+    texto.push_str(&String::from_utf8_lossy(&saida.stderr));
+    texto
+        .lines()
+        .find_map(|l| l.trim().strip_prefix(MARCA_MPV))
+        .map(parse_mpv)
+        .ok_or_else(|| Falha::Arquivo("mpv não abriu o arquivo".to_string()))
+}
+
+fn parse_mpv(linha: &str) -> InfoMidia {
+    let campos: Vec<&str> = linha.split('|').map(str::trim).collect();
+    let campo = |i: usize| campos.get(i).copied().filter(|c| !c.is_empty());
+    InfoMidia {
+        duracao: campo(0).and_then(|c| c.parse().ok()).filter(|d: &f64| *d > 0.0),
+        largura: campo(1).and_then(|c| c.parse().ok()),
+        altura: campo(2).and_then(|c| c.parse().ok()),
+        codec_video: campo(3).map(str::to_string),
+        codec_audio: campo(4).map(str::to_string),
+        canais_audio: campo(5).and_then(|c| c.parse().ok()),
+        ..Default::default()
+    }
+}
+
+/// Loudness integrado e pico verdadeiro do áudio, pelo filtro ebur128.
+fn loudness(path: &Path) -> Result<(Option<f64>, Option<f64>), String> {
+    // As medidas por quadro vão para o nível verbose; só o resumo sai
+    const FILTRO: &str = "ebur128=framelog=verbose:peak=true";
+    let saida = if let Some(ffmpeg) = ferramentas::localizar("ffmpeg") {
+        let mut cmd = ferramentas::comando(&ffmpeg);
+        cmd.args(["-hide_banner", "-nostats", "-i"])
+            .arg(path)
+            .args(["-vn", "-sn", "-af", FILTRO, "-f", "null", "-"]);
+        ferramentas::executar(cmd, LIMITE_LOUDNESS)?
+    } else {
+        let mpv = ferramentas::localizar("mpv").ok_or("nem ffmpeg nem mpv disponíveis")?;
+        let mut cmd = ferramentas::comando(&mpv);
+        // O resumo do ebur128 (nível info do ffmpeg) só aparece no verbose do mpv
+        cmd.args([
+            "--no-config",
+            "--vid=no",
+            "--sid=no",
+            "--ao=null",
+            "--ao-null-untimed",
+            "--msg-level=all=warn,ffmpeg=v",
+            &format!("--af=lavfi=[{}]", FILTRO),
+        ])
+        .arg(path);
+        ferramentas::executar(cmd, LIMITE_LOUDNESS)?
+    };
+    let mut texto = String::from_utf8_lossy(&saida.stderr).to_string();
+    texto.push_str(&String::from_utf8_lossy(&saida.stdout));
+    Ok(parse_ebur128(&texto))
+}
+
+/// Lê `I: -14.2 LUFS` e `Peak: -0.3 dBFS` do resumo do ebur128 (o último,
+/// se houver mais de um).
+fn parse_ebur128(texto: &str) -> (Option<f64>, Option<f64>) {
+    let valor = |rotulo: &str, unidade: &str| {
+        texto
+            .lines()
+            .rev()
+            .filter_map(|l| {
+                let resto = l.split_once(rotulo)?.1.trim();
+                resto.strip_suffix(unidade)?.trim().parse::<f64>().ok()
+            })
+            .find(|v| v.is_finite())
+    };
+    (valor("I:", "LUFS"), valor("Peak:", "dBFS"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ebur128_no_ffmpeg() {
+        let saida = "\
+[Parsed_ebur128_0 @ 0x55d0c8c0e2c0] Summary:
+
+  Integrated loudness:
+    I:         -14.2 LUFS
+    Threshold: -24.6 LUFS
+
+  Loudness range:
+    LRA:         6.3 LU
+    Threshold: -34.7 LUFS
+    LRA low:   -18.9 LUFS
+    LRA high:  -12.6 LUFS
+
+  True peak:
+    Peak:       -0.3 dBFS
+";
+        assert_eq!(parse_ebur128(saida), (Some(-14.2), Some(-0.3)));
+    }
+
+    #[test]
+    fn ebur128_no_mpv() {
+        // `--msg-level=all=warn,ffmpeg=v`: cada linha do resumo vem com o módulo
+        let saida = "\
+[ffmpeg] Parsed_ebur128_0: Summary:
+[ffmpeg] 
+[ffmpeg]   Integrated loudness:
+[ffmpeg]     I:         -19.8 LUFS
+[ffmpeg]     Threshold: -30.1 LUFS
+[ffmpeg] 
+[ffmpeg]   Loudness range:
+[ffmpeg]     LRA:         8.1 LU
+[ffmpeg]     Threshold: -40.3 LUFS
+[ffmpeg]     LRA low:   -25.2 LUFS
+[ffmpeg]     LRA high:  -17.1 LUFS
+[ffmpeg] 
+[ffmpeg]   True peak:
+[ffmpeg]     Peak:       -1.2 dBFS
+Exiting... (End of file)
+";
+        assert_eq!(parse_ebur128(saida), (Some(-19.8), Some(-1.2)));
+    }
+
+    #[test]
+    fn ebur128_sem_resumo() {
+        // Linha por quadro (framelog) não é o resumo
+        let quadro = "[ffmpeg] Parsed_ebur128_0: t: 0.4  TARGET:-23 LUFS    M: -21.3 S:-120.7     I: -21.3 LUFS       LRA:   0.0 LU";
+        assert_eq!(parse_ebur128(quadro), (None, None));
+        assert_eq!(parse_ebur128("Exiting... (Errors when loading file)"), (None, None));
+    }
+}
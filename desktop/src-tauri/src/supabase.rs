@@ -1,225 +1,472 @@
-use reqwest::Client;
-use serde::{Deserialize, Serialize};
-use std::sync::OnceLock;
+// Implementação PostgREST (Supabase) de `CatalogBackend` e `LicenseBackend`.
 
-static CLIENT: OnceLock<Client> = OnceLock::new();
+use crate::backend::{
+    ArquivoBaixado, AssinaturaRemota, BackendError, BoxFuture, CatalogBackend, ChaveRemota, Integridade,
+    ItemHistorico, LicenseBackend, MusicaRemota, OnPagina, OnProgresso, PaginaCatalogo, PopularidadeRemota,
+};
+use crate::db;
+use crate::download::{ArquivoParcial, LIMITE_BANDA};
+use crate::http::HttpClient;
+use reqwest::header;
+use reqwest::{RequestBuilder, Response, StatusCode};
+use std::path::Path;
+use std::sync::Arc;
 
-fn client() -> &'static Client {
-    CLIENT.get_or_init(|| Client::new())
+#[derive(Debug, Clone)]
+pub struct SupabaseConfig {
+    pub url: String,
+    pub key: String,
 }
 
-// Supabase config - loaded from env file or hardcoded for the karaoke app
-fn supabase_url() -> String {
-    std::env::var("SUPABASE_URL").unwrap_or_else(|_| {
-        std::env::var("NEXT_PUBLIC_SUPABASE_URL").unwrap_or_default()
-    })
+impl SupabaseConfig {
+    // Supabase config - loaded from env file or hardcoded for the karaoke app
+    pub fn from_env() -> Self {
+        let url = std::env::var("SUPABASE_URL").unwrap_or_else(|_| {
+            std::env::var("NEXT_PUBLIC_SUPABASE_URL").unwrap_or_default()
+        });
+        let key = std::env::var("SUPABASE_ANON_KEY")
+            .or_else(|_| std::env::var("NEXT_PUBLIC_SUPABASE_ANON_KEY"))
+            .or_else(|_| std::env::var("NEXT_PUBLIC_SUPABASE_PUBLISHABLE_DEFAULT_KEY"))
+            .unwrap_or_default();
+        Self { url: url.trim_end_matches('/').to_string(), key }
+    }
+
+    fn is_configured(&self) -> bool {
+        !self.url.is_empty() && !self.key.is_empty()
+    }
 }
 
-fn supabase_key() -> String {
-    std::env::var("SUPABASE_ANON_KEY")
-        .or_else(|_| std::env::var("NEXT_PUBLIC_SUPABASE_ANON_KEY"))
-        .or_else(|_| std::env::var("NEXT_PUBLIC_SUPABASE_PUBLISHABLE_DEFAULT_KEY"))
-        .unwrap_or_default()
-}
-
-#[derive(Debug, Serialize, Deserialize)]
-pub struct SupabaseMusica {
-    pub id: String,
-    pub codigo: String,
-    pub artista: String,
-    pub titulo: String,
-    pub arquivo: String,
-    pub nome_arquivo: Option<String>,
-    pub tamanho: Option<serde_json::Value>,
-    pub duracao: Option<serde_json::Value>,
-    pub user_id: Option<String>,
-    pub created_at: Option<String>,
-    pub updated_at: Option<String>,
-}
-
-#[derive(Debug, Serialize, Deserialize)]
-pub struct SupabaseChave {
-    pub id: String,
-    pub chave: String,
-    #[serde(default)]
-    pub tipo: String,
-    #[serde(default)]
-    pub status: String,
-    pub data_expiracao: Option<String>,
-    pub data_inicio: Option<String>,
-    pub limite_tempo: Option<f64>,
-    pub user_id: Option<serde_json::Value>,
-    pub ultimo_uso: Option<String>,
-    /// Identificador único da máquina que ativou esta chave.
-    /// None = ainda não ativada em nenhuma máquina.
-    pub machine_id: Option<String>,
-    // Accept any extra fields from Supabase without failing
-    #[serde(flatten)]
-    pub extra: std::collections::HashMap<String, serde_json::Value>,
-}
-
-#[derive(Debug, Serialize, Deserialize)]
-#[allow(dead_code)]
-pub struct SupabaseAssinatura {
-    pub id: String,
-    pub user_id: String,
-    pub status: String,
-    pub data_fim: Option<String>,
-}
-
-/// Fetch all musicas from Supabase
-pub async fn fetch_all_musicas() -> Result<Vec<SupabaseMusica>, String> {
-    let url = supabase_url();
-    let key = supabase_key();
-    log::info!("[SUPABASE] URL: {}, Key length: {}", url, key.len());
-    if url.is_empty() || key.is_empty() {
-        return Err("Supabase not configured".to_string());
-    }
-
-    let full_url = format!("{}/rest/v1/musicas?select=*", url);
-    log::info!("[SUPABASE] Fetching: {}", full_url);
-
-    let resp = client()
-        .get(&full_url)
-        .header("apikey", &key)
-        .header("Authorization", format!("Bearer {}", key))
-        .send()
-        .await
-        .map_err(|e| {
-            log::error!("[SUPABASE] Request error: {}", e);
-            e.to_string()
-        })?;
+pub struct PostgrestBackend {
+    config: SupabaseConfig,
+    http: Arc<HttpClient>,
+}
+
+impl PostgrestBackend {
+    pub fn new(config: SupabaseConfig, http: Arc<HttpClient>) -> Self {
+        log::info!("[SUPABASE] URL: {}, Key length: {}", config.url, config.key.len());
+        Self { config, http }
+    }
+
+    /// Monta uma requisição para `/rest/v1/<path>` com `apikey` e `Authorization`.
+    fn rest(&self, method: reqwest::Method, path: &str) -> Result<RequestBuilder, BackendError> {
+        if !self.config.is_configured() {
+            return Err(BackendError::NaoConfigurado);
+        }
+        Ok(self
+            .http
+            .client()
+            .request(method, format!("{}/rest/v1/{}", self.config.url, path))
+            .header("apikey", &self.config.key)
+            .header("Authorization", format!("Bearer {}", self.config.key)))
+    }
+
+    /// GET idempotente em `/rest/v1/<path>`, com retry e checagem de status.
+    async fn get(&self, path: &str) -> Result<Response, BackendError> {
+        let req = self.rest(reqwest::Method::GET, path)?;
+        check_status(self.http.enviar(req, true).await?).await
+    }
+
+    /// PATCH em uma linha de `chaves_ativacao` com `return=minimal`.
+    /// Reenviar o mesmo corpo não muda o resultado, então pode ser repetido.
+    async fn patch_chave(&self, chave_id: &str, body: serde_json::Value) -> Result<(), BackendError> {
+        let req = self
+            .rest(reqwest::Method::PATCH, &format!("chaves_ativacao?id=eq.{}", chave_id))?
+            .header("Content-Type", "application/json")
+            .header("Prefer", "return=minimal")
+            .json(&body);
+        check_status(self.http.enviar(req, true).await?).await?;
+        Ok(())
+    }
+}
 
+/// Converte respostas fora de 2xx em `BackendError::Http`.
+async fn check_status(resp: Response) -> Result<Response, BackendError> {
     let status = resp.status();
-    if !status.is_success() {
-        let body = resp.text().await.unwrap_or_default();
-        log::error!("[SUPABASE] Error {}: {}", status, body);
-        return Err(format!("Supabase error {}: {}", status, body));
-    }
-
-    let text = resp.text().await.map_err(|e| e.to_string())?;
-    log::info!("[SUPABASE] Response length: {} chars", text.len());
-    
-    serde_json::from_str::<Vec<SupabaseMusica>>(&text)
-        .map_err(|e| {
-            log::error!("[SUPABASE] Parse error: {}. First 200 chars: {}", e, &text[..text.len().min(200)]);
-            format!("Parse error: {}", e)
+    if status.is_success() {
+        return Ok(resp);
+    }
+    let corpo = resp.text().await.unwrap_or_default();
+    log::error!("[SUPABASE] Error {}: {}", status, corpo);
+    Err(BackendError::Http { status: status.as_u16(), corpo })
+}
+
+/// Linhas pedidas por página do catálogo.
+const PAGINA: i64 = 1000;
+
+/// Total de `Content-Range: 0-999/12345` (ou `*/0`); `*` = desconhecido.
+fn total_content_range(valor: &str) -> Option<i64> {
+    valor.rsplit('/').next()?.trim().parse().ok()
+}
+
+/// Início de `Content-Range: bytes 1000-4999/5000`.
+fn inicio_content_range(valor: &str) -> Option<u64> {
+    let faixa = valor.trim().strip_prefix("bytes")?.trim();
+    faixa.split('-').next()?.trim().parse().ok()
+}
+
+/// Parcial retomável de `dest`: existe, é da mesma URL e há um validador
+/// para `If-Range` (ETag forte ou Last-Modified). Retorna (offset, validador).
+fn retomada_possivel(url: &str, dest: &str) -> Option<(u64, String)> {
+    let estado = db::get_download_parcial(dest).ok()??;
+    if estado.url != url {
+        return None;
+    }
+    let baixados = std::fs::metadata(crate::download::caminho_parcial(Path::new(dest))).ok()?.len();
+    if baixados == 0 || estado.tamanho_total.is_some_and(|t| baixados >= t as u64) {
+        return None;
+    }
+    // ETag fraco (W/"...") não vale para If-Range
+    let validador = estado
+        .etag
+        .filter(|e| !e.starts_with("W/"))
+        .or(estado.last_modified)?;
+    Some((baixados, validador))
+}
+
+fn estado_download(url: &str, dest: &str, resp: &Response) -> db::DownloadParcial {
+    let cabecalho = |nome: header::HeaderName| {
+        resp.headers().get(nome).and_then(|v| v.to_str().ok()).map(str::to_string)
+    };
+    db::DownloadParcial {
+        destino: dest.to_string(),
+        url: url.to_string(),
+        etag: cabecalho(header::ETAG),
+        last_modified: cabecalho(header::LAST_MODIFIED),
+        tamanho_total: resp.content_length().map(|t| t as i64),
+        updated_at: chrono::Utc::now().timestamp_millis(),
+    }
+}
+
+/// Extrai os objetos de um array JSON à medida que os bytes chegam, sem
+/// esperar o corpo inteiro. Só entende arrays de objetos (o que o PostgREST
+/// devolve); bytes já consumidos são descartados do buffer.
+#[derive(Default)]
+struct JsonArrayStream {
+    buf: Vec<u8>,
+    /// Próximo byte de `buf` a examinar
+    pos: usize,
+    /// Início, em `buf`, do objeto sendo lido
+    inicio: Option<usize>,
+    profundidade: u32,
+    em_string: bool,
+    escape: bool,
+    abriu: bool,
+    fechou: bool,
+}
+
+impl JsonArrayStream {
+    fn push<T: serde::de::DeserializeOwned>(&mut self, chunk: &[u8], out: &mut Vec<T>) -> Result<(), BackendError> {
+        self.buf.extend_from_slice(chunk);
+        let mut i = self.pos;
+        while i < self.buf.len() && !self.fechou {
+            let b = self.buf[i];
+            if self.em_string {
+                if self.escape {
+                    self.escape = false;
+                } else if b == b'\\' {
+                    self.escape = true;
+                } else if b == b'"' {
+                    self.em_string = false;
+                }
+            } else {
+                match b {
+                    b'"' => self.em_string = true,
+                    b'[' if !self.abriu => self.abriu = true,
+                    b'{' | b'[' => {
+                        if self.profundidade == 0 {
+                            self.inicio = Some(i);
+                        }
+                        self.profundidade += 1;
+                    }
+                    b']' if self.profundidade == 0 => self.fechou = true,
+                    b'}' | b']' => {
+                        self.profundidade = self.profundidade.saturating_sub(1);
+                        if self.profundidade == 0 {
+                            if let Some(inicio) = self.inicio.take() {
+                                let item = serde_json::from_slice(&self.buf[inicio..=i]).map_err(|e| {
+                                    log::error!("[SUPABASE] Parse error: {}", e);
+                                    BackendError::Parse(e.to_string())
+                                })?;
+                                out.push(item);
+                            }
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            i += 1;
+        }
+        // Mantém no buffer só o objeto incompleto
+        let corte = self.inicio.unwrap_or(i);
+        self.buf.drain(..corte);
+        if let Some(inicio) = self.inicio.as_mut() {
+            *inicio -= corte;
+        }
+        self.pos = i - corte;
+        Ok(())
+    }
+
+    fn finalizar(&self) -> Result<(), BackendError> {
+        if self.fechou && self.inicio.is_none() {
+            Ok(())
+        } else {
+            Err(BackendError::Parse("resposta JSON incompleta".to_string()))
+        }
+    }
+}
+
+/// Percent-encoding de um valor de filtro (ex.: o `+` do fuso em timestamps).
+fn encode_param(valor: &str) -> String {
+    valor
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' | b':' => (b as char).to_string(),
+            _ => format!("%{:02X}", b),
         })
+        .collect()
+}
+
+async fn parse_json<T: serde::de::DeserializeOwned>(http: &HttpClient, resp: Response) -> Result<T, BackendError> {
+    let text = resp.text().await.map_err(|e| http.erro_rede(e))?;
+    serde_json::from_str::<T>(&text).map_err(|e| {
+        log::error!("[SUPABASE] Parse error: {}. First 200 chars: {}", e, text.chars().take(200).collect::<String>());
+        BackendError::Parse(e.to_string())
+    })
 }
 
-/// Validate activation key via Supabase
-pub async fn validar_chave_supabase(chave: &str) -> Result<Option<SupabaseChave>, String> {
-    let url = supabase_url();
-    let key = supabase_key();
-    if url.is_empty() || key.is_empty() {
-        return Err("Supabase not configured".to_string());
-    }
-
-    let resp = client()
-        .get(format!(
-            "{}/rest/v1/chaves_ativacao?chave=eq.{}&select=*",
-            url, chave
-        ))
-        .header("apikey", &key)
-        .header("Authorization", format!("Bearer {}", key))
-        .send()
-        .await
-        .map_err(|e| e.to_string())?;
-
-    if !resp.status().is_success() {
-        return Err(format!("Supabase error: {}", resp.status()));
-    }
-
-    let chaves: Vec<SupabaseChave> = resp.json().await.map_err(|e| e.to_string())?;
-    Ok(chaves.into_iter().next())
-}
-
-/// Check subscription status
-#[allow(dead_code)]
-pub async fn check_assinatura(user_id: &str) -> Result<Option<SupabaseAssinatura>, String> {
-    let url = supabase_url();
-    let key = supabase_key();
-
-    let resp = client()
-        .get(format!(
-            "{}/rest/v1/assinaturas?user_id=eq.{}&status=eq.ativa&select=*",
-            url, user_id
-        ))
-        .header("apikey", &key)
-        .header("Authorization", format!("Bearer {}", key))
-        .send()
-        .await
-        .map_err(|e| e.to_string())?;
-
-    let assinaturas: Vec<SupabaseAssinatura> = resp.json().await.map_err(|e| e.to_string())?;
-    Ok(assinaturas.into_iter().next())
-}
-
-/// Update last use timestamp for a key (optionally vincula machine_id na primeira vez)
-pub async fn update_ultimo_uso(chave_id: &str) -> Result<(), String> {
-    let url = supabase_url();
-    let key = supabase_key();
-
-    let now = chrono::Utc::now().to_rfc3339();
-    client()
-        .patch(format!("{}/rest/v1/chaves_ativacao?id=eq.{}", url, chave_id))
-        .header("apikey", &key)
-        .header("Authorization", format!("Bearer {}", key))
-        .header("Content-Type", "application/json")
-        .header("Prefer", "return=minimal")
-        .json(&serde_json::json!({ "ultimo_uso": now }))
-        .send()
-        .await
-        .map_err(|e| e.to_string())?;
-
-    Ok(())
-}
-
-/// Vincula o machine_id a uma chave (primeira ativação nesta máquina).
-/// Só deve ser chamado quando machine_id da chave for None.
-pub async fn vincular_machine_id(chave_id: &str, machine_id: &str) -> Result<(), String> {
-    let url = supabase_url();
-    let key = supabase_key();
-
-    let now = chrono::Utc::now().to_rfc3339();
-    let resp = client()
-        .patch(format!("{}/rest/v1/chaves_ativacao?id=eq.{}", url, chave_id))
-        .header("apikey", &key)
-        .header("Authorization", format!("Bearer {}", key))
-        .header("Content-Type", "application/json")
-        .header("Prefer", "return=minimal")
-        .json(&serde_json::json!({
-            "machine_id": machine_id,
-            "usado_em": now,
-            "ultimo_uso": now,
-        }))
-        .send()
-        .await
-        .map_err(|e| e.to_string())?;
-
-    if !resp.status().is_success() {
-        return Err(format!("Supabase error: {}", resp.status()));
-    }
-    log::info!("[SUPABASE] machine_id vinculado: {} → {}", chave_id, machine_id);
-    Ok(())
-}
-
-/// Download a file from URL
-pub async fn download_file(url: &str, dest: &str) -> Result<u64, String> {
-    let resp = client()
-        .get(url)
-        .send()
-        .await
-        .map_err(|e| e.to_string())?;
-
-    if !resp.status().is_success() {
-        return Err(format!("Download failed: {}", resp.status()));
-    }
-
-    let bytes = resp.bytes().await.map_err(|e| e.to_string())?;
-    let size = bytes.len() as u64;
-    std::fs::write(dest, &bytes).map_err(|e| e.to_string())?;
-    Ok(size)
+impl CatalogBackend for PostgrestBackend {
+    fn fetch_musicas<'a>(
+        &'a self,
+        alteradas_desde: Option<&'a str>,
+        on_pagina: OnPagina<'a>,
+    ) -> BoxFuture<'a, Result<i64, BackendError>> {
+        Box::pin(async move {
+            // Ordem estável (updated_at + id) para a paginação não pular nem repetir linhas
+            let mut path = "musicas?select=*&order=updated_at.asc.nullsfirst,id.asc".to_string();
+            if let Some(desde) = alteradas_desde {
+                path.push_str(&format!("&updated_at=gte.{}", encode_param(desde)));
+            }
+
+            let mut offset: i64 = 0;
+            let mut total: Option<i64> = None;
+            loop {
+                let fim = offset + PAGINA - 1;
+                let req = self
+                    .rest(reqwest::Method::GET, &path)?
+                    .header("Range-Unit", "items")
+                    .header("Range", format!("{}-{}", offset, fim))
+                    .header("Prefer", "count=exact");
+                let resp = self.http.enviar(req, true).await?;
+                // Offset além do fim (catálogo encolheu entre páginas)
+                if resp.status() == reqwest::StatusCode::RANGE_NOT_SATISFIABLE {
+                    break;
+                }
+                let mut resp = check_status(resp).await?;
+                if let Some(t) = resp
+                    .headers()
+                    .get(reqwest::header::CONTENT_RANGE)
+                    .and_then(|v| v.to_str().ok())
+                    .and_then(total_content_range)
+                {
+                    total = Some(t);
+                }
+
+                // Parse incremental: cada objeto é desserializado assim que chega
+                let mut parser = JsonArrayStream::default();
+                let mut musicas: Vec<MusicaRemota> = Vec::new();
+                while let Some(chunk) = resp.chunk().await.map_err(|e| self.http.erro_rede(e))? {
+                    parser.push(&chunk, &mut musicas)?;
+                }
+                parser.finalizar()?;
+
+                let recebidas_pagina = musicas.len() as i64;
+                offset += recebidas_pagina;
+                log::info!("[SUPABASE] Catálogo: {} de {:?} linhas", offset, total);
+                if recebidas_pagina > 0 {
+                    on_pagina(PaginaCatalogo { musicas, recebidas: offset, total })?;
+                }
+
+                // O servidor pode limitar abaixo de PAGINA (max-rows): só para quando
+                // atingir o total informado ou vier uma página vazia/incompleta sem total
+                let acabou = match total {
+                    Some(t) => offset >= t || recebidas_pagina == 0,
+                    None => recebidas_pagina < PAGINA,
+                };
+                if acabou {
+                    break;
+                }
+            }
+            Ok(offset)
+        })
+    }
+
+    fn download_file<'a>(
+        &'a self,
+        url: &'a str,
+        dest: &'a str,
+        integridade: &'a Integridade,
+        progresso: OnProgresso<'a>,
+    ) -> BoxFuture<'a, Result<ArquivoBaixado, BackendError>> {
+        Box::pin(async move {
+            let destino = Path::new(dest);
+            let mut retomada = retomada_possivel(url, dest);
+            loop {
+                let mut req = self.http.client().get(url);
+                if let Some((inicio, validador)) = &retomada {
+                    // If-Range: se o arquivo mudou no servidor, vem 200 com ele inteiro
+                    req = req
+                        .header(header::RANGE, format!("bytes={}-", inicio))
+                        .header(header::IF_RANGE, validador.as_str());
+                }
+                let resp = self.http.enviar(req, true).await?;
+                if resp.status() == StatusCode::RANGE_NOT_SATISFIABLE && retomada.is_some() {
+                    log::warn!("[DOWNLOAD] Parcial de {} não aceito pelo servidor; baixando do zero", dest);
+                    retomada = None;
+                    continue;
+                }
+                let mut resp = check_status(resp).await?;
+
+                let retomado = retomada.is_some();
+                let (mut arquivo, total) = match retomada.take() {
+                    Some((inicio, _)) if resp.status() == StatusCode::PARTIAL_CONTENT => {
+                        let faixa = resp
+                            .headers()
+                            .get(header::CONTENT_RANGE)
+                            .and_then(|v| v.to_str().ok())
+                            .unwrap_or("");
+                        if inicio_content_range(faixa) != Some(inicio) {
+                            log::warn!("[DOWNLOAD] Content-Range inesperado ({}); baixando do zero", faixa);
+                            continue;
+                        }
+                        log::info!("[DOWNLOAD] Retomando {} a partir de {} bytes", dest, inicio);
+                        (ArquivoParcial::retomar(destino)?, total_content_range(faixa))
+                    }
+                    _ => {
+                        // Download completo (primeira vez, servidor sem Range ou arquivo mudou)
+                        let estado = estado_download(url, dest, &resp);
+                        db::salvar_download_parcial(&estado).map_err(BackendError::Io)?;
+                        (ArquivoParcial::criar(destino)?, estado.tamanho_total)
+                    }
+                };
+
+                // Em blocos direto para o disco: o vídeo nunca fica inteiro na memória.
+                // Se a conexão cair, o parcial fica para a próxima tentativa.
+                while let Some(bloco) = resp.chunk().await.map_err(|e| self.http.erro_rede(e))? {
+                    LIMITE_BANDA.aguardar(bloco.len()).await;
+                    arquivo.gravar(&bloco)?;
+                    progresso(arquivo.gravados(), total.map(|t| t as u64));
+                }
+                if let Some(total) = total {
+                    if arquivo.gravados() != total as u64 {
+                        return Err(BackendError::Rede(format!(
+                            "Download incompleto: {} de {} bytes",
+                            arquivo.gravados(),
+                            total
+                        )));
+                    }
+                }
+                match arquivo.concluir(integridade) {
+                    Err(BackendError::Integridade(e)) => {
+                        db::remover_download_parcial(dest).ok();
+                        if retomado {
+                            // O trecho antigo pode ser de outra versão: tenta uma vez do zero
+                            log::warn!("[DOWNLOAD] {} retomado não confere ({}); baixando do zero", dest, e);
+                            continue;
+                        }
+                        return Err(BackendError::Integridade(e));
+                    }
+                    Err(e) => return Err(e),
+                    Ok(baixado) => {
+                        db::remover_download_parcial(dest).ok();
+                        return Ok(baixado);
+                    }
+                }
+            }
+        })
+    }
+
+    fn fetch_popularidade<'a>(&'a self, limite: i64) -> BoxFuture<'a, Result<Vec<PopularidadeRemota>, BackendError>> {
+        Box::pin(async move {
+            // Agregação no servidor (web/drizzle/0007_musicas_populares.sql); só leitura
+            let req = self
+                .rest(reqwest::Method::POST, "rpc/musicas_populares")?
+                .header("Content-Type", "application/json")
+                .json(&serde_json::json!({ "limite": limite }));
+            let resp = check_status(self.http.enviar(req, true).await?).await?;
+            parse_json(&self.http, resp).await
+        })
+    }
+}
+
+impl LicenseBackend for PostgrestBackend {
+    fn buscar_chave<'a>(&'a self, chave: &'a str) -> BoxFuture<'a, Result<Option<ChaveRemota>, BackendError>> {
+        Box::pin(async move {
+            let resp = self.get(&format!("chaves_ativacao?chave=eq.{}&select=*", chave)).await?;
+            let chaves: Vec<ChaveRemota> = parse_json(&self.http, resp).await?;
+            Ok(chaves.into_iter().next())
+        })
+    }
+
+    fn check_assinatura<'a>(&'a self, user_id: &'a str) -> BoxFuture<'a, Result<Option<AssinaturaRemota>, BackendError>> {
+        Box::pin(async move {
+            let resp = self.get(&format!("assinaturas?user_id=eq.{}&status=eq.ativa&select=*", user_id)).await?;
+            let assinaturas: Vec<AssinaturaRemota> = parse_json(&self.http, resp).await?;
+            Ok(assinaturas.into_iter().next())
+        })
+    }
+
+    fn update_ultimo_uso<'a>(&'a self, chave_id: &'a str) -> BoxFuture<'a, Result<(), BackendError>> {
+        Box::pin(async move {
+            let now = chrono::Utc::now().to_rfc3339();
+            self.patch_chave(chave_id, serde_json::json!({ "ultimo_uso": now })).await
+        })
+    }
+
+    fn vincular_machine_id<'a>(&'a self, chave_id: &'a str, machine_id: &'a str) -> BoxFuture<'a, Result<(), BackendError>> {
+        Box::pin(async move {
+            let now = chrono::Utc::now().to_rfc3339();
+            self.patch_chave(
+                chave_id,
+                serde_json::json!({
+                    "machine_id": machine_id,
+                    "usado_em": now,
+                    "ultimo_uso": now,
+                }),
+            )
+            .await?;
+            log::info!("[SUPABASE] machine_id vinculado: {} → {}", chave_id, machine_id);
+            Ok(())
+        })
+    }
+
+    fn segredo_rede_local<'a>(&'a self, chave: &'a str, machine_id: &'a str) -> BoxFuture<'a, Result<Option<String>, BackendError>> {
+        Box::pin(async move {
+            // Conferência da chave e do machine_id no servidor (web/drizzle/0008_segredo_rede_local.sql)
+            let req = self
+                .rest(reqwest::Method::POST, "rpc/segredo_rede_local")?
+                .header("Content-Type", "application/json")
+                .json(&serde_json::json!({ "p_chave": chave, "p_machine_id": machine_id }));
+            let resp = check_status(self.http.enviar(req, true).await?).await?;
+            parse_json(&self.http, resp).await
+        })
+    }
+
+    fn registrar_historico<'a>(
+        &'a self,
+        chave: &'a str,
+        machine_id: &'a str,
+        itens: &'a [ItemHistorico],
+    ) -> BoxFuture<'a, Result<Option<i64>, BackendError>> {
+        Box::pin(async move {
+            // Conferência da chave no servidor (web/drizzle/0009_historico_tom.sql)
+            let req = self
+                .rest(reqwest::Method::POST, "rpc/registrar_historico")?
+                .header("Content-Type", "application/json")
+                .json(&serde_json::json!({ "p_chave": chave, "p_machine_id": machine_id, "p_itens": itens }));
+            let resp = check_status(self.http.enviar(req, true).await?).await?;
+            parse_json(&self.http, resp).await
+        })
+    }
 }
 
 /// Load env from .env file in data dir
@@ -0,0 +1,22 @@
+// Apoio aos testes que usam o banco (global no app).
+
+use crate::db;
+use once_cell::sync::Lazy;
+use std::path::PathBuf;
+use std::sync::{Mutex, MutexGuard};
+
+/// Banco e pasta de dados de teste, um por execução.
+static DADOS: Lazy<Mutex<PathBuf>> = Lazy::new(|| {
+    let dir = std::env::temp_dir().join(format!("bk-teste-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    db::init_db(dir.to_str().unwrap()).unwrap();
+    // Disco de teste pode ter menos que a reserva padrão livre
+    db::set_config("disco_reserva_mb", "0").unwrap();
+    Mutex::new(dir)
+});
+
+/// Pasta de dados de teste. O banco é um só: quem o usa segura o guard até
+/// o fim do teste.
+pub fn dados() -> MutexGuard<'static, PathBuf> {
+    DADOS.lock().unwrap_or_else(|e| e.into_inner())
+}
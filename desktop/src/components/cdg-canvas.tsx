@@ -0,0 +1,78 @@
+import { useEffect, useRef, type RefObject } from "react"
+import { cdgQuadro } from "@/lib/tauri"
+
+// O .cdg muda a tela em pacotes de 1/300 s; os players CD+G mostram 25 quadros/s
+const QUADROS_POR_SEGUNDO = 25
+
+/**
+ * Gráficos CD+G desenhados sobre o <video> que toca o MP3 (fallback sem mpv).
+ * QUADROS_POR_SEGUNDO vezes por segundo pede ao Rust a tela do instante atual
+ * do áudio.
+ */
+export function CdgCanvas({ arquivo, mediaRef }: { arquivo: string; mediaRef: RefObject<HTMLMediaElement | null> }) {
+  const canvasRef = useRef<HTMLCanvasElement>(null)
+
+  useEffect(() => {
+    let ativo = true
+    let versao: number | undefined
+    let pedindo = false
+    let frame = 0
+    let ultimo = 0
+
+    const desenhar = async (agora: number) => {
+      const media = mediaRef.current
+      const canvas = canvasRef.current
+      if (media && canvas && !pedindo && agora - ultimo >= 1000 / QUADROS_POR_SEGUNDO) {
+        pedindo = true
+        ultimo = agora
+        try {
+          const quadro = await cdgQuadro(arquivo, media.currentTime * 1000, versao)
+          const ctx = canvas.getContext("2d")
+          if (ativo && quadro && ctx) {
+            versao = quadro.versao
+            canvas.width = quadro.largura
+            canvas.height = quadro.altura
+            const cores = quadro.paleta.map((c) => [
+              parseInt(c.slice(1, 3), 16),
+              parseInt(c.slice(3, 5), 16),
+              parseInt(c.slice(5, 7), 16),
+            ])
+            const bytes = Uint8Array.from(atob(quadro.pixels), (ch) => ch.charCodeAt(0))
+            const imagem = ctx.createImageData(quadro.largura, quadro.altura)
+            for (let i = 0; i < bytes.length; i++) {
+              const par = [bytes[i] >> 4, bytes[i] & 0x0f]
+              for (let j = 0; j < 2; j++) {
+                const [r, g, b] = cores[par[j]]
+                const o = (i * 2 + j) * 4
+                imagem.data[o] = r
+                imagem.data[o + 1] = g
+                imagem.data[o + 2] = b
+                imagem.data[o + 3] = 255
+              }
+            }
+            ctx.putImageData(imagem, 0, 0)
+          }
+        } catch (err) {
+          console.error("[CdgCanvas] Quadro não carregado:", err)
+        } finally {
+          pedindo = false
+        }
+      }
+      if (ativo) frame = requestAnimationFrame(desenhar)
+    }
+    frame = requestAnimationFrame(desenhar)
+
+    return () => {
+      ativo = false
+      cancelAnimationFrame(frame)
+    }
+  }, [arquivo, mediaRef])
+
+  return (
+    <canvas
+      ref={canvasRef}
+      className="absolute inset-0 w-full h-full bg-black pointer-events-none"
+      style={{ imageRendering: "pixelated" }}
+    />
+  )
+}
@@ -0,0 +1,61 @@
+import { useEffect, useState, type RefObject } from "react"
+import { getLetra, type Letra } from "@/lib/tauri"
+
+/**
+ * Letra sincronizada (.lrc/.ass) sobre o <video> que toca o áudio (fallback
+ * sem mpv). Mostra a linha atual, com a palavra cantada destacada, e a próxima.
+ */
+export function LetraOverlay({ arquivo, mediaRef }: { arquivo: string; mediaRef: RefObject<HTMLMediaElement | null> }) {
+  const [letra, setLetra] = useState<Letra | null>(null)
+  const [posicaoMs, setPosicaoMs] = useState(0)
+
+  useEffect(() => {
+    let ativo = true
+    getLetra(arquivo)
+      .then((l) => {
+        if (ativo) setLetra(l)
+      })
+      .catch((err) => console.error("[LetraOverlay] Letra não carregada:", err))
+    return () => {
+      ativo = false
+    }
+  }, [arquivo])
+
+  useEffect(() => {
+    let frame = 0
+    const atualizar = () => {
+      const media = mediaRef.current
+      if (media) setPosicaoMs(media.currentTime * 1000)
+      frame = requestAnimationFrame(atualizar)
+    }
+    frame = requestAnimationFrame(atualizar)
+    return () => cancelAnimationFrame(frame)
+  }, [mediaRef])
+
+  if (!letra || letra.linhas.length === 0) return null
+
+  const indice = letra.linhas.findIndex((l) => posicaoMs >= l.inicioMs && posicaoMs < l.fimMs)
+  const atual = indice >= 0 ? letra.linhas[indice] : null
+  const proxima = letra.linhas[indice >= 0 ? indice + 1 : letra.linhas.findIndex((l) => l.inicioMs > posicaoMs)]
+
+  return (
+    <div className="absolute inset-0 flex flex-col items-center justify-end pb-24 px-8 pointer-events-none text-center">
+      {!atual && (letra.titulo || letra.artista) && posicaoMs < letra.linhas[0].inicioMs && (
+        <p className="text-3xl text-white/80 mb-8">
+          {[letra.titulo, letra.artista].filter(Boolean).join(" — ")}
+        </p>
+      )}
+      <p className="text-5xl font-bold text-white drop-shadow-lg min-h-[1.2em]">
+        {atual &&
+          (atual.palavras.length > 0
+            ? atual.palavras.map((p, i) => (
+                <span key={i} className={posicaoMs >= p.inicioMs ? "text-yellow-400" : undefined}>
+                  {p.texto}
+                </span>
+              ))
+            : atual.texto)}
+      </p>
+      {proxima && <p className="text-3xl text-white/60 mt-4 min-h-[1.2em]">{proxima.texto}</p>}
+    </div>
+  )
+}
@@ -11,7 +11,8 @@ import { createPortal } from "react-dom"
 import { Input } from "@/components/ui/input"
 import { Search, Music, Loader2, Hash } from "lucide-react"
 import { useNavigate } from "react-router-dom"
-import { buscarMusicas, getMusicaByCodigo, type MusicaSimple } from "@/lib/tauri"
+import { convertFileSrc } from "@tauri-apps/api/core"
+import { buscarMusicas, getMiniatura, getMusicaByCodigo, type MusicaSimple } from "@/lib/tauri"
 
 export interface UnifiedSearchProps {
   onSelectCodigo?: (codigo: string, info?: { titulo: string; artista?: string }) => void
@@ -302,7 +303,7 @@ export function UnifiedSearch({ onSelectCodigo, value, onChange, autoFocus, tipo
               }`}
             >
               <div className="flex items-center gap-3">
-                <Music className={`flex-shrink-0 h-5 w-5 ${index === selectedIndex ? "text-cyan-600" : "text-stone-500"}`} />
+                <Miniatura codigo={musica.codigo} selecionada={index === selectedIndex} />
                 <div className="flex-1 min-w-0">
                   <p className="font-medium text-lg text-stone-900 truncate">{musica.titulo}</p>
                   <p className="text-base text-stone-600 truncate">{musica.artista}</p>
@@ -322,3 +323,26 @@ export function UnifiedSearch({ onSelectCodigo, value, onChange, autoFocus, tipo
     </div>
   )
 }
+
+/** Quadro do vídeo no resultado; ícone enquanto carrega, se ainda não foi gerado ou se não houver (só áudio). */
+function Miniatura({ codigo, selecionada }: { codigo: string; selecionada: boolean }) {
+  const [src, setSrc] = useState<string | null>(null)
+
+  useEffect(() => {
+    let ativo = true
+    setSrc(null)
+    getMiniatura(codigo)
+      .then((caminho) => {
+        if (ativo && caminho) setSrc(convertFileSrc(caminho.replace(/\\/g, "/")))
+      })
+      .catch(() => {})
+    return () => {
+      ativo = false
+    }
+  }, [codigo])
+
+  if (!src) {
+    return <Music className={`flex-shrink-0 h-5 w-5 ${selecionada ? "text-cyan-600" : "text-stone-500"}`} />
+  }
+  return <img src={src} alt="" className="flex-shrink-0 h-10 w-[4.5rem] rounded object-cover bg-black" />
+}
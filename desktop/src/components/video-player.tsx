@@ -2,9 +2,11 @@ import { useNavigate } from "react-router-dom"
 import { useEffect, useState, useCallback, useRef } from "react"
 import { toast } from "sonner"
 import { useFilaProxima } from "@/contexts/fila-proxima"
-import { useAtivacao } from "@/hooks/use-ativacao"
+import { useAtivacao } from "@/contexts/ativacao"
 import { ConfiguracoesDialog } from "@/components/configuracoes-dialog"
 import { UnifiedSearch } from "@/components/unified-search"
+import { CdgCanvas } from "@/components/cdg-canvas"
+import { LetraOverlay } from "@/components/letra-overlay"
 import { Settings } from "lucide-react"
 import {
   salvarHistorico,
@@ -13,6 +15,10 @@ import {
   nativePlayerAvailable,
   playNative,
   stopNative,
+  reproducaoAtiva,
+  alternarPausaNative,
+  reiniciarNative,
+  definirTomNative,
   nativePlayerEnded,
   type MusicaSimple,
 } from "@/lib/tauri"
@@ -51,6 +57,8 @@ export default function VideoPlayer({ musica }: { musica: MusicaSimple }) {
   const videoRef = useRef<HTMLVideoElement>(null)
   const containerRef = useRef<HTMLDivElement>(null)
   const pollIntervalRef = useRef<ReturnType<typeof setInterval> | null>(null)
+  // Tom atual em semitons (cada música começa no original)
+  const tomRef = useRef(0)
   // Evita múltiplas navegações por pressões rápidas de tecla (C / aleatória)
   const isChangingSongRef = useRef(false)
 
@@ -147,9 +155,11 @@ export default function VideoPlayer({ musica }: { musica: MusicaSimple }) {
     }
   }, [useNativePlayer, handleVideoEnd])
 
-  // --- Cleanup ao desmontar: matar mpv e restaurar janela ---
+  // --- Reprodução em andamento; ao desmontar: matar mpv e restaurar janela ---
   useEffect(() => {
+    reproducaoAtiva(true).catch(() => {})
     return () => {
+      reproducaoAtiva(false).catch(() => {})
       if (pollIntervalRef.current) clearInterval(pollIntervalRef.current)
       if (useNativePlayer) stopNative().catch(() => {})
     }
@@ -266,11 +276,15 @@ export default function VideoPlayer({ musica }: { musica: MusicaSimple }) {
         e.preventDefault()
         setVideoError(null)
         if (useNativePlayer && rawPath) {
-          stopNative().then(() => {
-            nativeStartedRef.current = false
-            hasFinishedRef.current = false
-            if (rawPath) playNative(rawPath).catch(() => {})
-          }).catch(() => {})
+          // Pelo IPC; se o mpv não responder, abre de novo
+          reiniciarNative().catch(() => {
+            stopNative().then(() => {
+              nativeStartedRef.current = false
+              hasFinishedRef.current = false
+              tomRef.current = 0
+              if (rawPath) playNative(rawPath).catch(() => {})
+            }).catch(() => {})
+          })
         } else {
           const video = videoRef.current
           if (video) { video.currentTime = 0; video.play().catch(() => {}) }
@@ -302,10 +316,31 @@ export default function VideoPlayer({ musica }: { musica: MusicaSimple }) {
         return
       }
 
+      // Subir / descer o tom (só no mpv)
+      const subir = matchKey(e, getKey("tom_subir"))
+      if (subir || matchKey(e, getKey("tom_descer"))) {
+        e.preventDefault()
+        if (!useNativePlayer) {
+          toast.info("Mudar o tom exige o player nativo (mpv).", { id: "tom" })
+          return
+        }
+        const novo = Math.max(-6, Math.min(6, tomRef.current + (subir ? 1 : -1)))
+        if (novo === tomRef.current) return
+        definirTomNative(novo)
+          .then((tom) => {
+            tomRef.current = tom
+            toast.info(tom === 0 ? "Tom original" : `Tom ${tom > 0 ? "+" : ""}${tom}`, { id: "tom" })
+          })
+          .catch((err) => toast.error(`Não foi possível mudar o tom: ${err}`, { id: "tom" }))
+        return
+      }
+
       // Pausar / retomar reprodução
       if (matchKey(e, getKey("pausar"))) {
         e.preventDefault()
-        if (!useNativePlayer) {
+        if (useNativePlayer) {
+          alternarPausaNative().catch((err) => console.warn("[VideoPlayer] Pausa no mpv falhou:", err))
+        } else {
           const video = videoRef.current
           if (video) {
             if (video.paused) video.play().catch(() => {})
@@ -558,6 +593,16 @@ export default function VideoPlayer({ musica }: { musica: MusicaSimple }) {
         />
       )}
 
+      {/* MP3+G sem mpv: o <video> toca o MP3 e os gráficos CD+G vão num canvas por cima */}
+      {useNativePlayer === false && videoSrc && musica.tipoMidia === "cdg" && rawPath && (
+        <CdgCanvas arquivo={rawPath} mediaRef={videoRef} />
+      )}
+
+      {/* Áudio + letra sem mpv: a letra sincronizada vai por cima do <video> */}
+      {useNativePlayer === false && videoSrc && musica.tipoMidia === "audio" && musica.letra && rawPath && (
+        <LetraOverlay arquivo={rawPath} mediaRef={videoRef} />
+      )}
+
       {/* Fallback de erro */}
       {videoError && (
         <div className="absolute inset-0 flex items-center justify-center z-20 bg-black/90">
@@ -0,0 +1,53 @@
+import { createContext, useContext, useState, useEffect, useCallback, type ReactNode } from "react"
+import { verificarAtivacao, type AtivacaoStatus } from "@/lib/tauri"
+
+interface AtivacaoState extends AtivacaoStatus {
+  modo: string | "loading"
+}
+
+interface AtivacaoContextType {
+  status: AtivacaoState
+  verificar: () => Promise<void>
+}
+
+const AtivacaoContext = createContext<AtivacaoContextType | null>(null)
+
+// Verificada uma vez na abertura do app: as páginas remontam a cada música e
+// não podem voltar ao estado "não ativada" enquanto verificam de novo
+export function AtivacaoProvider({ children }: { children: ReactNode }) {
+  const [status, setStatus] = useState<AtivacaoState>({
+    ativada: false,
+    expirada: false,
+    modo: "loading",
+    chave: null,
+    tipo: "assinatura",
+    diasRestantes: null,
+    horasRestantes: null,
+  })
+
+  const verificar = useCallback(async () => {
+    try {
+      const result = await verificarAtivacao()
+      setStatus(result)
+    } catch (error) {
+      console.error("Erro ao verificar ativacao:", error)
+      setStatus(prev => ({ ...prev, modo: "offline" }))
+    }
+  }, [])
+
+  useEffect(() => {
+    verificar()
+  }, [verificar])
+
+  return (
+    <AtivacaoContext.Provider value={{ status, verificar }}>
+      {children}
+    </AtivacaoContext.Provider>
+  )
+}
+
+export function useAtivacao() {
+  const ctx = useContext(AtivacaoContext)
+  if (!ctx) throw new Error("useAtivacao must be used within AtivacaoProvider")
+  return ctx
+}
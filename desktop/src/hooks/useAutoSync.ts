@@ -1,28 +1,62 @@
 import { useEffect, useRef, useState, useCallback } from "react"
-import { getOfflineStatus, downloadBatch, reindexMusicas, type OfflineStatus } from "@/lib/tauri"
+import {
+  getOfflineStatus,
+  reindexMusicas,
+  retomarDownloads,
+  pausarDownloads,
+  atualizarFilaDownloads,
+  statusDownloads,
+  onDownloadsStatus,
+  onDownloadProgresso,
+  onDownloadErro,
+  type OfflineStatus,
+  type StatusDownloads,
+  type ProgressoFila,
+} from "@/lib/tauri"
 
 interface SyncState {
   isDownloading: boolean
   message: string | null
   offline: OfflineStatus
+  downloads: StatusDownloads | null
+  progresso: ProgressoFila | null
 }
 
 interface UseAutoSyncOptions {
   intervalMinutes?: number
   isActivated?: boolean
+  /** Ativação ainda sendo verificada: não libera nem pausa */
+  ativacaoPendente?: boolean
   blockDownloads?: boolean
 }
 
+function estaBaixando(s: StatusDownloads): boolean {
+  return !s.pausado && (s.baixando.length > 0 || s.pendentes > 0)
+}
+
+/**
+ * Os downloads rodam no gerenciador nativo (Rust), independente da página.
+ * Este hook só libera/pausa conforme ativação e bloqueio, e acompanha o
+ * andamento pelo evento "downloads-status".
+ */
 export function useAutoSync(options: UseAutoSyncOptions = {}) {
-  const { intervalMinutes = 30, isActivated = false, blockDownloads = false } = options
+  const { intervalMinutes = 30, isActivated = false, ativacaoPendente = false, blockDownloads = false } = options
   const blockRef = useRef(blockDownloads)
   blockRef.current = blockDownloads
-  const batchTimeoutRef = useRef<ReturnType<typeof setTimeout> | null>(null)
 
   const [state, setState] = useState<SyncState>({
     isDownloading: false,
     message: null,
-    offline: { totalMusicas: 0, musicasOffline: 0, musicasOnline: 0, storageUsed: 0, storageUsedMB: 0 },
+    offline: {
+      totalMusicas: 0,
+      musicasOffline: 0,
+      musicasOnline: 0,
+      storageUsed: 0,
+      storageUsedMB: 0,
+      catalogoSincronizadoEm: null,
+    },
+    downloads: null,
+    progresso: null,
   })
 
   const checkOfflineStatus = useCallback(async () => {
@@ -45,53 +79,66 @@ export function useAutoSync(options: UseAutoSyncOptions = {}) {
 
   const startBackgroundDownload = useCallback(async () => {
     if (!isActivated || blockRef.current) return
-    if (state.isDownloading) return
-
-    setState(prev => ({ ...prev, isDownloading: true }))
-
     try {
-      const result = await downloadBatch(3)
-      console.log(`[AutoSync] Batch: ${result.downloaded} baixados, ${result.remaining} restantes`)
-      await checkOfflineStatus()
-
-      if (result.remaining > 0 && !blockRef.current) {
-        batchTimeoutRef.current = setTimeout(() => {
-          batchTimeoutRef.current = null
-          startBackgroundDownload()
-        }, 5000)
-      } else {
-        setState(prev => ({ ...prev, isDownloading: false }))
-      }
+      await atualizarFilaDownloads()
+      const status = await retomarDownloads()
+      setState(prev => ({ ...prev, downloads: status, isDownloading: estaBaixando(status) }))
     } catch (e) {
-      console.error("[AutoSync] Erro batch:", e)
-      setState(prev => ({ ...prev, isDownloading: false }))
+      console.error("[AutoSync] Erro ao iniciar downloads:", e)
     }
-  }, [isActivated, state.isDownloading, checkOfflineStatus])
+  }, [isActivated])
 
-  // Block downloads: cancel pending batch
+  // Andamento da fila; cada download concluído atualiza os números offline
+  const concluidosRef = useRef(0)
   useEffect(() => {
-    if (!blockDownloads) return
-    if (batchTimeoutRef.current) {
-      clearTimeout(batchTimeoutRef.current)
-      batchTimeoutRef.current = null
+    const aplicar = (status: StatusDownloads) => {
+      setState(prev => ({
+        ...prev,
+        downloads: status,
+        isDownloading: estaBaixando(status),
+        message: status.semEspaco ?? prev.message,
+      }))
+      if (status.concluidos !== concluidosRef.current) {
+        concluidosRef.current = status.concluidos
+        checkOfflineStatus()
+      }
     }
-    setState(prev => ({ ...prev, isDownloading: false }))
-  }, [blockDownloads])
+    statusDownloads().then(aplicar).catch(() => {})
+    const unlisteners = [
+      onDownloadsStatus(aplicar),
+      onDownloadProgresso(progresso => {
+        const atual = progresso.musicas[0]
+        const message = atual
+          ? `Baixando ${atual.codigo}` +
+            (atual.total ? ` — ${Math.floor((atual.recebidos / atual.total) * 100)}%` : "") +
+            (progresso.musicas.length > 1 ? ` (+${progresso.musicas.length - 1})` : "")
+          : null
+        setState(prev => ({ ...prev, progresso, message }))
+      }),
+      onDownloadErro(e => console.warn(`[AutoSync] ${e.codigo}: ${e.erro} (tentativa ${e.tentativa}/${e.maxTentativas})`)),
+    ]
+    return () => {
+      unlisteners.forEach(u => u.then(fn => fn()))
+    }
+  }, [checkOfflineStatus])
+
+  // Libera ou pausa o gerenciador conforme ativação e bloqueio. Enquanto a
+  // ativação é verificada, o gerenciador continua como está (pausar derruba
+  // os downloads em andamento)
+  useEffect(() => {
+    if (ativacaoPendente && !blockDownloads) return
+    const liberar = isActivated && !blockDownloads
+    const acao = liberar ? retomarDownloads() : pausarDownloads()
+    acao
+      .then(status => setState(prev => ({ ...prev, downloads: status, isDownloading: estaBaixando(status) })))
+      .catch(e => console.error("[AutoSync] Erro no gerenciador de downloads:", e))
+  }, [isActivated, ativacaoPendente, blockDownloads])
 
   // Initial: reindex + check status
   useEffect(() => {
     reindexMusicas().then(() => checkOfflineStatus()).catch(() => checkOfflineStatus())
   }, [checkOfflineStatus])
 
-  // Auto-start background download when there are pending musicas
-  useEffect(() => {
-    if (!isActivated || blockDownloads || state.isDownloading) return
-    if (state.offline.musicasOnline > 0) {
-      const timer = setTimeout(() => startBackgroundDownload(), 10000)
-      return () => clearTimeout(timer)
-    }
-  }, [isActivated, blockDownloads, state.offline.musicasOnline, state.isDownloading, startBackgroundDownload])
-
   // Periodic check
   useEffect(() => {
     const interval = setInterval(checkOfflineStatus, intervalMinutes * 60 * 1000)
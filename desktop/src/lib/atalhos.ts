@@ -47,6 +47,18 @@ export const ATALHOS: AtalhoDefinicao[] = [
     descricao: "Reinicia a música atual do início; na tela inicial, volta ao estado padrão",
     teclaDefault: "+",
   },
+  {
+    id: "tom_subir",
+    acao: "Subir o tom",
+    descricao: "Sobe meio tom na música em reprodução (até +6)",
+    teclaDefault: "PageUp",
+  },
+  {
+    id: "tom_descer",
+    acao: "Descer o tom",
+    descricao: "Desce meio tom na música em reprodução (até -6)",
+    teclaDefault: "PageDown",
+  },
   {
     id: "cancelar",
     acao: "Cancelar / Sair da música",
@@ -106,6 +118,8 @@ export function formatarTecla(key: string): string {
     "ArrowDown": "↓",
     "ArrowLeft": "←",
     "ArrowRight": "→",
+    "PageUp": "Page Up",
+    "PageDown": "Page Down",
     // Teclas numpad armazenadas por código
     "NumpadDecimal": "Del (Num)",
     "NumpadEnter": "Enter (Num)",
@@ -1,4 +1,5 @@
 import { invoke } from "@tauri-apps/api/core"
+import { listen, type UnlistenFn } from "@tauri-apps/api/event"
 
 // Types
 export interface MusicaSimple {
@@ -6,6 +7,14 @@ export interface MusicaSimple {
   artista: string
   titulo: string
   arquivo: string
+  /** Contêiner real do arquivo (mp4, mkv, webm, avi, mp3...) */
+  extensao: string
+  /** "cdg" = MP3+G: `arquivo` é o áudio, os gráficos vêm do .cdg ao lado */
+  tipoMidia: "video" | "audio" | "cdg"
+  /** Letra sincronizada (.ass/.lrc) das faixas só de áudio */
+  letra?: string | null
+  /** Segundos (do catálogo ou medida no arquivo) */
+  duracao?: number | null
 }
 
 export interface AtivacaoStatus {
@@ -29,18 +38,107 @@ export interface ValidacaoResult {
   } | null
 }
 
+export interface AtivacaoEvento {
+  id: string
+  dataEvento: number
+  /** "validar_chave" | "verificar_ativacao" | "remover_ativacao" */
+  acao: string
+  chaveMascarada: string | null
+  /** "online" | "offline" */
+  modo: string
+  /** "ativada" | "expirada" | "invalida" | "conflito_maquina" | "removida" | "erro" */
+  resultado: string
+  detalhe: string | null
+  machineIdLocal: string | null
+  machineIdRemoto: string | null
+  diasRestantes: number | null
+  horasRestantes: number | null
+}
+
 export interface OfflineStatus {
   totalMusicas: number
   musicasOffline: number
   musicasOnline: number
   storageUsed: number
   storageUsedMB: number
+  /** Última atualização do catálogo local (ms); null se nunca sincronizado */
+  catalogoSincronizadoEm: number | null
 }
 
-export interface DownloadResult {
-  downloaded: number
-  remaining: number
-  errors: string[]
+export interface MusicaCatalogoBusca {
+  codigo: string
+  artista: string
+  titulo: string
+  duracao: number | null
+  /** true se o vídeo já está na máquina */
+  baixada: boolean
+}
+
+export interface MusicaCatalogo {
+  id: string
+  codigo: string
+  artista: string
+  titulo: string
+  arquivo: string
+  nomeArquivo: string | null
+  tamanho: number | null
+  duracao: number | null
+  userId: string | null
+  updatedAt: string | null
+  createdAt: string | null
+  sha256: string | null
+}
+
+export interface ConectividadeStatus {
+  /** "desconhecido" | "online" | "offline" */
+  estado: string
+  online: boolean
+  alteradoEm: number | null
+}
+
+export interface CatalogSyncResult {
+  /** false quando a última sincronização foi há menos de um minuto */
+  executada: boolean
+  incremental: boolean
+  recebidas: number
+  novas: number
+  atualizadas: number
+  /** Músicas já baixadas cujos metadados foram corrigidos */
+  locaisAtualizadas: number
+  totalCatalogo: number
+  /** Códigos que saíram do catálogo e foram tirados da biblioteca */
+  removidas: string[]
+  /** Códigos baixados cujo vídeo mudou e será baixado de novo */
+  substituidas: string[]
+  /** Códigos que voltaram ao catálogo e saíram da quarentena */
+  restauradas: string[]
+  expurgadas: number
+}
+
+export interface MusicaQuarentena {
+  codigo: string
+  id: string
+  artista: string
+  titulo: string
+  arquivoOriginal: string
+  arquivoQuarentena: string
+  nomeArquivo: string | null
+  tamanho: number | null
+  duracao: number | null
+  userId: string | null
+  arquivoRemoto: string | null
+  sha256: string | null
+  /** "removida" (tombstone), "ausente" (sumiu numa sincronização completa) ou "corrompida" (conferência) */
+  motivo: string
+  quarentenaEm: number
+  expiraEm: number
+}
+
+export interface ProgressoCatalogo {
+  completa: boolean
+  pagina: number
+  recebidas: number
+  total: number | null
 }
 
 export interface ReindexResult {
@@ -66,6 +164,33 @@ export async function getAllMusicasCount(): Promise<number> {
   return invoke("get_all_musicas_count")
 }
 
+/** Busca no catálogo completo, inclusive músicas não baixadas (funciona offline). */
+export async function buscarCatalogo(query: string): Promise<MusicaCatalogoBusca[]> {
+  return invoke("buscar_catalogo", { query })
+}
+
+export async function listarNaoBaixadas(limite?: number, offset?: number): Promise<MusicaCatalogo[]> {
+  return invoke("listar_nao_baixadas", { limite, offset })
+}
+
+export interface InfoMidia {
+  /** Segundos */
+  duracao: number | null
+  largura: number | null
+  altura: number | null
+  codecVideo: string | null
+  codecAudio: string | null
+  canaisAudio: number | null
+  /** Loudness integrado (EBU R128) */
+  loudnessLufs: number | null
+  picoDbtp: number | null
+}
+
+/** Medidas do arquivo da música; null se não baixada ou ainda não sondada. */
+export async function getInfoMidia(codigo: string): Promise<InfoMidia | null> {
+  return invoke("get_info_midia", { codigo })
+}
+
 export async function salvarHistorico(codigo: string): Promise<void> {
   return invoke("salvar_historico", { codigo })
 }
@@ -82,12 +207,333 @@ export async function removerAtivacao(): Promise<void> {
   return invoke("remover_ativacao")
 }
 
+/** Eventos de validação de chave, do mais recente para o mais antigo. */
+export async function listarEventosAtivacao(limite?: number): Promise<AtivacaoEvento[]> {
+  return invoke("listar_eventos_ativacao", { limite })
+}
+
+/** Gera o pacote de suporte (JSON) e retorna o caminho do arquivo. */
+export async function exportarPacoteSuporte(): Promise<string> {
+  return invoke("exportar_pacote_suporte")
+}
+
 export async function getOfflineStatus(): Promise<OfflineStatus> {
   return invoke("get_offline_status")
 }
 
-export async function downloadBatch(size?: number): Promise<DownloadResult> {
-  return invoke("download_batch", { size: size ?? 3 })
+/**
+ * Sincroniza o catálogo remoto agora: só o que mudou desde a última vez, ou
+ * tudo com `completa = true`. Andamento em `onCatalogoProgresso`.
+ */
+export async function sincronizarCatalogo(completa?: boolean): Promise<CatalogSyncResult> {
+  return invoke("sincronizar_catalogo", { completa })
+}
+
+export async function onCatalogoProgresso(cb: (p: ProgressoCatalogo) => void): Promise<UnlistenFn> {
+  return listen<ProgressoCatalogo>("catalogo-progresso", (e) => cb(e.payload))
+}
+
+export async function listarQuarentena(): Promise<MusicaQuarentena[]> {
+  return invoke("listar_quarentena")
+}
+
+/** Sem argumento, só lê o prazo atual (em dias). */
+export async function configurarQuarentena(dias?: number): Promise<number> {
+  return invoke("configurar_quarentena", { dias })
+}
+
+export interface VerificacaoResult {
+  verificadas: number
+  /** Baixadas antes do hash ser guardado; não dá para conferir */
+  semHash: number
+  /** Códigos que não conferem; ficam marcados para novo download */
+  corrompidas: string[]
+}
+
+/** Recalcula o SHA-256 dos vídeos baixados (todos, ou só `codigos`). */
+export async function reverificarMusicas(codigos?: string[]): Promise<VerificacaoResult> {
+  return invoke("reverificar_musicas", { codigos })
+}
+
+export interface ProblemaArquivo {
+  codigo: string
+  arquivo: string
+  tamanho: number
+  /** Tamanho registrado no download ou, sem ele, o do catálogo */
+  esperado: number | null
+}
+
+export interface ResultadoReparo {
+  /** Códigos postos na fila de downloads */
+  reenfileiradas: string[]
+  reindexadas: string[]
+  /** Corrompidos postos em quarentena ou apagados */
+  descartadas: string[]
+  /** Órfãos fora do catálogo (arquivos mantidos) */
+  ignoradas: string[]
+  erros: string[]
+}
+
+export interface RelatorioBiblioteca {
+  registros: number
+  arquivos: number
+  semArquivo: string[]
+  orfaos: ProblemaArquivo[]
+  vazios: ProblemaArquivo[]
+  truncados: ProblemaArquivo[]
+  divergentes: ProblemaArquivo[]
+  reparo: ResultadoReparo | null
+}
+
+export interface ProgressoBiblioteca {
+  /** "registros" | "arquivos" | "reparo" */
+  etapa: string
+  atual: number
+  total: number
+}
+
+/**
+ * Confere a pasta de vídeos contra o banco. Com `reparar`, baixa de novo o
+ * que falta, indexa órfãos e descarta corrompidos. Andamento em `onBibliotecaProgresso`.
+ */
+export async function verificarBiblioteca(opts?: {
+  reparar?: boolean
+  descarte?: "quarentena" | "apagar"
+}): Promise<RelatorioBiblioteca> {
+  return invoke("verificar_biblioteca", { ...opts })
+}
+
+export async function onBibliotecaProgresso(cb: (p: ProgressoBiblioteca) => void): Promise<UnlistenFn> {
+  return listen<ProgressoBiblioteca>("biblioteca-progresso", (e) => cb(e.payload))
+}
+
+export interface ProgressoPacote {
+  /** "exportando" | "verificando" | "copiando" */
+  etapa: string
+  atual: number
+  total: number
+  codigo: string | null
+}
+
+export interface ResultadoExportacao {
+  pasta: string
+  musicas: number
+  bytes: number
+  chavePublica: string
+  erros: string[]
+}
+
+export interface ResultadoImportacao {
+  versaoCatalogo: string | null
+  chavePublica: string
+  /** Assinante está entre as chaves confiáveis desta máquina */
+  confiavel: boolean
+  importadas: string[]
+  /** Já estavam na biblioteca com o mesmo vídeo */
+  duplicadas: string[]
+  /** O catálogo local tem outra versão do vídeo */
+  desatualizadas: string[]
+  /** Assinante não confiável e música fora do catálogo local */
+  rejeitadas: string[]
+  erros: string[]
+}
+
+export interface ChavesPacote {
+  /** Chave desta máquina, para cadastrar nas que vão importar seus pacotes */
+  chavePublica: string
+  confiaveis: string[]
+}
+
+/** Exporta músicas baixadas para uma pasta (ex.: pendrive). Andamento em `onPacoteProgresso`. */
+export async function exportarPacoteOffline(destino: string, codigos?: string[]): Promise<ResultadoExportacao> {
+  return invoke("exportar_pacote_offline", { destino, codigos })
+}
+
+/** Importa um pacote offline de qualquer pasta montada, conferindo assinatura e hashes. */
+export async function importarPacoteOffline(origem: string): Promise<ResultadoImportacao> {
+  return invoke("importar_pacote_offline", { origem })
+}
+
+export async function chavesPacoteOffline(): Promise<ChavesPacote> {
+  return invoke("chaves_pacote_offline")
+}
+
+export async function confiarChavePacote(chave: string, confiar: boolean): Promise<ChavesPacote> {
+  return invoke("confiar_chave_pacote", { chave, confiar })
+}
+
+export async function onPacoteProgresso(cb: (p: ProgressoPacote) => void): Promise<UnlistenFn> {
+  return listen<ProgressoPacote>("pacote-progresso", (e) => cb(e.payload))
+}
+
+export interface StatusDownloads {
+  pausado: boolean
+  paralelos: number
+  /** 0 = sem limite */
+  limiteKbps: number
+  /** Códigos sendo baixados agora */
+  baixando: string[]
+  pendentes: number
+  erros: number
+  cancelados: number
+  /** Concluídos desde que o app abriu */
+  concluidos: number
+  /** Fila parada por falta de espaço (motivo) */
+  semEspaco: string | null
+}
+
+export interface ProgressoMusica {
+  codigo: string
+  recebidos: number
+  total: number | null
+  /** bytes/s */
+  velocidade: number
+  etaSegundos: number | null
+}
+
+export interface ProgressoFila {
+  musicas: ProgressoMusica[]
+  pendentes: number
+  erros: number
+  concluidos: number
+  /** Soma das velocidades, em bytes/s */
+  velocidade: number
+}
+
+export interface ErroDownload {
+  codigo: string
+  erro: string
+  tentativa: number
+  maxTentativas: number
+  /** ms; null quando as tentativas se esgotaram */
+  proximaTentativa: number | null
+}
+
+export async function statusDownloads(): Promise<StatusDownloads> {
+  return invoke("status_downloads")
+}
+
+export async function retomarDownloads(): Promise<StatusDownloads> {
+  return invoke("retomar_downloads")
+}
+
+export async function pausarDownloads(): Promise<StatusDownloads> {
+  return invoke("pausar_downloads")
+}
+
+/** Sem `codigo`, cancela a fila inteira. */
+export async function cancelarDownload(codigo?: string): Promise<StatusDownloads> {
+  return invoke("cancelar_download", { codigo })
+}
+
+export async function reenfileirarDownload(codigo?: string): Promise<StatusDownloads> {
+  return invoke("reenfileirar_download", { codigo })
+}
+
+/** Põe a música na frente da fila de downloads. */
+export async function priorizarDownload(codigo: string): Promise<StatusDownloads> {
+  return invoke("priorizar_download", { codigo })
+}
+
+export async function configurarDownloads(opts: { paralelos?: number; limiteKbps?: number }): Promise<StatusDownloads> {
+  return invoke("configurar_downloads", opts)
+}
+
+export interface StatusArmazenamento {
+  /** Bytes ocupados pela biblioteca */
+  usado: number
+  /** 0 = sem limite */
+  limiteMb: number
+  /** Espaço que sempre fica livre no disco */
+  reservaMb: number
+  livreDisco: number | null
+  /** "menos_tocadas" | "sem_tocar" */
+  politica: string
+  fixadas: string[]
+  /** Apagadas para liberar espaço; só voltam se pedidas */
+  liberadas: number
+}
+
+export async function statusArmazenamento(): Promise<StatusArmazenamento> {
+  return invoke("status_armazenamento")
+}
+
+/** Diminuir o limite apaga músicas na hora, pela política escolhida. */
+export async function configurarArmazenamento(opts: {
+  limiteMb?: number
+  reservaMb?: number
+  politica?: "menos_tocadas" | "sem_tocar"
+}): Promise<StatusArmazenamento> {
+  return invoke("configurar_armazenamento", opts)
+}
+
+/** Protege (ou não) a música da liberação de espaço. Retorna as fixadas. */
+export async function fixarMusica(codigo: string, fixada: boolean): Promise<string[]> {
+  return invoke("fixar_musica", { codigo, fixada })
+}
+
+// --- Normalização de volume ---
+
+export interface StatusNormalizacao {
+  ativa: boolean
+  /** Loudness que todas as músicas passam a ter ao tocar */
+  alvoLufs: number
+  /** Músicas com loudness já medido */
+  analisadas: number
+  /** Ainda não sondadas (medida em segundo plano) */
+  pendentes: number
+}
+
+export async function statusNormalizacao(): Promise<StatusNormalizacao> {
+  return invoke("status_normalizacao")
+}
+
+/** `alvoLufs` de -30 a -8 (padrão -16); vale a partir da próxima música. */
+export async function configurarNormalizacao(opts: { ativa?: boolean; alvoLufs?: number }): Promise<StatusNormalizacao> {
+  return invoke("configurar_normalizacao", opts)
+}
+
+export interface ParRedeLocal {
+  machineId: string
+  nome: string
+  endereco: string
+  musicas: number
+  /** Segundos desde o último anúncio */
+  vistoHa: number
+}
+
+export interface StatusRedeLocal {
+  ativa: boolean
+  /** false até a máquina ser ativada online com uma chave de conta */
+  contaVinculada: boolean
+  porta: number | null
+  pares: ParRedeLocal[]
+}
+
+export async function statusRedeLocal(): Promise<StatusRedeLocal> {
+  return invoke("status_rede_local")
+}
+
+/** Liga/desliga a troca de vídeos com as máquinas da mesma conta. */
+export async function configurarRedeLocal(ativa: boolean): Promise<StatusRedeLocal> {
+  return invoke("configurar_rede_local", { ativa })
+}
+
+export async function atualizarFilaDownloads(): Promise<number> {
+  return invoke("atualizar_fila_downloads")
+}
+
+export async function onDownloadsStatus(cb: (s: StatusDownloads) => void): Promise<UnlistenFn> {
+  return listen<StatusDownloads>("downloads-status", (e) => cb(e.payload))
+}
+
+/** No máximo a cada 500 ms enquanto houver download ativo. */
+export async function onDownloadProgresso(cb: (p: ProgressoFila) => void): Promise<UnlistenFn> {
+  return listen<ProgressoFila>("download-progresso", (e) => cb(e.payload))
+}
+
+export async function onDownloadErro(cb: (e: ErroDownload) => void): Promise<UnlistenFn> {
+  return listen<ErroDownload>("download-erro", (e) => cb(e.payload))
 }
 
 export async function reindexMusicas(): Promise<ReindexResult> {
@@ -98,6 +544,62 @@ export async function getVideoPath(codigo: string): Promise<string> {
   return invoke("get_video_path", { codigo })
 }
 
+export interface QuadroCdg {
+  largura: number
+  altura: number
+  versao: number
+  /** 16 cores "#rrggbb" */
+  paleta: string[]
+  /** Índices da paleta, 2 pixels por byte (esquerdo no nibble alto), base64 */
+  pixels: string
+}
+
+/** Tela CD+G no instante do áudio; null se não mudou desde `versao`. */
+export async function cdgQuadro(arquivo: string, posicaoMs: number, versao?: number): Promise<QuadroCdg | null> {
+  return invoke("cdg_quadro", { arquivo, posicaoMs: Math.max(0, Math.floor(posicaoMs)), versao })
+}
+
+export interface Palavra {
+  inicioMs: number
+  fimMs: number
+  texto: string
+}
+
+export interface Linha {
+  inicioMs: number
+  fimMs: number
+  texto: string
+  /** Vazio quando o arquivo não marca o tempo de cada palavra */
+  palavras: Palavra[]
+}
+
+export interface Letra {
+  titulo: string | null
+  artista: string | null
+  linhas: Linha[]
+}
+
+/** Miniatura (JPEG) da música; null se não baixada, só áudio ou ainda não gerada. */
+export async function getMiniatura(codigo: string): Promise<string | null> {
+  return invoke("get_miniatura", { codigo })
+}
+
+/** Letra (.ass/.lrc) que acompanha o áudio `arquivo`; null se não houver. */
+export async function getLetra(arquivo: string): Promise<Letra | null> {
+  return invoke("get_letra", { arquivo })
+}
+
+// --- Conectividade ---
+
+export async function getConectividade(): Promise<ConectividadeStatus> {
+  return invoke("get_conectividade")
+}
+
+/** Chamado sempre que a rede muda entre online e offline. */
+export async function onConectividadeAlterada(cb: (status: ConectividadeStatus) => void): Promise<UnlistenFn> {
+  return listen<ConectividadeStatus>("conectividade-alterada", (e) => cb(e.payload))
+}
+
 // --- Player nativo (mpv) ---
 
 /** Retorna true se o mpv está disponível no sistema (bundled ou PATH). */
@@ -105,7 +607,7 @@ export async function nativePlayerAvailable(): Promise<boolean> {
   return invoke("native_player_available")
 }
 
-/** Inicia reprodução nativa via mpv. O path é o caminho local do arquivo de vídeo. */
+/** Inicia reprodução nativa via mpv. O path é o caminho local do arquivo (vídeo ou áudio). */
 export async function playNative(path: string): Promise<void> {
   return invoke("play_native", { path })
 }
@@ -119,3 +621,63 @@ export async function stopNative(): Promise<void> {
 export async function nativePlayerEnded(): Promise<boolean> {
   return invoke("native_player_ended")
 }
+
+// Controle do mpv em reprodução (IPC JSON)
+
+export interface EstadoNative {
+  /** Segundos; null antes do arquivo abrir */
+  posicao: number | null
+  duracao: number | null
+  pausado: boolean
+  /** 0–130 (100 = original) */
+  volume: number
+  mudo: boolean
+}
+
+export async function pausarNative(): Promise<void> {
+  return invoke("pausar_native")
+}
+
+export async function retomarNative(): Promise<void> {
+  return invoke("retomar_native")
+}
+
+/** Pausa ou retoma conforme o estado atual do mpv. */
+export async function alternarPausaNative(): Promise<void> {
+  return invoke("alternar_pausa_native")
+}
+
+/** Vai para `posicao` segundos; com `relativo`, anda `posicao` segundos. */
+export async function buscarNative(posicao: number, relativo?: boolean): Promise<void> {
+  return invoke("buscar_native", { posicao, relativo })
+}
+
+export async function reiniciarNative(): Promise<void> {
+  return invoke("reiniciar_native")
+}
+
+export async function volumeNative(volume: number): Promise<void> {
+  return invoke("volume_native", { volume })
+}
+
+export async function mudoNative(mudo: boolean): Promise<void> {
+  return invoke("mudo_native", { mudo })
+}
+
+export async function estadoNative(): Promise<EstadoNative> {
+  return invoke("estado_native")
+}
+
+/** Tom em semitons (-6 a +6), sem mudar o andamento; só com mpv. Retorna o aplicado. */
+export async function definirTomNative(semitons: number): Promise<number> {
+  return invoke("definir_tom_native", { semitons })
+}
+
+export async function resetarTomNative(): Promise<void> {
+  return invoke("resetar_tom_native")
+}
+
+/** Tela de reprodução aberta/fechada: tarefas de fundo pesadas esperam. */
+export async function reproducaoAtiva(ativa: boolean): Promise<void> {
+  return invoke("reproducao_ativa", { ativa })
+}
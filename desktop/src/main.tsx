@@ -3,16 +3,19 @@ import ReactDOM from "react-dom/client"
 import { BrowserRouter } from "react-router-dom"
 import App from "./App"
 import { FilaProximaProvider } from "./contexts/fila-proxima"
+import { AtivacaoProvider } from "./contexts/ativacao"
 import { Toaster } from "./components/ui/sonner"
 import "./styles/globals.css"
 
 ReactDOM.createRoot(document.getElementById("root")!).render(
   <React.StrictMode>
     <BrowserRouter>
-      <FilaProximaProvider>
-        <App />
-        <Toaster />
-      </FilaProximaProvider>
+      <AtivacaoProvider>
+        <FilaProximaProvider>
+          <App />
+          <Toaster />
+        </FilaProximaProvider>
+      </AtivacaoProvider>
     </BrowserRouter>
   </React.StrictMode>
 )
@@ -4,7 +4,7 @@ import { Spotlight } from "@/components/ui/spotlight-new"
 import { useAutoSync } from "@/hooks/useAutoSync"
 import { AtivacaoDialog } from "@/components/ativacao-dialog"
 import { UnifiedSearch } from "@/components/unified-search"
-import { useAtivacao } from "@/hooks/use-ativacao"
+import { useAtivacao } from "@/contexts/ativacao"
 import { ConfiguracoesDialog } from "@/components/configuracoes-dialog"
 import { QrCodesHome } from "@/components/qr-code"
 import { toast } from "sonner"
@@ -74,7 +74,12 @@ function HomePageContent() {
     offline,
     isDownloading,
     message: syncMessage
-  } = useAutoSync({ intervalMinutes: 30, isActivated, blockDownloads })
+  } = useAutoSync({
+    intervalMinutes: 30,
+    isActivated,
+    ativacaoPendente: ativacaoStatus.modo === "loading",
+    blockDownloads,
+  })
 
   // Listener para atalho de sincronização (tecla *)
   useEffect(() => {
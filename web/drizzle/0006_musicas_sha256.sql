@@ -0,0 +1,6 @@
+-- Migration: hash SHA-256 do vídeo de cada música
+-- O app desktop confere tamanho + hash depois de baixar; músicas antigas ficam
+-- com NULL (só o tamanho é conferido).
+-- Executar no Supabase SQL Editor ou via drizzle-kit
+
+ALTER TABLE musicas ADD COLUMN IF NOT EXISTS sha256 text;
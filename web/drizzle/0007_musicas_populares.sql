@@ -0,0 +1,21 @@
+-- Migration: ranking global de músicas mais tocadas
+-- O app desktop chama via PostgREST (POST /rest/v1/rpc/musicas_populares) para
+-- baixar primeiro as músicas mais pedidas em todas as máquinas.
+-- Executar no Supabase SQL Editor ou via drizzle-kit
+
+CREATE OR REPLACE FUNCTION musicas_populares(limite integer DEFAULT 500)
+RETURNS TABLE (musica_id uuid, total bigint)
+LANGUAGE sql
+STABLE
+SECURITY DEFINER
+SET search_path = public
+AS $$
+  SELECT h.musica_id, COUNT(*) AS total
+  FROM historico h
+  WHERE h.data_execucao > now() - interval '90 days'
+  GROUP BY h.musica_id
+  ORDER BY total DESC
+  LIMIT LEAST(GREATEST(limite, 1), 5000)
+$$;
+
+GRANT EXECUTE ON FUNCTION musicas_populares(integer) TO anon, authenticated;
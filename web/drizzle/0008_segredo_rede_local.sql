@@ -0,0 +1,47 @@
+-- Migration: segredo da rede local por conta
+-- As máquinas da mesma conta se reconhecem na rede local (anúncio UDP e
+-- requisições HTTP assinadas com HMAC) por este segredo. Ele só é entregue a
+-- quem apresenta uma chave ativa já vinculada ao próprio machine_id; a tabela
+-- não é legível pela API.
+-- Executar no Supabase SQL Editor ou via drizzle-kit
+
+CREATE TABLE IF NOT EXISTS segredos_rede_local (
+  user_id text PRIMARY KEY REFERENCES users(id) ON DELETE CASCADE,
+  segredo text NOT NULL,
+  created_at timestamp NOT NULL DEFAULT now()
+);
+
+ALTER TABLE segredos_rede_local ENABLE ROW LEVEL SECURITY;
+REVOKE ALL ON segredos_rede_local FROM anon, authenticated;
+
+CREATE OR REPLACE FUNCTION segredo_rede_local(p_chave text, p_machine_id text)
+RETURNS text
+LANGUAGE plpgsql
+VOLATILE
+SECURITY DEFINER
+SET search_path = public, extensions
+AS $$
+DECLARE
+  conta text;
+  resultado text;
+BEGIN
+  SELECT c.user_id INTO conta
+  FROM chaves_ativacao c
+  WHERE c.chave = p_chave
+    AND c.machine_id = p_machine_id
+    AND c.status = 'ativa'
+    AND c.user_id IS NOT NULL;
+  IF conta IS NULL THEN
+    RETURN NULL;
+  END IF;
+
+  INSERT INTO segredos_rede_local (user_id, segredo)
+  VALUES (conta, encode(gen_random_bytes(32), 'hex'))
+  ON CONFLICT (user_id) DO NOTHING;
+
+  SELECT s.segredo INTO resultado FROM segredos_rede_local s WHERE s.user_id = conta;
+  RETURN resultado;
+END;
+$$;
+
+GRANT EXECUTE ON FUNCTION segredo_rede_local(text, text) TO anon, authenticated;
@@ -0,0 +1,40 @@
+-- Migration: tom do histórico e envio do histórico pelo desktop
+-- `tom` são os semitons em relação ao original (-6..+6) em que a música foi
+-- cantada. O app desktop envia o que tocou via PostgREST
+-- (POST /rest/v1/rpc/registrar_historico), identificado pela chave ativa já
+-- vinculada ao próprio machine_id, como em 0008_segredo_rede_local.sql.
+-- Executar no Supabase SQL Editor ou via drizzle-kit
+
+ALTER TABLE historico ADD COLUMN IF NOT EXISTS tom integer NOT NULL DEFAULT 0;
+
+CREATE OR REPLACE FUNCTION registrar_historico(p_chave text, p_machine_id text, p_itens jsonb)
+RETURNS integer
+LANGUAGE plpgsql
+VOLATILE
+SECURITY DEFINER
+SET search_path = public
+AS $$
+DECLARE
+  conta text;
+  gravados integer;
+BEGIN
+  SELECT COALESCE(c.user_id, c.criado_por) INTO conta
+  FROM chaves_ativacao c
+  WHERE c.chave = p_chave
+    AND c.machine_id = p_machine_id
+    AND c.status = 'ativa';
+  IF conta IS NULL THEN
+    RETURN NULL;
+  END IF;
+
+  -- Músicas fora do catálogo ficam de fora (não há musica_id)
+  INSERT INTO historico (user_id, musica_id, codigo, data_execucao, tom)
+  SELECT conta, m.id, i.codigo, to_timestamp(i.data_execucao / 1000.0), LEAST(GREATEST(i.tom, -6), 6)
+  FROM jsonb_to_recordset(p_itens) AS i(codigo text, data_execucao bigint, tom integer)
+  JOIN musicas m ON m.codigo = i.codigo;
+  GET DIAGNOSTICS gravados = ROW_COUNT;
+  RETURN gravados;
+END;
+$$;
+
+GRANT EXECUTE ON FUNCTION registrar_historico(text, text, jsonb) TO anon, authenticated;
@@ -290,6 +290,11 @@ export default function HistoricoPage() {
                                   <code className="text-xs bg-muted px-2 py-1 rounded">
                                     {entry.codigo}
                                   </code>
+                                  {entry.tom !== 0 && (
+                                    <span className="ml-2 text-xs text-muted-foreground" title="Tom em que foi cantada">
+                                      Tom {entry.tom > 0 ? `+${entry.tom}` : entry.tom}
+                                    </span>
+                                  )}
                                 </TableCell>
                                 <TableCell>
                                   <div className="text-sm">{date}</div>
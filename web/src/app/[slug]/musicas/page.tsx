@@ -170,6 +170,12 @@ function parseFilename(filename: string): { codigo: string; artista: string; tit
   return { codigo: nameWithoutExt.trim(), artista: "Desconhecido", titulo: nameWithoutExt.trim() }
 }
 
+// SHA-256 (hex) do arquivo, gravado no catálogo para o desktop conferir o download
+async function sha256Hex(file: File): Promise<string> {
+  const digest = await crypto.subtle.digest("SHA-256", await file.arrayBuffer())
+  return Array.from(new Uint8Array(digest), (b) => b.toString(16).padStart(2, "0")).join("")
+}
+
 export default function MusicasPage() {
   const router = useRouter()
   const params = useParams()
@@ -584,7 +590,8 @@ export default function MusicasPage() {
           xhr.send(file)
         })
 
-        // 3. Confirmar no banco de dados
+        // 3. Confirmar no banco de dados (com o hash que o desktop confere no download)
+        const sha256 = await sha256Hex(file)
         const confirmRes = await fetch("/api/upload/confirm", {
           method: "POST",
           headers: { "Content-Type": "application/json" },
@@ -594,6 +601,7 @@ export default function MusicasPage() {
             artista: artista.trim(),
             titulo: titulo.trim(),
             size: file.size,
+            sha256,
           }),
         })
         const confirmData = await confirmRes.json()
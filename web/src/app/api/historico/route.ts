@@ -55,6 +55,7 @@ export async function GET(request: NextRequest) {
           musicaId: historico.musicaId,
           codigo: historico.codigo,
           dataExecucao: historico.dataExecucao,
+          tom: historico.tom,
           musica: {
             id: musicas.id,
             titulo: musicas.titulo,
@@ -112,12 +112,14 @@ export async function POST(request: NextRequest) {
             dataExecucaoRaw != null
               ? new Date(typeof dataExecucaoRaw === "number" ? dataExecucaoRaw : dataExecucaoRaw)
               : new Date()
+          const tom = Math.max(-6, Math.min(6, Math.trunc(Number(item.tom) || 0)))
 
           await db.insert(historico).values({
             userId: historicoUserId,
             musicaId: musica.id,
             codigo,
             dataExecucao,
+            tom,
           })
         }
       }
@@ -21,18 +21,23 @@ export async function POST(request: NextRequest) {
     }
 
     const body = await request.json()
-    const { path, codigo, artista, titulo, size } = body as {
+    const { path, codigo, artista, titulo, size, sha256 } = body as {
       path?: string
       codigo?: string
       artista?: string
       titulo?: string
       size?: number
+      sha256?: string
     }
 
     if (!path || !codigo?.trim()) {
       return NextResponse.json({ error: "path e codigo são obrigatórios" }, { status: 400 })
     }
 
+    if (sha256 !== undefined && !/^[0-9a-f]{64}$/i.test(sha256)) {
+      return NextResponse.json({ error: "sha256 inválido" }, { status: 400 })
+    }
+
     // Dupla verificação de código (race condition)
     const existing = await db
       .select({ id: musicas.id })
@@ -61,6 +66,7 @@ export async function POST(request: NextRequest) {
         arquivo: urlData.publicUrl,
         nomeArquivo: filename,
         tamanho: size ?? 0,
+        sha256: sha256?.toLowerCase() ?? null,
         userId: currentUser.userId,
       })
       .returning()
@@ -3,6 +3,7 @@ import { getCurrentUser } from "@/lib/auth"
 import { uploadToStorage } from "@/lib/supabase-storage"
 import { db, musicas } from "@/lib/db"
 import { eq } from "drizzle-orm"
+import { createHash } from "crypto"
 
 // Função para extrair artista e título do nome do arquivo
 // Formato esperado: "codigo - artista - titulo.mp4" ou "codigo.mp4"
@@ -112,6 +113,7 @@ export async function POST(request: NextRequest) {
         arquivo: uploadResult.url,
         nomeArquivo: file.name,
         tamanho: uploadResult.size,
+        sha256: createHash("sha256").update(buffer).digest("hex"),
         userId: currentUser.userId,
       })
       .returning()
@@ -8,6 +8,8 @@ export interface HistoryEntry {
   musicaId: string
   codigo: string
   dataExecucao: string
+  /** Semitons em relação ao original (0 = tom original) */
+  tom: number
   musica: {
     id: string
     titulo: string
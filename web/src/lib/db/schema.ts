@@ -74,6 +74,7 @@ export const musicas = pgTable("musicas", {
   arquivo: text("arquivo").notNull(), // caminho do arquivo ou URL na nuvem
   nomeArquivo: text("nome_arquivo"), // nome original do arquivo
   tamanho: integer("tamanho"), // tamanho em bytes
+  sha256: text("sha256"), // hash do arquivo (hex), conferido pelo desktop após o download
   duracao: integer("duracao"), // duração em segundos
   userId: text("user_id").references(() => users.id).notNull(), // usuário que fez upload
   createdAt: timestamp("created_at").notNull().defaultNow(),
@@ -87,6 +88,7 @@ export const historico = pgTable("historico", {
   musicaId: uuid("musica_id").references(() => musicas.id).notNull(),
   codigo: text("codigo").notNull(), // código da música para referência rápida
   dataExecucao: timestamp("data_execucao").notNull().defaultNow(),
+  tom: integer("tom").notNull().default(0), // semitons em relação ao original (-6..+6)
 })
 
 // Tabela de Estatísticas (para cache de dados agregados)